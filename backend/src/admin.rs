@@ -0,0 +1,449 @@
+use geo::Point;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::oneshot;
+use utoipa::{PartialSchema, ToSchema};
+
+use crate::tick::server_time_ms;
+use crate::{
+    AttractionPoint, DataDir, PhysicsHandle, SavedView, Simulation, SimulationConfig, ViewStore,
+    WorldPreset,
+};
+
+/// Bumped whenever a command changes in a way an existing admin panel would trip over
+pub const ADMIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Most sharks a single `spawn` or `load_scenario` may add, so a typo doesn't
+/// bury the physics thread
+pub const MAX_SPAWN: usize = 1000;
+
+/// Everything an operator can do to a running simulation.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum AdminCommand {
+    /// Describes every command and its arguments
+    Help,
+    /// Stops the sharks moving, frames keep being sent
+    Pause,
+    /// Continues after `pause`
+    Resume,
+    /// Overrides steering parameters, keys and values as in the simulation
+    /// file, e.g. `{"cohesion_strength": 0.2}`. Replies with the full new set.
+    SetParams {
+        #[schema(value_type = Object)]
+        params: Map<String, Value>,
+    },
+    /// Overrides a single steering parameter, e.g.
+    /// `{"cmd": "set_param", "name": "cohesion_strength", "value": 0.2}`.
+    /// Replies with the full new set.
+    SetParam {
+        name: String,
+        #[schema(value_type = Object)]
+        value: Value,
+    },
+    /// Re-reads the simulation file, undoing `set_params`
+    ReloadParams,
+    /// Replies with the steering parameters in use
+    GetParams,
+    /// Adds an attraction point owned by the request's `session`, which counts
+    /// towards its quota and expires. Replies with its `id`. Goals that stay
+    /// for good go in the goals file or through `POST /goals`.
+    AddGoal { lon: f64, lat: f64 },
+    /// Removes the attraction point at `index` of the frame's `goals`, whoever
+    /// added it, so only through `POST /admin`. Clients remove their own
+    /// with `remove_my_goal`.
+    RemoveGoal { index: usize },
+    /// Lists the goals of the request's `session`
+    ListMyGoals,
+    /// Removes goal `id` of the request's `session`
+    RemoveMyGoal { id: u64 },
+    /// Releases `count` sharks at a point in water
+    Spawn { lon: f64, lat: f64, count: usize },
+    /// Swaps the land for a world preset, see `world` in the config file, and
+    /// scatters `sharks` new sharks over it, at most `MAX_SPAWN`. Goals are kept.
+    /// Fails for a preset `WorldPreset::validate` refuses and for a world
+    /// without water.
+    LoadScenario {
+        #[schema(inline)]
+        world: WorldPreset,
+        sharks: usize,
+    },
+    /// Saves a view for later, owned by the request's `session` if any.
+    /// Replies with the short `code` to restore it by.
+    SaveView { view: SavedView },
+    /// Replies with the view saved under `code`
+    LoadView { code: String },
+    /// Lists the views saved by the request's `session` with their codes
+    ListMyViews,
+    /// Clock sync: replies with the client's `t0` and `t1`, the server time in
+    /// ms when the request arrived, on the same clock as `server_time_ms` in
+    /// frames. Half the round trip added to `t1` estimates the server clock.
+    TimeSync { t0: f64 },
+    /// Replay only: jumps to the first recorded frame at or after `tick`
+    ReplaySeek { tick: u64 },
+    /// Replay only: recorded frames per tick, e.g. 4 for four times as fast,
+    /// negative to play backwards
+    ReplaySpeed { speed: f64 },
+    /// Overrides fault injection settings, keys and values as in `chaos` in
+    /// the config file, e.g. `{"drop_frame_chance": 0.1}`. Replies with the
+    /// full new set. Only with the `chaos` feature.
+    #[cfg(feature = "chaos")]
+    SetChaos {
+        #[schema(value_type = Object)]
+        chaos: Map<String, Value>,
+    },
+    /// Replies with the fault injection settings in use. Only with the `chaos` feature.
+    #[cfg(feature = "chaos")]
+    GetChaos,
+}
+
+impl AdminCommand {
+    /// Whether a WebSocket viewer may send it: the commands that only touch
+    /// the sender's own goals and views, clock sync, and `set_param` for
+    /// tuning during demos. The rest only go through `POST /admin`.
+    pub fn for_clients(&self) -> bool {
+        matches!(
+            self,
+            AdminCommand::AddGoal { .. }
+                | AdminCommand::ListMyGoals
+                | AdminCommand::RemoveMyGoal { .. }
+                | AdminCommand::SaveView { .. }
+                | AdminCommand::LoadView { .. }
+                | AdminCommand::ListMyViews
+                | AdminCommand::TimeSync { .. }
+                | AdminCommand::SetParam { .. }
+        )
+    }
+}
+
+/// A command as sent by a client, e.g. `{"version": 1, "command": "pause"}`.
+/// `version` may be left out, otherwise it has to be `ADMIN_PROTOCOL_VERSION`.
+#[derive(Debug, Deserialize)]
+pub struct AdminRequest {
+    pub version: Option<u32>,
+    /// Identifies the client for goal ownership, any string it keeps using
+    pub session: Option<String>,
+    #[serde(flatten)]
+    pub command: AdminCommand,
+}
+
+impl AdminRequest {
+    /// Parses a request, also taking `cmd` as shorthand for `command`.
+    pub fn from_json(text: &str) -> serde_json::Result<Self> {
+        let mut value: Value = serde_json::from_str(text)?;
+        if let Some(object) = value.as_object_mut()
+            && !object.contains_key("command")
+            && let Some(command) = object.remove("cmd")
+        {
+            object.insert("command".to_string(), command);
+        }
+        serde_json::from_value(value)
+    }
+}
+
+/// Outcome of an `AdminRequest`, `result` on success and `error` otherwise.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminReply {
+    pub version: u32,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AdminReply {
+    pub fn success(result: Value) -> Self {
+        Self {
+            version: ADMIN_PROTOCOL_VERSION,
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(error: String) -> Self {
+        Self {
+            version: ADMIN_PROTOCOL_VERSION,
+            ok: false,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Carries out admin commands, on the physics thread where they touch the simulation.
+#[derive(Clone)]
+pub struct Admin {
+    physics: PhysicsHandle,
+    /// Where `reload_params` reads from, see `Config::simulation_file`
+    simulation_file: Option<String>,
+    data_dir: DataDir,
+    /// Where `load_scenario` finds the `natural_earth` land, see `Args::shapefile`
+    shapefile: String,
+    views: Arc<ViewStore>,
+}
+
+impl Admin {
+    pub fn new(
+        physics: PhysicsHandle,
+        simulation_file: Option<String>,
+        data_dir: DataDir,
+        shapefile: String,
+        views: ViewStore,
+    ) -> Self {
+        Self {
+            physics,
+            simulation_file,
+            data_dir,
+            shapefile,
+            views: Arc::new(views),
+        }
+    }
+
+    pub async fn execute(&self, request: AdminRequest) -> AdminReply {
+        let outcome = match request.version {
+            Some(version) if version != ADMIN_PROTOCOL_VERSION => Err(format!(
+                "unsupported version {}, this server speaks {}",
+                version, ADMIN_PROTOCOL_VERSION
+            )),
+            _ => self.run(request.command, request.session).await,
+        };
+        match outcome {
+            Ok(result) => AdminReply::success(result),
+            Err(error) => AdminReply::failure(error),
+        }
+    }
+
+    async fn run(&self, command: AdminCommand, session: Option<String>) -> Result<Value, String> {
+        match command {
+            AdminCommand::TimeSync { t0 } => Ok(json!({ "t0": t0, "t1": server_time_ms() })),
+            AdminCommand::Help => Ok(json!({
+                "version": ADMIN_PROTOCOL_VERSION,
+                "commands": AdminCommand::schema(),
+            })),
+            AdminCommand::Pause => {
+                self.on_physics(|simulation, _| {
+                    simulation.paused = true;
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            AdminCommand::Resume => {
+                self.on_physics(|simulation, _| {
+                    simulation.paused = false;
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            AdminCommand::SetParams { params } => self.override_params(params),
+            AdminCommand::SetParam { name, value } => {
+                self.override_params(Map::from_iter([(name, value)]))
+            }
+            AdminCommand::ReloadParams => {
+                let path = self
+                    .simulation_file
+                    .clone()
+                    .ok_or("no simulation_file is configured")?;
+                let reloaded = self
+                    .data_dir
+                    .resolve(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|path| SimulationConfig::load(path).map_err(|e| e.to_string()))?;
+                let mut config = self.physics.config.write().unwrap();
+                *config = reloaded;
+                Ok(serde_json::to_value(&*config).unwrap())
+            }
+            AdminCommand::GetParams => {
+                Ok(serde_json::to_value(&*self.physics.config.read().unwrap()).unwrap())
+            }
+            AdminCommand::AddGoal { lon, lat } => {
+                let session = session.ok_or("add_goal needs a session")?;
+                self.on_physics(move |simulation, config| {
+                    let position = Point::new(lon, lat);
+                    let id = simulation.user_goals.add(&session, position)?;
+                    simulation.disturb(position, config);
+                    Ok(json!({ "id": id }))
+                })
+                .await
+            }
+            AdminCommand::RemoveGoal { index } => {
+                self.on_physics(move |simulation, _| {
+                    if index >= simulation.goals.len() {
+                        return Err(format!("no goal at index {}", index));
+                    }
+                    simulation.goals.remove(index);
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            AdminCommand::ListMyGoals => {
+                let session = session.ok_or("list_my_goals needs a session")?;
+                self.on_physics(move |simulation, _| {
+                    let now = Instant::now();
+                    let goals: Vec<Value> = simulation
+                        .user_goals
+                        .owned_by(&session)
+                        .map(|goal| {
+                            let expires_in = goal.expires_at.saturating_duration_since(now);
+                            json!({
+                                "id": goal.id,
+                                "position": goal.position,
+                                "expires_in_secs": expires_in.as_secs_f64(),
+                            })
+                        })
+                        .collect();
+                    Ok(Value::from(goals))
+                })
+                .await
+            }
+            AdminCommand::RemoveMyGoal { id } => {
+                let session = session.ok_or("remove_my_goal needs a session")?;
+                self.on_physics(move |simulation, _| {
+                    simulation.user_goals.remove(&session, id)?;
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            AdminCommand::Spawn { lon, lat, count } => {
+                if count > MAX_SPAWN {
+                    return Err(format!("at most {} sharks per spawn", MAX_SPAWN));
+                }
+                self.on_physics(move |simulation, _| {
+                    let position = Point::new(lon, lat);
+                    if !simulation.in_water(position) {
+                        return Err("that point is on land".to_string());
+                    }
+                    simulation.spawn_sharks(position, count, &mut rand::rng());
+                    Ok(json!({ "sharks": simulation.sharks.len() }))
+                })
+                .await
+            }
+            AdminCommand::LoadScenario { world, sharks } => {
+                if sharks > MAX_SPAWN {
+                    return Err(format!("at most {} sharks per scenario", MAX_SPAWN));
+                }
+                world.validate()?;
+                let data_dir = self.data_dir.clone();
+                let shapefile = self.shapefile.clone();
+                // the land and the sharks' places take long enough to stall ticks
+                let fresh = tokio::task::spawn_blocking(move || {
+                    let land = world.land_polygons(&data_dir, &shapefile);
+                    Simulation::new(sharks, &mut rand::rng(), Arc::new(land), Vec::new())
+                })
+                .await
+                .map_err(|e| e.to_string())??;
+                self.on_physics(move |simulation, _| {
+                    simulation.replace_world(fresh, &mut rand::rng());
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            AdminCommand::ReplaySeek { tick } => {
+                self.on_physics(move |simulation, _| {
+                    let playback = simulation.playback.as_mut().ok_or("not replaying")?;
+                    playback.seek_to = Some(tick);
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            AdminCommand::ReplaySpeed { speed } => {
+                if !speed.is_finite() {
+                    return Err("speed has to be a number".to_string());
+                }
+                self.on_physics(move |simulation, _| {
+                    let playback = simulation.playback.as_mut().ok_or("not replaying")?;
+                    playback.speed = speed;
+                    Ok(Value::Null)
+                })
+                .await
+            }
+            #[cfg(feature = "chaos")]
+            AdminCommand::SetChaos { chaos } => {
+                self.on_physics(move |simulation, _| {
+                    let mut settings = serde_json::to_value(simulation.chaos).unwrap();
+                    for (name, value) in chaos {
+                        match settings.get_mut(&name) {
+                            Some(setting) => *setting = value,
+                            None => return Err(format!("unknown chaos setting {}", name)),
+                        }
+                    }
+                    simulation.chaos =
+                        serde_json::from_value(settings).map_err(|e| e.to_string())?;
+                    Ok(serde_json::to_value(simulation.chaos).unwrap())
+                })
+                .await
+            }
+            #[cfg(feature = "chaos")]
+            AdminCommand::GetChaos => {
+                self.on_physics(|simulation, _| Ok(serde_json::to_value(simulation.chaos).unwrap()))
+                    .await
+            }
+            AdminCommand::SaveView { view } => {
+                let code = self.views.save(view, session)?;
+                let views = self.views.clone();
+                tokio::task::spawn_blocking(move || views.write())
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| format!("failed to store the view: {}", e))?;
+                Ok(json!({ "code": code }))
+            }
+            AdminCommand::LoadView { code } => {
+                let view = self
+                    .views
+                    .get(&code)
+                    .ok_or_else(|| format!("no view saved as {}", code))?;
+                Ok(serde_json::to_value(view).unwrap())
+            }
+            AdminCommand::ListMyViews => {
+                let session = session.ok_or("list_my_views needs a session")?;
+                let listed: Vec<Value> = self
+                    .views
+                    .owned_by(&session)
+                    .into_iter()
+                    .map(|(code, view)| json!({ "code": code, "view": view }))
+                    .collect();
+                Ok(Value::from(listed))
+            }
+        }
+    }
+
+    /// Applies `overrides` to the shared parameters, replying with the full new set.
+    fn override_params(&self, overrides: Map<String, Value>) -> Result<Value, String> {
+        let mut config = self.physics.config.write().unwrap();
+        *config = config
+            .with_overrides(overrides)
+            .map_err(|e| e.to_string())?;
+        Ok(serde_json::to_value(&*config).unwrap())
+    }
+
+    /// Adds an attraction point for good, metadata and all. Replies with its
+    /// `index` in `goals`.
+    pub async fn add_goal(&self, goal: AttractionPoint) -> Result<Value, String> {
+        self.on_physics(move |simulation, _| {
+            simulation.goals.push(goal);
+            Ok(json!({ "index": simulation.goals.len() - 1 }))
+        })
+        .await
+    }
+
+    /// Runs `f` between two steps and waits for its result.
+    async fn on_physics<F>(&self, f: F) -> Result<Value, String>
+    where
+        F: FnOnce(&mut Simulation, &mut SimulationConfig) -> Result<Value, String> + Send + 'static,
+    {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.physics
+            .commands
+            .send(Box::new(move |simulation, config| {
+                let _ = reply_tx.send(f(simulation, config));
+            }))
+            .map_err(|_| "the physics thread has stopped".to_string())?;
+        reply_rx
+            .await
+            .map_err(|_| "the physics thread has stopped".to_string())?
+    }
+}
@@ -0,0 +1,71 @@
+use geo::{Area, LineString, Polygon, Rect, Scale};
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Harmonics summed into an island's outline; higher ones add smaller wiggles.
+const HARMONICS: usize = 6;
+const OUTLINE_SEGMENTS: usize = 48;
+
+/// Generates `islands` random blob-shaped islands inside `bounds` whose combined area
+/// is `coverage` (0..1) of the bounds. Overlapping islands make the real coverage a bit lower.
+pub fn generate_archipelago<R: Rng>(
+    rng: &mut R,
+    islands: usize,
+    coverage: f64,
+    bounds: Rect<f64>,
+) -> Vec<Polygon<f64>> {
+    if islands == 0 || coverage <= 0.0 {
+        return vec![];
+    }
+
+    let target_area = bounds.unsigned_area() * coverage.min(1.0);
+    let mean_radius = (target_area / (islands as f64 * PI)).sqrt();
+
+    let mut polygons: Vec<Polygon<f64>> = (0..islands)
+        .map(|_| {
+            // spread of sizes: a few big islands, many small ones
+            let radius = mean_radius * rng.random_range(0.3..1.7);
+            // keep islands inside the bounds, huge ones just get centred
+            let margin = radius.min(bounds.width().min(bounds.height()) / 2.0);
+            let center_x = rng.random_range(bounds.min().x + margin..=bounds.max().x - margin);
+            let center_y = rng.random_range(bounds.min().y + margin..=bounds.max().y - margin);
+            noise_blob(rng, center_x, center_y, radius)
+        })
+        .collect();
+
+    // rescale each island around its own center so the summed area hits the target
+    let generated_area: f64 = polygons.iter().map(|poly| poly.unsigned_area()).sum();
+    if generated_area > 0.0 {
+        let factor = (target_area / generated_area).sqrt();
+        polygons = polygons.iter().map(|poly| poly.scale(factor)).collect();
+    }
+
+    polygons
+}
+
+/// A star-shaped blob whose radius is perturbed by random low-frequency harmonics.
+fn noise_blob<R: Rng>(rng: &mut R, center_x: f64, center_y: f64, radius: f64) -> Polygon<f64> {
+    let harmonics: Vec<(f64, f64)> = (2..2 + HARMONICS)
+        .map(|k| {
+            // amplitudes fall off with frequency and sum below 1 so the radius stays positive
+            let amplitude = rng.random_range(0.0..0.35) / k as f64;
+            let phase = rng.random_range(0.0..2.0 * PI);
+            (amplitude, phase)
+        })
+        .collect();
+
+    let ring = (0..=OUTLINE_SEGMENTS)
+        .map(|i| {
+            let angle = 2.0 * PI * (i % OUTLINE_SEGMENTS) as f64 / OUTLINE_SEGMENTS as f64;
+            let wobble: f64 = harmonics
+                .iter()
+                .enumerate()
+                .map(|(k, (amplitude, phase))| amplitude * ((k + 2) as f64 * angle + phase).sin())
+                .sum();
+            let r = radius * (1.0 + wobble);
+            (center_x + r * angle.cos(), center_y + r * angle.sin())
+        })
+        .collect::<Vec<_>>();
+
+    Polygon::new(LineString::from(ring), vec![])
+}
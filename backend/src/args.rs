@@ -0,0 +1,62 @@
+use clap::{Parser, ValueEnum};
+
+use crate::tick::DEFAULT_TPS;
+use crate::{CONFIG_PATH, LoadTestOptions, ReplayOptions, SHAPEFILE_PATH};
+
+/// Command line of the server. The server options can also come from the
+/// environment, e.g. `SHARKSIM_SHARKS=1000` for `--sharks 1000`.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Simulates sharks migrating and streams them to WebSocket clients"
+)]
+pub struct Args {
+    #[arg(long, value_enum, default_value_t = Mode::Serve)]
+    pub mode: Mode,
+    /// Checks the config and its data files and runs a few ticks without
+    /// serving, then exits with 1 when anything is wrong, see `run_self_test`
+    #[arg(long)]
+    pub self_test: bool,
+    /// Server config file, looked up in the data directory like the data
+    /// files, defaults apply when it isn't found
+    #[arg(long, env = "SHARKSIM_CONFIG", default_value = CONFIG_PATH)]
+    pub config: String,
+    /// Addresses to accept WebSocket clients on in place of `listeners` in the
+    /// config, comma separated, e.g. `0.0.0.0:25555,unix:/run/sharks.sock`
+    #[arg(long, env = "SHARKSIM_BIND", value_delimiter = ',')]
+    pub bind: Vec<String>,
+    /// Sharks to start with
+    #[arg(long, env = "SHARKSIM_SHARKS", default_value_t = 300)]
+    pub sharks: usize,
+    /// Land of the `natural_earth` world, relative to the data directory, a
+    /// shapefile or a `.geojson` file
+    #[arg(long, env = "SHARKSIM_SHAPEFILE", default_value = SHAPEFILE_PATH)]
+    pub shapefile: String,
+    /// Physics ticks per second
+    #[arg(
+        long,
+        env = "SHARKSIM_TPS",
+        default_value_t = DEFAULT_TPS,
+        value_parser = clap::value_parser!(u64).range(1..=1000)
+    )]
+    pub tps: u64,
+    /// Frames sent to clients per second, every tick when unset and at most `--tps`
+    #[arg(
+        long,
+        env = "SHARKSIM_BROADCAST_HZ",
+        value_parser = clap::value_parser!(u64).range(1..=1000)
+    )]
+    pub broadcast_hz: Option<u64>,
+    #[command(flatten)]
+    pub replay: ReplayOptions,
+    #[command(flatten)]
+    pub loadtest: LoadTestOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Run the simulation server
+    Serve,
+    /// Connect many clients to a running server and report how it copes
+    Loadtest,
+}
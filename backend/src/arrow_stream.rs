@@ -0,0 +1,83 @@
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use std::sync::Arc;
+
+use crate::Simulation;
+
+/// Content type of an Arrow IPC stream
+pub const ARROW_STREAM_MIME: &str = "application/vnd.apache.arrow.stream";
+
+/// One row per shark, the tick repeated on every row so batches can be concatenated.
+fn shark_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("tick", DataType::UInt64, false),
+        Field::new("server_time_ms", DataType::Float64, false),
+        Field::new("id", DataType::UInt64, false),
+        Field::new("species", DataType::Utf8, false),
+        Field::new("lon", DataType::Float64, false),
+        Field::new("lat", DataType::Float64, false),
+        Field::new("rotation_rad", DataType::Float64, false),
+        Field::new("reported_rotation_rad", DataType::Float64, false),
+        Field::new("speed", DataType::Float64, false),
+        Field::new("school_id", DataType::UInt64, true),
+        Field::new("informed", DataType::Boolean, false),
+    ])
+}
+
+fn shark_batch(schema: Arc<Schema>, simulation: &Simulation) -> Result<RecordBatch, ArrowError> {
+    let sharks = &simulation.sharks;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(vec![simulation.tick; sharks.len()])),
+        Arc::new(Float64Array::from(vec![
+            simulation.server_time_ms;
+            sharks.len()
+        ])),
+        Arc::new(UInt64Array::from_iter_values(sharks.iter().map(|s| s.id))),
+        Arc::new(StringArray::from_iter_values(
+            sharks.iter().map(|s| s.species.name()),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            sharks.iter().map(|s| s.position.x()),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            sharks.iter().map(|s| s.position.y()),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            sharks.iter().map(|s| s.rotation_rad),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            sharks.iter().map(|s| s.reported_rotation_rad),
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            sharks.iter().map(|s| s.speed),
+        )),
+        Arc::new(UInt64Array::from_iter(sharks.iter().map(|s| s.school_id))),
+        Arc::new(BooleanArray::from_iter(
+            sharks.iter().map(|s| Some(s.informed)),
+        )),
+    ];
+    RecordBatch::try_new(schema, columns)
+}
+
+/// Turns frames into an Arrow IPC stream, one record batch per frame, that
+/// `pyarrow.ipc.open_stream` and `polars` read as is.
+pub struct ArrowStream {
+    schema: Arc<Schema>,
+    writer: StreamWriter<Vec<u8>>,
+}
+
+impl ArrowStream {
+    pub fn new() -> Result<Self, ArrowError> {
+        let schema = Arc::new(shark_schema());
+        let writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        Ok(Self { schema, writer })
+    }
+
+    /// Bytes carrying `simulation` as the next batch, after the schema on the first call.
+    pub fn encode(&mut self, simulation: &Simulation) -> Result<Vec<u8>, ArrowError> {
+        let batch = shark_batch(self.schema.clone(), simulation)?;
+        self.writer.write(&batch)?;
+        Ok(std::mem::take(self.writer.get_mut()))
+    }
+}
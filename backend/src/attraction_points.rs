@@ -0,0 +1,311 @@
+use geo::Point;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use utoipa::ToSchema;
+
+use crate::Species;
+use crate::summary::PointSchema;
+
+/// The attraction points used when no `goals_file` is configured, `(x, y)`
+const BUILTIN: [(f64, f64); 18] = [
+    (167.0, -28.299544),
+    (41.202671, -39.916056),
+    (30.744196, 131.833367),
+    (39.361909, -21.325484),
+    (39.903416, -66.289550),
+    (36.666216, -148.250722),
+    (-143.194445, -18.377986),
+    (171.527249, -13.651325),
+    (186.228940, -26.049380),
+    (55.205441, -28.730335),
+    (52.043905, -36.138984),
+    (-24.249463, 36.257563),
+    (-48.143093, 44.800109),
+    (-64.082863, 37.817378),
+    (-68.246077, 32.671749),
+    (-136.808344, 37.278424),
+    (-142.077571, 28.743580),
+    (-160.695504, 20.771523),
+];
+
+/// Months of the year a point attracts sharks, 1 to 12 and both inclusive.
+/// `from` after `to` wraps around the new year, e.g. 11 to 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Months {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl Months {
+    pub fn contains(self, month: u32) -> bool {
+        if self.from <= self.to {
+            (self.from..=self.to).contains(&month)
+        } else {
+            month >= self.from || month <= self.to
+        }
+    }
+
+    /// Reads `"6-9"`, or `"7"` for a single month.
+    fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let month = |text: &str| -> Result<u32, Box<dyn Error>> {
+            let month: u32 = text.trim().parse()?;
+            if !(1..=12).contains(&month) {
+                return Err(format!("no month {}", month).into());
+            }
+            Ok(month)
+        };
+        match text.split_once('-') {
+            Some((from, to)) => Ok(Self {
+                from: month(from)?,
+                to: month(to)?,
+            }),
+            None => {
+                let only = month(text)?;
+                Ok(Self {
+                    from: only,
+                    to: only,
+                })
+            }
+        }
+    }
+}
+
+/// How strongly each category of goal draws each species, e.g. seal colonies
+/// pulling white sharks but not whale sharks. Missing entries count as 1.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CategoryAffinity(pub BTreeMap<String, BTreeMap<Species, f64>>);
+
+impl CategoryAffinity {
+    pub fn get(&self, category: &str, species: Species) -> Option<f64> {
+        self.0.get(category)?.get(&species).copied()
+    }
+}
+
+/// Everything a goals file holds, see `AttractionPoint::load`.
+#[derive(Debug, Clone, Default)]
+pub struct GoalCatalog {
+    pub points: Vec<AttractionPoint>,
+    pub category_affinity: CategoryAffinity,
+}
+
+/// A goal the sharks are drawn to, with what clients need to label it, e.g.
+/// "Neptune Islands — seal colony". Serializes with the `x` and `y` of a
+/// plain point next to the metadata.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AttractionPoint {
+    #[serde(flatten)]
+    #[schema(value_type = PointSchema)]
+    pub position: Point<f64>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// What is there, e.g. `"seal colony"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Multiplies the goal seeking force towards it
+    #[serde(default = "one")]
+    pub strength: f64,
+    /// When it attracts sharks, all year when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub months: Option<Months>,
+    /// Multiplies `strength` per species, overriding `CategoryAffinity`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub affinity: BTreeMap<Species, f64>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+impl AttractionPoint {
+    /// A point without metadata, attracting every species all year.
+    pub fn at(position: Point<f64>) -> Self {
+        Self {
+            position,
+            name: String::new(),
+            category: None,
+            strength: 1.0,
+            months: None,
+            affinity: BTreeMap::new(),
+        }
+    }
+
+    pub fn builtin() -> Vec<Self> {
+        BUILTIN
+            .iter()
+            .map(|&(x, y)| Self::at(Point::new(x, y)))
+            .collect()
+    }
+
+    /// How hard `species` is drawn here in `month`, 0 out of season. The
+    /// point's own affinity goes first, then that of its category.
+    pub fn pull(&self, species: Species, month: u32, categories: &CategoryAffinity) -> f64 {
+        if self.months.is_some_and(|months| !months.contains(month)) {
+            return 0.0;
+        }
+        let affinity = self.affinity.get(&species).copied().or_else(|| {
+            self.category
+                .as_ref()
+                .and_then(|category| categories.get(category, species))
+        });
+        self.strength * affinity.unwrap_or(1.0)
+    }
+
+    /// Reads a `.geojson`/`.json` file with `load_geojson`, anything else
+    /// with `load_csv`.
+    pub fn load(path: impl AsRef<Path>) -> Result<GoalCatalog, Box<dyn Error>> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("geojson" | "json") => Self::load_geojson(path),
+            _ => Self::load_csv(path),
+        }
+    }
+
+    /// Reads points from a CSV file with a header naming `lat`/`latitude` and
+    /// `lon`/`longitude` columns and optionally `name`, `category`,
+    /// `strength`, `months` (e.g. `6-9`) and one column per species named
+    /// like `great_white` holding its affinity. Empty fields take the defaults.
+    /// A row with a `category` but no position sets the affinities of that
+    /// category instead, see `CategoryAffinity`.
+    pub fn load_csv(path: impl AsRef<Path>) -> Result<GoalCatalog, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<String> = lines
+            .next()
+            .ok_or("empty file")?
+            .split(',')
+            .map(|column| column.trim().to_ascii_lowercase())
+            .collect();
+        let column = |names: &[&str]| {
+            header
+                .iter()
+                .position(|column| names.contains(&column.as_str()))
+        };
+        let lat_column = column(&["lat", "latitude"]).ok_or("no latitude column")?;
+        let lon_column = column(&["lon", "longitude"]).ok_or("no longitude column")?;
+        let name_column = column(&["name"]);
+        let category_column = column(&["category"]);
+        let strength_column = column(&["strength"]);
+        let months_column = column(&["months"]);
+        let species_columns: Vec<(Species, usize)> = Species::ALL
+            .iter()
+            .filter_map(|species| Some((*species, column(&[species.name()])?)))
+            .collect();
+
+        let mut catalog = GoalCatalog::default();
+        for (index, line) in lines.enumerate() {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |column: Option<usize>| {
+                column
+                    .and_then(|i| fields.get(i))
+                    .copied()
+                    .filter(|field| !field.is_empty())
+            };
+            let number = |column: usize| -> Result<f64, Box<dyn Error>> {
+                let field = field(Some(column)).ok_or("missing a value")?;
+                field
+                    .parse()
+                    .map_err(|_| format!("{} is not a number", field).into())
+            };
+            let mut row = || -> Result<(), Box<dyn Error>> {
+                if field(Some(lat_column)).is_none()
+                    && field(Some(lon_column)).is_none()
+                    && let Some(category) = field(category_column)
+                {
+                    let affinities = catalog
+                        .category_affinity
+                        .0
+                        .entry(category.to_string())
+                        .or_default();
+                    for &(species, column) in &species_columns {
+                        if field(Some(column)).is_some() {
+                            affinities.insert(species, number(column)?);
+                        }
+                    }
+                    return Ok(());
+                }
+                let mut point = Self::at(Point::new(number(lon_column)?, number(lat_column)?));
+                point.name = field(name_column).unwrap_or_default().to_string();
+                point.category = field(category_column).map(str::to_string);
+                if let Some(column) = strength_column
+                    && field(Some(column)).is_some()
+                {
+                    point.strength = number(column)?;
+                }
+                point.months = field(months_column).map(Months::parse).transpose()?;
+                for &(species, column) in &species_columns {
+                    if field(Some(column)).is_some() {
+                        point.affinity.insert(species, number(column)?);
+                    }
+                }
+                catalog.points.push(point);
+                Ok(())
+            };
+            // the header is line 1
+            row().map_err(|e| format!("line {}: {}", index + 2, e))?;
+        }
+        Ok(catalog)
+    }
+
+    /// Reads the `Point` features of a GeoJSON `FeatureCollection`, taking
+    /// the metadata from properties named like the `load_csv` columns.
+    /// Other geometries are skipped. The category affinities go in a
+    /// `category_affinity` member next to `features`, e.g.
+    /// `{"seal colony": {"great_white": 2.0, "whale_shark": 0.0}}`.
+    pub fn load_geojson(path: impl AsRef<Path>) -> Result<GoalCatalog, Box<dyn Error>> {
+        let mut collection: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let category_affinity = match collection["category_affinity"].take() {
+            Value::Null => CategoryAffinity::default(),
+            matrix => serde_json::from_value(matrix)?,
+        };
+        let features = collection["features"]
+            .as_array()
+            .ok_or("not a FeatureCollection")?;
+
+        let mut points = Vec::new();
+        for feature in features {
+            let geometry = &feature["geometry"];
+            if geometry["type"] != "Point" {
+                continue;
+            }
+            let coordinate = |i: usize| {
+                geometry["coordinates"][i]
+                    .as_f64()
+                    .ok_or("a point without coordinates")
+            };
+            let mut point = Self::at(Point::new(coordinate(0)?, coordinate(1)?));
+            let properties = &feature["properties"];
+            let number = |name: &str| -> Result<Option<f64>, Box<dyn Error>> {
+                match &properties[name] {
+                    Value::Null => Ok(None),
+                    value => Ok(Some(
+                        value
+                            .as_f64()
+                            .ok_or_else(|| format!("{} is not a number", name))?,
+                    )),
+                }
+            };
+            point.name = properties["name"].as_str().unwrap_or_default().to_string();
+            point.category = properties["category"].as_str().map(str::to_string);
+            if let Some(strength) = number("strength")? {
+                point.strength = strength;
+            }
+            point.months = properties["months"]
+                .as_str()
+                .map(Months::parse)
+                .transpose()?;
+            for species in Species::ALL {
+                if let Some(affinity) = number(species.name())? {
+                    point.affinity.insert(species, affinity);
+                }
+            }
+            points.push(point);
+        }
+        Ok(GoalCatalog {
+            points,
+            category_affinity,
+        })
+    }
+}
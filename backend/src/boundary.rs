@@ -0,0 +1,158 @@
+use geo::{Contains, Point, Polygon};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use utoipa::ToSchema;
+
+/// What happens at the edges of the map.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Boundary {
+    /// Sharks steer away from the edges, see `border_strength`
+    #[default]
+    Walls,
+    /// Sharks swim out and are removed; new ones swim in along the edges at up
+    /// to `inflow_per_sec` while the population is below where it started
+    Open { inflow_per_sec: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    West,
+    East,
+    South,
+    North,
+}
+
+/// Sharks per edge of the map.
+#[derive(Debug, Clone, Copy, Default, Serialize, ToSchema)]
+pub struct EdgeCounts {
+    pub west: u64,
+    pub east: u64,
+    pub south: u64,
+    pub north: u64,
+}
+
+impl EdgeCounts {
+    pub fn add(&mut self, edge: Edge) {
+        match edge {
+            Edge::West => self.west += 1,
+            Edge::East => self.east += 1,
+            Edge::South => self.south += 1,
+            Edge::North => self.north += 1,
+        }
+    }
+}
+
+/// Migration across an open boundary since the start of the run.
+#[derive(Debug, Clone, Copy, Default, Serialize, ToSchema)]
+pub struct BoundaryStats {
+    pub entries: EdgeCounts,
+    pub exits: EdgeCounts,
+    /// Entries minus exits per second, averaged over about a minute
+    pub net_flux_per_sec: f64,
+}
+
+/// Time constant of the `net_flux_per_sec` average, in seconds
+const FLUX_AVERAGE_SECS: f64 = 60.0;
+
+impl BoundaryStats {
+    /// Folds one tick's movement into the running flux average.
+    pub fn record_tick(&mut self, entered: usize, exited: usize, dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        let net_per_sec = (entered as f64 - exited as f64) / dt;
+        let alpha = 1.0 - (-dt / FLUX_AVERAGE_SECS).exp();
+        self.net_flux_per_sec += (net_per_sec - self.net_flux_per_sec) * alpha;
+    }
+}
+
+/// Bookkeeping for an open boundary.
+#[derive(Debug, Clone, Default)]
+pub struct BoundaryFlow {
+    /// Population the inflow tops up to
+    pub population: usize,
+    /// Fractional sharks owed by the inflow rate, carried between ticks
+    inflow_credit: f64,
+}
+
+/// Sharks this close to an edge have left the map.
+const EXIT_MARGIN: f64 = 0.01;
+/// How far inside the edge new sharks appear, clear of `EXIT_MARGIN`.
+const SPAWN_INSET: f64 = 0.1;
+/// Tries at finding water along the edges before giving up for this tick.
+const SPAWN_ATTEMPTS: usize = 10;
+
+impl BoundaryFlow {
+    pub fn new(population: usize) -> Self {
+        Self {
+            population,
+            inflow_credit: 0.0,
+        }
+    }
+
+    /// Whole sharks allowed in this tick, at most enough to refill the population.
+    pub fn take_inflow(&mut self, inflow_per_sec: f64, dt: f64, current: usize) -> usize {
+        let missing = self.population.saturating_sub(current);
+        if missing == 0 {
+            self.inflow_credit = 0.0;
+            return 0;
+        }
+        self.inflow_credit += inflow_per_sec * dt;
+        let allowed = (self.inflow_credit.floor() as usize).min(missing);
+        self.inflow_credit -= allowed as f64;
+        allowed
+    }
+}
+
+/// The edge a position has reached, if any.
+pub fn exit_edge(position: Point<f64>, map_bounds: (f64, f64, f64, f64)) -> Option<Edge> {
+    let (min_x, min_y, max_x, max_y) = map_bounds;
+    if position.x() <= min_x + EXIT_MARGIN {
+        Some(Edge::West)
+    } else if position.x() >= max_x - EXIT_MARGIN {
+        Some(Edge::East)
+    } else if position.y() <= min_y + EXIT_MARGIN {
+        Some(Edge::South)
+    } else if position.y() >= max_y - EXIT_MARGIN {
+        Some(Edge::North)
+    } else {
+        None
+    }
+}
+
+/// A point in water just inside a random edge, picked proportionally to edge
+/// length, with a heading into the map.
+pub fn spawn_on_edge<R: Rng>(
+    rng: &mut R,
+    map_bounds: (f64, f64, f64, f64),
+    land_polygons: &[Polygon<f64>],
+) -> Option<(Edge, Point<f64>, f64)> {
+    let (min_x, min_y, max_x, max_y) = map_bounds;
+    let (width, height) = (max_x - min_x, max_y - min_y);
+
+    for _ in 0..SPAWN_ATTEMPTS {
+        let along = rng.random_range(0.0..2.0 * (width + height));
+        let (edge, point, inward) = if along < width {
+            let x = min_x + along;
+            (Edge::South, Point::new(x, min_y + SPAWN_INSET), PI / 2.0)
+        } else if along < 2.0 * width {
+            let x = min_x + along - width;
+            (Edge::North, Point::new(x, max_y - SPAWN_INSET), -PI / 2.0)
+        } else if along < 2.0 * width + height {
+            let y = min_y + along - 2.0 * width;
+            (Edge::West, Point::new(min_x + SPAWN_INSET, y), 0.0)
+        } else {
+            let y = min_y + along - 2.0 * width - height;
+            (Edge::East, Point::new(max_x - SPAWN_INSET, y), PI)
+        };
+        if land_polygons.iter().any(|poly| poly.contains(&point)) {
+            continue;
+        }
+        let heading = inward + rng.random_range(-PI / 4.0..PI / 4.0);
+        return Some((edge, point, heading));
+    }
+    None
+}
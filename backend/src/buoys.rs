@@ -0,0 +1,92 @@
+use geo::{Distance, Euclidean, Point};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::{Environment, Km, Shark};
+
+/// How often clients of the `/buoys` feed get readings
+pub const BUOY_READING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A moored sensor, e.g. `{"name": "B1", "lon": -70.5, "lat": 38.2, "radius_km": 100}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct BuoyConfig {
+    pub name: String,
+    pub lon: f64,
+    pub lat: f64,
+    /// How far away it notices sharks
+    pub radius_km: f64,
+}
+
+/// A stationary sensor counting the sharks that swim past it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Buoy {
+    pub name: String,
+    pub position: Point<f64>,
+    #[serde(skip)]
+    radius: Km,
+    /// Sharks within range right now
+    pub sharks_in_range: usize,
+    /// Sharks that came into range since the start of the run
+    pub pass_bys: u64,
+    /// Sea surface temperature in °C, when a grid is loaded
+    pub temperature_c: Option<f64>,
+    /// Chlorophyll-a in mg/m³, when a productivity raster is loaded
+    pub chlorophyll_mg_m3: Option<f64>,
+    /// Eastward and northward surface current in m/s, when currents are loaded
+    pub current_mps: Option<(f64, f64)>,
+    #[serde(skip)]
+    in_range: HashSet<u64>,
+}
+
+impl Buoy {
+    pub fn new(config: &BuoyConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            position: Point::new(config.lon, config.lat),
+            radius: Km(config.radius_km),
+            sharks_in_range: 0,
+            pass_bys: 0,
+            temperature_c: None,
+            chlorophyll_mg_m3: None,
+            current_mps: None,
+            in_range: HashSet::new(),
+        }
+    }
+
+    /// Counts the sharks that entered range since the last update and reads
+    /// the conditions at the buoy. Returns the ids of those sharks, lowest first.
+    pub fn update(&mut self, sharks: &[Shark], environment: &Environment) -> Vec<u64> {
+        let radius = self.radius.to_degrees();
+        let in_range: HashSet<u64> = sharks
+            .iter()
+            .filter(|shark| Euclidean.distance(shark.position, self.position) <= radius)
+            .map(|shark| shark.id)
+            .collect();
+        let mut entered: Vec<u64> = in_range.difference(&self.in_range).copied().collect();
+        entered.sort_unstable();
+        self.pass_bys += entered.len() as u64;
+        self.sharks_in_range = in_range.len();
+        self.in_range = in_range;
+        self.temperature_c = environment
+            .sst
+            .as_ref()
+            .and_then(|sst| sst.sample(self.position));
+        self.chlorophyll_mg_m3 = environment
+            .chlorophyll
+            .as_ref()
+            .and_then(|chlorophyll| chlorophyll.sample(self.position));
+        self.current_mps = environment
+            .currents
+            .as_ref()
+            .and_then(|currents| currents.sample(self.position));
+        entered
+    }
+}
+
+/// One message of the `/buoys` feed.
+#[derive(Debug, Serialize)]
+pub struct BuoyReadings<'a> {
+    pub tick: u64,
+    pub buoys: &'a [Buoy],
+}
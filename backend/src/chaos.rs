@@ -0,0 +1,39 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Fault injection for exercising the resilience paths, only built with the
+/// `chaos` feature. Lives on the simulation so it can be changed between ticks
+/// like any other setting. Everything is off by default.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Chaos {
+    /// Chance per tick of stalling the physics thread for `tick_delay_ms`
+    pub tick_delay_chance: f64,
+    pub tick_delay_ms: u64,
+    /// Chance per tick of not broadcasting the frame at all
+    pub drop_frame_chance: f64,
+    /// Chance for a new connection to be slow, waiting `slow_client_delay_ms`
+    /// before each frame it sends
+    pub slow_client_chance: f64,
+    pub slow_client_delay_ms: u64,
+}
+
+impl Chaos {
+    pub fn tick_delay(&self) -> Option<Duration> {
+        roll(self.tick_delay_chance).then(|| Duration::from_millis(self.tick_delay_ms))
+    }
+
+    pub fn drop_frame(&self) -> bool {
+        roll(self.drop_frame_chance)
+    }
+
+    /// Decided once per connection.
+    pub fn slow_client_delay(&self) -> Option<Duration> {
+        roll(self.slow_client_chance).then(|| Duration::from_millis(self.slow_client_delay_ms))
+    }
+}
+
+fn roll(chance: f64) -> bool {
+    chance > 0.0 && rand::rng().random_bool(chance.min(1.0))
+}
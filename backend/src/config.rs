@@ -0,0 +1,144 @@
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::{
+    Boundary, BuoyConfig, ContactConfig, DataDir, HotEventConfig, Leadership, OverrunPolicy,
+    PreyConfig, ProductivityConfig, RecorderConfig, SpeciesRangeConfig, SteeringScheme,
+    TrackDecimation, UserGoalLimits, WorldPreset,
+};
+
+pub const CONFIG_PATH: &str = "config.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Addresses to accept WebSocket clients on, `host:port` or `unix:/path/to.sock`
+    pub listeners: Vec<String>,
+    /// Address for the plain HTTP endpoints such as `GET /summary`, `null` disables them
+    pub http_listener: Option<String>,
+    /// Land to simulate on, e.g. `"natural_earth"`, `"island"`, or
+    /// `{"archipelago": {"islands": 200, "coverage": 0.2}}`
+    pub world: WorldPreset,
+    /// Fixed RNG seed for reproducible runs, random when unset
+    pub seed: Option<u64>,
+    /// Ticks between state hashes, 0 disables hashing
+    pub state_hash_interval: u64,
+    /// Informed-leader dynamics, e.g.
+    /// `{"informed_fraction": 0.1, "alignment_factor": 0.5, "goal_factor": 2.0}`
+    pub leadership: Option<Leadership>,
+    /// What to give up when a tick runs over budget:
+    /// `"dilate_time"`, `"skip_broadcast"` or `"fewer_substeps"`
+    pub overrun_policy: OverrunPolicy,
+    /// Physics sub-steps per tick, the most `fewer_substeps` can take away from
+    pub physics_substeps: u32,
+    /// Seconds of recent frames kept for clients connecting with `?history_seconds=N`
+    pub history_seconds: u64,
+    /// How finely tracks from the history are kept, for `?history=tracks` and
+    /// `GET /tracks`, see `TrackDecimation`
+    pub track_decimation: TrackDecimation,
+    /// Writes every frame's sharks to disk for replaying later, see `RecorderConfig`
+    pub recording: Option<RecorderConfig>,
+    /// Run as a hot standby of the primary at this WebSocket URL, e.g.
+    /// `"ws://primary:25555"`, taking over when it goes away. Give it the
+    /// primary's `--tps` and `--broadcast-hz`, see `standby::takeover_after`.
+    pub replicate_from: Option<String>,
+    /// `"walls"`, or `{"open": {"inflow_per_sec": 5.0}}` to let sharks swim off
+    /// the map while new ones arrive along the edges
+    pub boundary: Boundary,
+    /// `"sum"`, `{"weighted": {"max_force": 1.0}}` to blend the flocking
+    /// behaviours by relative strength, or
+    /// `{"priority": {"max_force": 1.0, "satisfied_below": 0.01}}` to let more
+    /// important behaviours steer first
+    pub steering: SteeringScheme,
+    /// Pairwise contact-time tracking with a periodic CSV export, see `ContactConfig`
+    pub contacts: Option<ContactConfig>,
+    /// Moored sensors streaming shark pass-bys to clients of the `/buoys` path,
+    /// see `BuoyConfig`
+    pub buoys: Vec<BuoyConfig>,
+    /// Sea surface temperature grid sharks steer by, a CSV file with
+    /// `latitude`, `longitude` and °C columns found through the data
+    /// directory, see `Raster::load_csv`. Temperature plays no part when unset.
+    pub sst_file: Option<String>,
+    /// Surface currents that carry the sharks along, a CSV file with
+    /// `latitude`, `longitude`, `u` and `v` columns in m/s found through the
+    /// data directory, see `CurrentField::load_csv`
+    pub currents_file: Option<String>,
+    /// Attraction points with names, strengths, seasons and species
+    /// affinities, a CSV or GeoJSON file found through the data directory,
+    /// see `AttractionPoint::load`. The built-in points when unset.
+    pub goals_file: Option<String>,
+    /// Chlorophyll-a raster whose most productive spots attract the sharks in
+    /// place of the built-in attraction points, see `ProductivityConfig`
+    pub productivity: Option<ProductivityConfig>,
+    /// Fish schools the sharks hunt in place of seeking the goals, which
+    /// then only mark where schools appear, see `PreyConfig`
+    pub prey: Option<PreyConfig>,
+    /// Random short-lived spots sharks flock to, e.g. bait balls, see `HotEventConfig`
+    pub hot_events: Option<HotEventConfig>,
+    /// Where each species may go, softly or as hard as land, see `SpeciesRangeConfig`
+    pub species_ranges: Vec<SpeciesRangeConfig>,
+    /// Steering parameters file, `.toml` or `.json`, see `SimulationConfig`,
+    /// relative to the data directory. Defaults apply when unset.
+    pub simulation_file: Option<String>,
+    /// Quota and lifetime of goals added by clients, see `UserGoalLimits`
+    pub user_goals: UserGoalLimits,
+    /// Where views saved with the `save_view` admin command are kept
+    pub views_file: String,
+    /// Fault injection, only with the `chaos` feature, e.g.
+    /// `{"drop_frame_chance": 0.05, "tick_delay_chance": 0.01, "tick_delay_ms": 500}`
+    #[cfg(feature = "chaos")]
+    pub chaos: crate::Chaos,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listeners: vec!["0.0.0.0:25555".to_string()],
+            http_listener: Some("0.0.0.0:25556".to_string()),
+            world: WorldPreset::default(),
+            seed: None,
+            state_hash_interval: 100,
+            leadership: None,
+            overrun_policy: OverrunPolicy::default(),
+            physics_substeps: 1,
+            history_seconds: 30,
+            track_decimation: TrackDecimation::default(),
+            recording: None,
+            replicate_from: None,
+            boundary: Boundary::default(),
+            steering: SteeringScheme::default(),
+            contacts: None,
+            buoys: Vec::new(),
+            sst_file: None,
+            currents_file: None,
+            goals_file: None,
+            productivity: None,
+            prey: None,
+            hot_events: None,
+            species_ranges: Vec::new(),
+            simulation_file: None,
+            user_goals: UserGoalLimits::default(),
+            views_file: "views.json".to_string(),
+            #[cfg(feature = "chaos")]
+            chaos: crate::Chaos::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file, looked up in `data_dir` like any data file,
+    /// falling back to the defaults when it isn't there. Fails on settings
+    /// that would break the server later on.
+    pub fn load(path: &str, data_dir: &DataDir) -> Result<Self, Box<dyn Error>> {
+        let Ok(path) = data_dir.resolve(path) else {
+            return Ok(Self::default());
+        };
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&text)?;
+        config.user_goals.validate()?;
+        if let Some(prey) = &config.prey {
+            prey.validate()?;
+        }
+        Ok(config)
+    }
+}
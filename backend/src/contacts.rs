@@ -0,0 +1,136 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+
+use crate::{Km, Shark, SpatialGrid};
+
+/// Settings for `ContactTracker`, e.g.
+/// `{"distance_km": 50, "export_path": "contacts.csv", "export_interval_secs": 60}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContactConfig {
+    pub distance_km: f64,
+    pub export_path: PathBuf,
+    pub export_interval_secs: f64,
+}
+
+/// Contact seconds per pair as handed to the export thread
+type ContactTimes = Vec<((u64, u64), f64)>;
+
+/// Cumulative time each pair of sharks has spent within `distance` of each
+/// other, written out as CSV every so often on a thread of its own. Pairs are
+/// keyed by shark id, smaller first.
+///
+/// A copy starts out empty and never exports, like `SimulationScratch`, so
+/// the snapshots of the simulation don't carry a map of every pair.
+#[derive(Debug)]
+pub struct ContactTracker {
+    config: ContactConfig,
+    seconds: HashMap<(u64, u64), f64>,
+    since_export: f64,
+    /// `None` in copies and when the export thread couldn't be started
+    exports: Option<SyncSender<ContactTimes>>,
+}
+
+impl Clone for ContactTracker {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            seconds: HashMap::new(),
+            since_export: 0.0,
+            exports: None,
+        }
+    }
+}
+
+impl ContactTracker {
+    pub fn new(config: ContactConfig) -> Self {
+        // room for one export waiting while another is written
+        let (exports, received) = sync_channel(1);
+        let path = config.export_path.clone();
+        let exporting = std::thread::Builder::new()
+            .name("contacts".to_string())
+            .spawn(move || write_exports(&path, received));
+        if let Err(e) = &exporting {
+            eprintln!("contacts won't be exported: {}", e);
+        }
+        Self {
+            config,
+            seconds: HashMap::new(),
+            since_export: 0.0,
+            exports: exporting.ok().map(|_| exports),
+        }
+    }
+
+    /// Keeps tracking without writing any exports, e.g. in a self-test.
+    pub fn stop_exporting(&mut self) {
+        self.exports = None;
+    }
+
+    /// Forgets all contact time, e.g. when the sharks are replaced.
+    pub fn clear(&mut self) {
+        self.seconds.clear();
+    }
+
+    /// Adds `dt` to every pair currently in contact and exports when due.
+    pub fn update(&mut self, sharks: &[Shark], dt: f64) {
+        let distance = Km(self.config.distance_km).to_degrees();
+        let grid = SpatialGrid::new(distance, sharks.iter().map(|shark| shark.position));
+        for shark in sharks {
+            for (j, _) in grid.within(shark.position, distance) {
+                let other = sharks[j].id;
+                if shark.id < other {
+                    *self.seconds.entry((shark.id, other)).or_default() += dt;
+                }
+            }
+        }
+
+        self.since_export += dt;
+        if self.since_export >= self.config.export_interval_secs {
+            self.since_export = 0.0;
+            self.export();
+        }
+    }
+
+    /// Hands the contact times to the export thread. Skipped while the last
+    /// two haven't been written yet, the next one has all the time anyway.
+    fn export(&self) {
+        let Some(exports) = &self.exports else {
+            return;
+        };
+        let pairs = self.seconds.iter().map(|(pair, seconds)| (*pair, *seconds));
+        match exports.try_send(pairs.collect()) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => {}
+            Err(TrySendError::Full(_)) => eprintln!(
+                "contacts export to {} is falling behind, skipping one",
+                self.config.export_path.display()
+            ),
+        }
+    }
+}
+
+/// Runs until the tracker is dropped, writing each export it gets.
+fn write_exports(path: &Path, exports: Receiver<ContactTimes>) {
+    for pairs in exports {
+        if let Err(e) = write_export(path, pairs) {
+            eprintln!("failed to export contacts to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Writes `shark_a,shark_b,seconds` rows, longest contact first. Goes through
+/// a temporary file so readers never see a half-written export.
+fn write_export(path: &Path, mut pairs: ContactTimes) -> std::io::Result<()> {
+    pairs.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let tmp_path = path.with_extension("tmp");
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+    writeln!(file, "shark_a,shark_b,seconds")?;
+    for ((a, b), seconds) in pairs {
+        writeln!(file, "{},{},{:.1}", a, b, seconds)?;
+    }
+    file.flush()?;
+    drop(file);
+    std::fs::rename(tmp_path, path)
+}
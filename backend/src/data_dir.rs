@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Environment variable pointing at the directory holding `land/` and other data files
+pub const DATA_DIR_ENV: &str = "SHARKSIM_DATA_DIR";
+
+/// Where data files are looked up, in priority order:
+/// `$SHARKSIM_DATA_DIR`, the working directory, next to the executable,
+/// the crate directory the binary was built from, then `/usr/share/sharksim`.
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    search_paths: Vec<PathBuf>,
+}
+
+impl DataDir {
+    pub fn from_env() -> Self {
+        let mut search_paths = Vec::new();
+
+        if let Some(dir) = std::env::var_os(DATA_DIR_ENV) {
+            search_paths.push(PathBuf::from(dir));
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            search_paths.push(cwd);
+        }
+        if let Ok(exe) = std::env::current_exe() {
+            // covers both an installed binary and `target/<profile>/backend`
+            search_paths.extend(exe.ancestors().skip(1).take(3).map(Path::to_path_buf));
+        }
+        search_paths.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+        search_paths.push(PathBuf::from("/usr/share/sharksim"));
+
+        let mut seen = Vec::new();
+        search_paths.retain(|path| {
+            let is_new = !seen.contains(path);
+            seen.push(path.clone());
+            is_new
+        });
+        Self { search_paths }
+    }
+
+    /// Returns the first existing `<search path>/<relative>`.
+    pub fn resolve(&self, relative: impl AsRef<Path>) -> Result<PathBuf, DataFileNotFound> {
+        let relative = relative.as_ref();
+        let attempted: Vec<PathBuf> = self
+            .search_paths
+            .iter()
+            .map(|dir| dir.join(relative))
+            .collect();
+
+        match attempted.iter().find(|path| path.is_file()) {
+            Some(path) => Ok(path.clone()),
+            None => Err(DataFileNotFound {
+                file: relative.to_path_buf(),
+                attempted,
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DataFileNotFound {
+    pub file: PathBuf,
+    pub attempted: Vec<PathBuf>,
+}
+
+impl Display for DataFileNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "data file {} not found (set {} to its directory), tried:",
+            self.file.display(),
+            DATA_DIR_ENV
+        )?;
+        for path in &self.attempted {
+            writeln!(f, "  {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DataFileNotFound {}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// Simulated seconds in a day
+pub const DAY_SECS: f64 = 86_400.0;
+/// Simulated days in a year, split into 12 equal months
+pub const YEAR_DAYS: f64 = 365.0;
+
+/// Local hours of sunrise and sunset, the same all year and everywhere
+const DAWN_HOUR: f64 = 6.0;
+const DUSK_HOUR: f64 = 18.0;
+
+/// Simulated time, advanced by `Simulation::step` at
+/// `SimulationConfig::clock_speed` times wall-clock speed. Starts at midnight
+/// UTC on the first of January.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SimClock {
+    /// Simulated seconds since the start
+    pub elapsed_secs: f64,
+    /// Hour of the day at Greenwich, 0 to 24
+    pub utc_hour: f64,
+}
+
+impl SimClock {
+    pub fn advance(&mut self, dt: f64, speed: f64) {
+        self.elapsed_secs += dt * speed;
+        self.utc_hour = self.elapsed_secs.rem_euclid(DAY_SECS) / 3600.0;
+    }
+
+    /// Month of the year, 1 to 12.
+    pub fn month(&self) -> u32 {
+        let day_of_year = (self.elapsed_secs / DAY_SECS).rem_euclid(YEAR_DAYS);
+        (day_of_year / YEAR_DAYS * 12.0) as u32 % 12 + 1
+    }
+
+    /// Solar hour at longitude `lon`, 15° to the hour.
+    pub fn local_hour(&self, lon: f64) -> f64 {
+        (self.utc_hour + lon / 15.0).rem_euclid(24.0)
+    }
+
+    /// 1 at dawn and dusk at longitude `lon`, falling off linearly to 0
+    /// `twilight_hours` either side of them.
+    pub fn twilight(&self, lon: f64, twilight_hours: f64) -> f64 {
+        let hour = self.local_hour(lon);
+        let from_twilight = [DAWN_HOUR, DUSK_HOUR]
+            .iter()
+            .map(|edge| (hour - edge).abs())
+            .fold(f64::INFINITY, f64::min);
+        (1.0 - from_twilight / twilight_hours.max(f64::EPSILON)).max(0.0)
+    }
+}
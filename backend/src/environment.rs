@@ -0,0 +1,225 @@
+use geo::Point;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Values on a regular lat/lon grid, e.g. sea surface temperature. Cells
+/// without data, such as land, hold NaN.
+#[derive(Debug, Clone)]
+pub struct Raster {
+    min_lon: f64,
+    min_lat: f64,
+    /// Spacing between grid points in degrees, the same in both directions
+    cell_deg: f64,
+    width: usize,
+    height: usize,
+    /// Row by row, south to north
+    values: Vec<f32>,
+}
+
+impl Raster {
+    /// Reads a grid from a CSV file with a header naming `lat`/`latitude` and
+    /// `lon`/`longitude` columns, taking the values from the last other column.
+    /// Rows that don't parse, like the units row of an ERDDAP export, and
+    /// empty or NaN values are skipped. The points have to lie on a regular grid.
+    pub fn load_csv(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Self::load_csv_column(path, None)
+    }
+
+    /// `load_csv` taking the values from the column named `value_column`
+    /// instead, when given.
+    pub fn load_csv_column(
+        path: impl AsRef<Path>,
+        value_column: Option<&str>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        let header: Vec<String> = lines
+            .next()
+            .ok_or("empty file")?
+            .split(',')
+            .map(|column| column.trim().to_ascii_lowercase())
+            .collect();
+        let column = |names: &[&str]| {
+            header
+                .iter()
+                .position(|column| names.contains(&column.as_str()))
+        };
+        let lat_column = column(&["lat", "latitude"]).ok_or("no latitude column")?;
+        let lon_column = column(&["lon", "longitude"]).ok_or("no longitude column")?;
+        let value_column = match value_column {
+            Some(name) => column(&[name]).ok_or_else(|| format!("no {} column", name))?,
+            None => (0..header.len())
+                .rev()
+                .find(|&i| i != lat_column && i != lon_column && header[i] != "time")
+                .ok_or("no value column")?,
+        };
+
+        let mut points = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let parse = |i: usize| fields.get(i).and_then(|field| field.parse::<f64>().ok());
+            if let (Some(lat), Some(lon), Some(value)) =
+                (parse(lat_column), parse(lon_column), parse(value_column))
+                && !value.is_nan()
+            {
+                points.push((lon, lat, value));
+            }
+        }
+        Self::from_points(&points)
+    }
+
+    /// Builds the grid spanned by `(lon, lat, value)` points, with the spacing
+    /// of the closest two.
+    fn from_points(points: &[(f64, f64, f64)]) -> Result<Self, Box<dyn Error>> {
+        if points.is_empty() {
+            return Err("no values in the grid".into());
+        }
+        let min_lon = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let min_lat = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_lon = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let max_lat = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let smallest_gap = |mut coordinates: Vec<f64>| {
+            coordinates.sort_by(f64::total_cmp);
+            coordinates
+                .windows(2)
+                .map(|pair| pair[1] - pair[0])
+                .filter(|gap| *gap > 1e-9)
+                .fold(f64::INFINITY, f64::min)
+        };
+        let cell_deg = f64::min(
+            smallest_gap(points.iter().map(|p| p.0).collect()),
+            smallest_gap(points.iter().map(|p| p.1).collect()),
+        );
+        if !cell_deg.is_finite() {
+            return Err("a grid needs at least two distinct coordinates".into());
+        }
+
+        let width = ((max_lon - min_lon) / cell_deg).round() as usize + 1;
+        let height = ((max_lat - min_lat) / cell_deg).round() as usize + 1;
+        let mut values = vec![f32::NAN; width * height];
+        for &(lon, lat, value) in points {
+            let column = (lon - min_lon) / cell_deg;
+            let row = (lat - min_lat) / cell_deg;
+            if (column - column.round()).abs() > 0.01 || (row - row.round()).abs() > 0.01 {
+                return Err(format!("({}, {}) is off the grid", lon, lat).into());
+            }
+            values[row.round() as usize * width + column.round() as usize] = value as f32;
+        }
+        Ok(Self {
+            min_lon,
+            min_lat,
+            cell_deg,
+            width,
+            height,
+            values,
+        })
+    }
+
+    /// Up to `count` positions of the highest values, highest first, each at
+    /// least `min_spacing_deg` from those picked before it.
+    pub fn hotspots(&self, count: usize, min_spacing_deg: f64) -> Vec<Point<f64>> {
+        let mut cells: Vec<(usize, f32)> = self
+            .values
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, value)| !value.is_nan())
+            .collect();
+        cells.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut picked: Vec<Point<f64>> = Vec::with_capacity(count);
+        for (index, _) in cells {
+            if picked.len() == count {
+                break;
+            }
+            let position = Point::new(
+                self.min_lon + (index % self.width) as f64 * self.cell_deg,
+                self.min_lat + (index / self.width) as f64 * self.cell_deg,
+            );
+            let spaced = picked.iter().all(|other| {
+                (other.x() - position.x()).hypot(other.y() - position.y()) >= min_spacing_deg
+            });
+            if spaced {
+                picked.push(position);
+            }
+        }
+        picked
+    }
+
+    fn at(&self, column: usize, row: usize) -> Option<f64> {
+        let value = *self.values.get(row * self.width + column)?;
+        (!value.is_nan()).then_some(value as f64)
+    }
+
+    /// Bilinear interpolation between the surrounding grid points, leaving out
+    /// those without data. `None` off the grid or with no data around.
+    pub fn sample(&self, point: Point<f64>) -> Option<f64> {
+        let x = (point.x() - self.min_lon) / self.cell_deg;
+        let y = (point.y() - self.min_lat) / self.cell_deg;
+        if x < 0.0 || y < 0.0 || x > (self.width - 1) as f64 || y > (self.height - 1) as f64 {
+            return None;
+        }
+        let (column, row) = (x.floor() as usize, y.floor() as usize);
+        let (fx, fy) = (x - column as f64, y - row as f64);
+        let corners = [
+            (column, row, (1.0 - fx) * (1.0 - fy)),
+            (column + 1, row, fx * (1.0 - fy)),
+            (column, row + 1, (1.0 - fx) * fy),
+            (column + 1, row + 1, fx * fy),
+        ];
+        let (sum, weights) = corners
+            .iter()
+            .filter(|(c, r, _)| *c < self.width && *r < self.height)
+            .filter_map(|&(c, r, weight)| Some((self.at(c, r)? * weight, weight)))
+            .fold((0.0, 0.0), |(sum, weights), (value, weight)| {
+                (sum + value, weights + weight)
+            });
+        (weights > 1e-9).then(|| sum / weights)
+    }
+
+    /// Change per degree east and north around `point`, `None` where the
+    /// grid has no data on both sides.
+    pub fn gradient(&self, point: Point<f64>) -> Option<(f64, f64)> {
+        let h = self.cell_deg;
+        let sample = |dx: f64, dy: f64| self.sample(Point::new(point.x() + dx, point.y() + dy));
+        let dx = (sample(h, 0.0)? - sample(-h, 0.0)?) / (2.0 * h);
+        let dy = (sample(0.0, h)? - sample(0.0, -h)?) / (2.0 * h);
+        Some((dx, dy))
+    }
+}
+
+/// Surface currents as eastward `u` and northward `v` components in m/s.
+#[derive(Debug, Clone)]
+pub struct CurrentField {
+    pub u: Raster,
+    pub v: Raster,
+}
+
+impl CurrentField {
+    /// Reads a CSV file like `Raster::load_csv` with `u` and `v` columns,
+    /// the layout of an ERDDAP export of OSCAR.
+    pub fn load_csv(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        Ok(Self {
+            u: Raster::load_csv_column(path, Some("u"))?,
+            v: Raster::load_csv_column(path, Some("v"))?,
+        })
+    }
+
+    /// `(u, v)` at `point`, `None` where there is no data.
+    pub fn sample(&self, point: Point<f64>) -> Option<(f64, f64)> {
+        Some((self.u.sample(point)?, self.v.sample(point)?))
+    }
+}
+
+/// Ocean conditions the sharks respond to, each layer optional.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// Sea surface temperature in °C
+    pub sst: Option<Arc<Raster>>,
+    /// Chlorophyll-a concentration in mg/m³, see `ProductivityConfig`
+    pub chlorophyll: Option<Arc<Raster>>,
+    /// Sweeps sharks along, see `SimulationConfig::current_drift_scale`
+    pub currents: Option<Arc<CurrentField>>,
+}
@@ -0,0 +1,185 @@
+use geo::Point;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::{Edge, Simulation};
+
+/// Events are numbered per stream. Events a client never got, e.g. after
+/// falling too far behind, are listed in the next batch, see `EventGap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventStream {
+    /// Sharks appearing and disappearing
+    Sharks,
+    Buoys,
+    /// Hot events starting and ending, see `HotEvents`
+    HotEvents,
+}
+
+/// Something that happened in a tick.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    /// Ran out of energy and was removed
+    Starved {
+        shark_id: u64,
+    },
+    /// Swam off an edge of an open map and was removed
+    LeftMap {
+        shark_id: u64,
+        edge: Edge,
+    },
+    /// Swam in from an edge of an open map
+    Arrived {
+        shark_id: u64,
+        edge: Edge,
+    },
+    /// Came within range of a buoy
+    PassBy {
+        buoy: String,
+        shark_id: u64,
+    },
+    /// A hot event began, drawing sharks to `position` for `duration_secs`
+    HotEventStarted {
+        id: u64,
+        kind: String,
+        /// E.g. "bait ball near 32N 64W"
+        description: String,
+        position: Point<f64>,
+        duration_secs: f64,
+    },
+    HotEventEnded {
+        id: u64,
+        kind: String,
+    },
+}
+
+impl EventKind {
+    pub fn stream(&self) -> EventStream {
+        match self {
+            EventKind::Starved { .. } | EventKind::LeftMap { .. } | EventKind::Arrived { .. } => {
+                EventStream::Sharks
+            }
+            EventKind::PassBy { .. } => EventStream::Buoys,
+            EventKind::HotEventStarted { .. } | EventKind::HotEventEnded { .. } => {
+                EventStream::HotEvents
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub stream: EventStream,
+    /// Counts up from 0 within `stream`
+    pub seq: u64,
+    pub tick: u64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// Events of the ticks since the last broadcast, waiting to go out with the
+/// next frame.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    next_seq: BTreeMap<EventStream, u64>,
+    pending: Vec<Event>,
+}
+
+impl EventLog {
+    pub fn emit(&mut self, tick: u64, kind: EventKind) {
+        let stream = kind.stream();
+        let seq = self.next_seq.entry(stream).or_default();
+        self.pending.push(Event {
+            stream,
+            seq: *seq,
+            tick,
+            kind,
+        });
+        *seq += 1;
+    }
+
+    /// The events for the frame about to be broadcast, oldest first.
+    pub fn take(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Events of `stream` from `first_seq` to `last_seq` that a client never got,
+/// because the frames they came with were dropped for it.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EventGap {
+    pub stream: EventStream,
+    pub first_seq: u64,
+    pub last_seq: u64,
+}
+
+/// Follows the frames a connection takes in, to tell which events were lost
+/// with the frames it never saw.
+#[derive(Debug, Default)]
+pub struct EventCursor {
+    /// The `seq` each stream's next event should have, `None` before the first frame
+    next_seq: Option<BTreeMap<EventStream, u64>>,
+}
+
+impl EventCursor {
+    /// Takes in the frame `snapshot`, adding the events lost since the last
+    /// one to `gaps`.
+    pub fn take_in(&mut self, snapshot: &Simulation, gaps: &mut Vec<EventGap>) {
+        let after = &snapshot.event_log.next_seq;
+        if let Some(seen) = &self.next_seq {
+            for (&stream, &end) in after {
+                let in_frame = snapshot
+                    .events
+                    .iter()
+                    .filter(|event| event.stream == stream)
+                    .count() as u64;
+                let first_seq = seen.get(&stream).copied().unwrap_or(0);
+                let start = end.saturating_sub(in_frame);
+                if first_seq < start {
+                    gaps.push(EventGap {
+                        stream,
+                        first_seq,
+                        last_seq: start - 1,
+                    });
+                }
+            }
+        }
+        self.next_seq = Some(after.clone());
+    }
+}
+
+#[derive(Serialize)]
+struct EventBatch<'a> {
+    events: EventBatchBody<'a>,
+}
+
+#[derive(Serialize)]
+struct EventBatchBody<'a> {
+    /// `Simulation::frame` of the frame sent just before
+    frame: u64,
+    tick: u64,
+    events: &'a [Event],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    missed: &'a [EventGap],
+}
+
+/// The message a connection sends right after frame `frame`, with every event
+/// up to it that the client hasn't had, so no effect arrives before the state
+/// it refers to: `{"events": {"frame": 7, "tick": 21, "events": [...]}}`.
+/// Events lost since the last batch are listed under `"missed"`.
+pub fn serialize_events(
+    frame: u64,
+    tick: u64,
+    events: &[Event],
+    missed: &[EventGap],
+) -> serde_json::Result<String> {
+    serde_json::to_string(&EventBatch {
+        events: EventBatchBody {
+            frame,
+            tick,
+            events,
+            missed,
+        },
+    })
+}
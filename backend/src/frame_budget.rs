@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// What the physics loop does when a tick takes longer than its 1/TPS budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrunPolicy {
+    /// Keep stepping by the full dt; simulated time falls behind the wall clock
+    #[default]
+    DilateTime,
+    /// Don't publish the frame of an overrunning tick, so clients get fewer frames
+    SkipBroadcast,
+    /// Split the next ticks into fewer physics sub-steps, down to one,
+    /// and add them back once ticks fit comfortably again
+    FewerSubsteps,
+}
+
+/// What happened in the last tick, as reported in `FrameStats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameAction {
+    #[default]
+    OnTime,
+    TimeDilated,
+    BroadcastSkipped,
+    SubstepsReduced,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct FrameStats {
+    /// Wall time the last tick took, in milliseconds
+    pub last_tick_ms: f64,
+    /// Ticks so far that went over budget
+    pub overruns: u64,
+    pub last_action: FrameAction,
+    /// Physics sub-steps per tick currently in use
+    pub substeps: u32,
+    /// Ticks actually run per second, below the tick rate when they can't
+    /// keep up, see `Scheduler`
+    pub measured_tps: f64,
+}
+
+/// Tracks tick times against the budget and applies the overrun policy.
+#[derive(Debug, Clone)]
+pub struct FrameBudget {
+    policy: OverrunPolicy,
+    max_substeps: u32,
+    stats: FrameStats,
+}
+
+impl FrameBudget {
+    pub fn new(policy: OverrunPolicy, max_substeps: u32) -> Self {
+        let max_substeps = max_substeps.max(1);
+        Self {
+            policy,
+            max_substeps,
+            stats: FrameStats {
+                substeps: max_substeps,
+                ..FrameStats::default()
+            },
+        }
+    }
+
+    pub fn substeps(&self) -> u32 {
+        self.stats.substeps
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+
+    /// Records how long a tick took and decides what to do about it.
+    pub fn record(&mut self, elapsed: Duration, budget: Duration) -> FrameAction {
+        self.stats.last_tick_ms = elapsed.as_secs_f64() * 1000.0;
+
+        let action = if elapsed <= budget {
+            // only add a sub-step back when there is clearly room for it
+            if self.policy == OverrunPolicy::FewerSubsteps
+                && self.stats.substeps < self.max_substeps
+                && elapsed < budget / 2
+            {
+                self.stats.substeps += 1;
+            }
+            FrameAction::OnTime
+        } else {
+            self.stats.overruns += 1;
+            match self.policy {
+                OverrunPolicy::DilateTime => FrameAction::TimeDilated,
+                OverrunPolicy::SkipBroadcast => FrameAction::BroadcastSkipped,
+                OverrunPolicy::FewerSubsteps if self.stats.substeps > 1 => {
+                    self.stats.substeps -= 1;
+                    FrameAction::SubstepsReduced
+                }
+                // nothing left to cut, time has to give
+                OverrunPolicy::FewerSubsteps => FrameAction::TimeDilated,
+            }
+        };
+        self.stats.last_action = action;
+        action
+    }
+}
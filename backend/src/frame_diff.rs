@@ -0,0 +1,500 @@
+//! Round trips of every frame encoding against `serialize_snapshot`, so a new
+//! or changed format can't quietly lose or mangle what it carries.
+
+use serde_json::{Value, json};
+
+/// A place two frames differ, `None` where one of them lacks the field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameDifference {
+    /// E.g. `sharks[3].position.x`
+    pub path: String,
+    pub a: Option<Value>,
+    pub b: Option<Value>,
+}
+
+/// Every field in which the decoded frames `a` and `b` differ, depth first.
+/// Numbers count as equal by value, so `1` and `1.0` are the same.
+pub fn frame_diff(a: &Value, b: &Value) -> Vec<FrameDifference> {
+    let mut differences = Vec::new();
+    diff_into(String::new(), Some(a), Some(b), &mut differences);
+    differences
+}
+
+fn diff_into(
+    path: String,
+    a: Option<&Value>,
+    b: Option<&Value>,
+    differences: &mut Vec<FrameDifference>,
+) {
+    let field = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (a, b) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            for key in a.keys().chain(b.keys().filter(|key| !a.contains_key(*key))) {
+                diff_into(field(key), a.get(key), b.get(key), differences);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for i in 0..a.len().max(b.len()) {
+                diff_into(format!("{}[{}]", path, i), a.get(i), b.get(i), differences);
+            }
+        }
+        (Some(Value::Number(a)), Some(Value::Number(b))) if a.as_f64() == b.as_f64() => {}
+        (a, b) if a == b => {}
+        (a, b) => differences.push(FrameDifference {
+            path,
+            a: a.cloned(),
+            b: b.cloned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow_stream::ArrowStream;
+    use crate::snapshot::{AGGREGATE_CELL_DEG, PACKED_SHARK_BYTES, QUANTIZE_SCALE};
+    use crate::{
+        FieldMask, Shark, Simulation, pack_columnar, pack_snapshot, serialize_aggregated,
+        serialize_batch, serialize_columnar, serialize_delta, serialize_history,
+        serialize_quantized, serialize_snapshot,
+    };
+    use arrow_array::{Array, BooleanArray, Float64Array, StringArray, UInt64Array};
+    use arrow_ipc::reader::StreamReader;
+    use geo::Point;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    /// Sharks all over the map, so the f32 and quantized formats lose
+    /// something on every value.
+    fn simulation(seed: u64) -> Simulation {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut simulation = Simulation::new(40, &mut rng, Arc::default(), Vec::new()).unwrap();
+        simulation.tick = 1200 + seed;
+        simulation.server_time_ms = 1_700_000_000_123.25 + seed as f64;
+        for (i, shark) in simulation.sharks.iter_mut().enumerate() {
+            shark.reported_rotation_rad = rng.random_range(-3.2..3.2);
+            shark.school_id = (i % 3 == 0).then_some(i as u64 / 3);
+            shark.informed = i % 2 == 0;
+        }
+        simulation
+    }
+
+    /// The full frame as clients get it
+    fn reference(simulation: &Simulation) -> Value {
+        serde_json::from_str(&serialize_snapshot(simulation, &FieldMask::all()).unwrap()).unwrap()
+    }
+
+    /// `frame` with only `fields` of each shark, and the tick and time when `header`.
+    fn project(frame: &Value, fields: &[&str], header: bool) -> Value {
+        let sharks: Vec<Value> = frame["sharks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|shark| {
+                let picked = fields
+                    .iter()
+                    .map(|field| (field.to_string(), shark[*field].clone()));
+                Value::Object(
+                    std::iter::once(("id".to_string(), shark["id"].clone()))
+                        .chain(picked)
+                        .collect(),
+                )
+            })
+            .collect();
+        if header {
+            json!({
+                "tick": frame["tick"],
+                "server_time_ms": frame["server_time_ms"],
+                "sharks": sharks,
+            })
+        } else {
+            json!({ "sharks": sharks })
+        }
+    }
+
+    fn shark(id: f64, x: f64, y: f64, heading: f64) -> Value {
+        json!({"id": id, "position": {"x": x, "y": y}, "reported_rotation_rad": heading})
+    }
+
+    const POSITION_AND_HEADING: [&str; 2] = ["position", "reported_rotation_rad"];
+
+    fn assert_lossless(decoded: &Value, expected: &Value) {
+        let differences = frame_diff(decoded, expected);
+        assert!(
+            differences.is_empty(),
+            "{:#?}",
+            &differences[..differences.len().min(5)]
+        );
+    }
+
+    /// Every difference is a number off by at most `bound` of the expected value.
+    fn assert_within(decoded: &Value, expected: &Value, bound: impl Fn(f64) -> f64) {
+        for difference in frame_diff(decoded, expected) {
+            let (Some(a), Some(b)) = (
+                difference.a.as_ref().and_then(Value::as_f64),
+                difference.b.as_ref().and_then(Value::as_f64),
+            ) else {
+                panic!("not just rounding: {:?}", difference);
+            };
+            assert!(
+                (a - b).abs() <= bound(b),
+                "{} off by {}: {:?}",
+                difference.path,
+                (a - b).abs(),
+                difference
+            );
+        }
+    }
+
+    /// An f32 of `value` is within half an f32 ulp of it.
+    fn f32_bound(value: f64) -> f64 {
+        value.abs() * f32::EPSILON as f64 / 2.0
+    }
+
+    /// Reads the header `pack_snapshot` and `pack_columnar` share.
+    fn packed_header(bytes: &[u8]) -> (u64, f64, usize) {
+        let tick = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let time = f64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        let count = u32::from_le_bytes(bytes[16..20].try_into().unwrap()) as usize;
+        assert_eq!(bytes.len(), 20 + count * PACKED_SHARK_BYTES);
+        (tick, time, count)
+    }
+
+    fn word(bytes: &[u8], at: usize) -> [u8; 4] {
+        bytes[at..at + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn frame_diff_finds_every_change() {
+        let a = json!({"tick": 1, "sharks": [{"id": 1, "position": {"x": 1.0, "y": 2.0}}]});
+        let b = json!({"tick": 1.0, "sharks": [{"id": 1, "position": {"x": 1.5, "y": 2.0}}, {"id": 2}], "extra": true});
+        let paths: Vec<String> = frame_diff(&a, &b).into_iter().map(|d| d.path).collect();
+        assert_eq!(paths, ["sharks[0].position.x", "sharks[1]", "extra"]);
+        assert!(frame_diff(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn full_json_round_trips() {
+        let simulation = simulation(1);
+        let sharks: Vec<Shark> =
+            serde_json::from_value(reference(&simulation)["sharks"].clone()).unwrap();
+        let mut reread = simulation.clone();
+        reread.sharks = sharks;
+        assert_lossless(&reference(&reread), &reference(&simulation));
+    }
+
+    #[test]
+    fn masked_json_keeps_the_masked_fields() {
+        let simulation = simulation(2);
+        let mask = FieldMask::from_query("fields=position,speed,school_id");
+        let decoded: Value =
+            serde_json::from_str(&serialize_snapshot(&simulation, &mask).unwrap()).unwrap();
+        assert_lossless(
+            &decoded,
+            &project(
+                &reference(&simulation),
+                &["position", "speed", "school_id"],
+                false,
+            ),
+        );
+    }
+
+    #[test]
+    fn delta_applied_to_the_previous_frame_gives_the_current() {
+        let previous = simulation(3);
+        let mut current = previous.clone();
+        current.tick += 1;
+        current.sharks.remove(5);
+        current.sharks[0].position = Point::new(12.5, -7.25);
+        current.sharks[1].reported_rotation_rad += 0.5;
+        let mut arrived = simulation(4).sharks[0];
+        arrived.id = 1000;
+        current.sharks.push(arrived);
+
+        let delta: Value =
+            serde_json::from_str(&serialize_delta(&previous, &current, &FieldMask::all()).unwrap())
+                .unwrap();
+        let delta = &delta["delta"];
+        let mut sharks: BTreeMap<u64, Value> =
+            project(&reference(&previous), &POSITION_AND_HEADING, false)["sharks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|shark| (shark["id"].as_u64().unwrap(), shark.clone()))
+                .collect();
+        for id in delta["removed"].as_array().unwrap() {
+            sharks.remove(&id.as_u64().unwrap());
+        }
+        for moved in delta["moved"].as_array().unwrap() {
+            sharks.insert(moved["id"].as_u64().unwrap(), moved.clone());
+        }
+        for added in delta["added"].as_array().unwrap() {
+            let added = project(&json!({ "sharks": [added] }), &POSITION_AND_HEADING, false);
+            let added = &added["sharks"][0];
+            sharks.insert(added["id"].as_u64().unwrap(), added.clone());
+        }
+
+        let mut expected = project(&reference(&current), &POSITION_AND_HEADING, false);
+        expected["sharks"]
+            .as_array_mut()
+            .unwrap()
+            .sort_by_key(|shark| shark["id"].as_u64());
+        assert_eq!(delta["tick"], current.tick);
+        assert_lossless(
+            &json!({ "sharks": sharks.into_values().collect::<Vec<_>>() }),
+            &expected,
+        );
+    }
+
+    #[test]
+    fn packed_loses_no_more_than_f32() {
+        let simulation = simulation(5);
+        let bytes = pack_snapshot(&simulation);
+        let (tick, time, count) = packed_header(&bytes);
+        let sharks: Vec<Value> = bytes[20..]
+            .chunks_exact(PACKED_SHARK_BYTES)
+            .map(|bytes| {
+                let float = |at| f32::from_le_bytes(word(bytes, at)) as f64;
+                shark(
+                    u32::from_le_bytes(word(bytes, 0)) as f64,
+                    float(4),
+                    float(8),
+                    float(12),
+                )
+            })
+            .collect();
+        assert_eq!(sharks.len(), count);
+        let decoded = json!({"tick": tick, "server_time_ms": time, "sharks": sharks});
+        assert_within(
+            &decoded,
+            &project(&reference(&simulation), &POSITION_AND_HEADING, true),
+            f32_bound,
+        );
+    }
+
+    #[test]
+    fn columnar_packed_loses_no_more_than_f32() {
+        let simulation = simulation(6);
+        let bytes = pack_columnar(&simulation);
+        let (tick, time, count) = packed_header(&bytes);
+        let column = |index: usize, i: usize| word(&bytes, 20 + (index * count + i) * 4);
+        let sharks: Vec<Value> = (0..count)
+            .map(|i| {
+                let float = |index| f32::from_le_bytes(column(index, i)) as f64;
+                shark(
+                    u32::from_le_bytes(column(0, i)) as f64,
+                    float(1),
+                    float(2),
+                    float(3),
+                )
+            })
+            .collect();
+        let decoded = json!({"tick": tick, "server_time_ms": time, "sharks": sharks});
+        assert_within(
+            &decoded,
+            &project(&reference(&simulation), &POSITION_AND_HEADING, true),
+            f32_bound,
+        );
+    }
+
+    #[test]
+    fn columnar_json_round_trips() {
+        let simulation = simulation(7);
+        let frame: Value = serde_json::from_str(&serialize_columnar(&simulation).unwrap()).unwrap();
+        let columns = &frame["columnar"];
+        let column = |name: &str, i: usize| columns[name][i].as_f64().unwrap();
+        let sharks: Vec<Value> = (0..columns["ids"].as_array().unwrap().len())
+            .map(|i| {
+                shark(
+                    column("ids", i),
+                    column("xs", i),
+                    column("ys", i),
+                    column("headings", i),
+                )
+            })
+            .collect();
+        let decoded = json!({"tick": columns["tick"], "server_time_ms": columns["server_time_ms"], "sharks": sharks});
+        assert_lossless(
+            &decoded,
+            &project(&reference(&simulation), &POSITION_AND_HEADING, true),
+        );
+    }
+
+    #[test]
+    fn quantized_loses_at_most_half_a_step() {
+        let simulation = simulation(8);
+        let frame: Value =
+            serde_json::from_str(&serialize_quantized(&simulation).unwrap()).unwrap();
+        let quantized = &frame["quantized"];
+        let sharks: Vec<Value> = quantized["sharks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|shark| {
+                let value = |i: usize| shark[i].as_f64().unwrap();
+                self::shark(value(0), value(1), value(2), value(3))
+            })
+            .collect();
+        let decoded = json!({"tick": quantized["tick"], "server_time_ms": quantized["server_time_ms"], "sharks": sharks});
+        // and a little for the decimal round trip
+        let half_step = 0.5 / QUANTIZE_SCALE + 1e-9;
+        assert_within(
+            &decoded,
+            &project(&reference(&simulation), &POSITION_AND_HEADING, true),
+            |_| half_step,
+        );
+    }
+
+    #[test]
+    fn aggregated_keeps_every_shark_in_its_cell() {
+        let simulation = simulation(9);
+        let frame: Value =
+            serde_json::from_str(&serialize_aggregated(&simulation).unwrap()).unwrap();
+        let cells = frame["aggregated"]["cells"].as_array().unwrap();
+        let counted: u64 = cells
+            .iter()
+            .map(|cell| cell["count"].as_u64().unwrap())
+            .sum();
+        assert_eq!(counted, simulation.sharks.len() as u64);
+        for cell in cells {
+            let (x, y) = (
+                cell["position"]["x"].as_f64().unwrap(),
+                cell["position"]["y"].as_f64().unwrap(),
+            );
+            let in_cell = simulation.sharks.iter().filter(|shark| {
+                (shark.position.x() / AGGREGATE_CELL_DEG).floor()
+                    == (x / AGGREGATE_CELL_DEG).floor()
+                    && (shark.position.y() / AGGREGATE_CELL_DEG).floor()
+                        == (y / AGGREGATE_CELL_DEG).floor()
+            });
+            assert_eq!(in_cell.count() as u64, cell["count"].as_u64().unwrap());
+        }
+    }
+
+    #[test]
+    fn batch_round_trips_every_frame() {
+        let frames: Vec<Arc<Simulation>> =
+            (10..13).map(|seed| Arc::new(simulation(seed))).collect();
+        let frame: Value = serde_json::from_str(&serialize_batch(&frames, None).unwrap()).unwrap();
+        let batch = &frame["batch"];
+        for (f, simulation) in frames.iter().enumerate() {
+            let columns = &batch["sharks"][f];
+            let column = |name: &str, i: usize| columns[name][i].as_f64().unwrap();
+            let sharks: Vec<Value> = (0..columns["id"].as_array().unwrap().len())
+                .map(|i| {
+                    shark(
+                        column("id", i),
+                        column("lon", i),
+                        column("lat", i),
+                        column("reported_rotation_rad", i),
+                    )
+                })
+                .collect();
+            let decoded = json!({"tick": batch["tick"][f], "server_time_ms": batch["server_time_ms"][f], "sharks": sharks});
+            assert_lossless(
+                &decoded,
+                &project(&reference(simulation), &POSITION_AND_HEADING, true),
+            );
+        }
+    }
+
+    #[test]
+    fn history_holds_the_live_frames() {
+        let frames: Vec<Arc<Simulation>> =
+            (13..15).map(|seed| Arc::new(simulation(seed))).collect();
+        let history: Value =
+            serde_json::from_str(&serialize_history(&frames, &FieldMask::all()).unwrap()).unwrap();
+        let expected: Vec<Value> = frames.iter().map(|frame| reference(frame)).collect();
+        assert_lossless(&history["history"], &Value::Array(expected));
+    }
+
+    #[test]
+    fn arrow_round_trips() {
+        let frames: Vec<Simulation> = (15..17).map(simulation).collect();
+        let mut stream = ArrowStream::new().unwrap();
+        let bytes: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| stream.encode(frame).unwrap())
+            .collect();
+        let reader = StreamReader::try_new(bytes.as_slice(), None).unwrap();
+        let fields = [
+            "species",
+            "position",
+            "rotation_rad",
+            "reported_rotation_rad",
+            "speed",
+            "school_id",
+            "informed",
+        ];
+
+        for (batch, simulation) in reader.zip(&frames) {
+            let batch = batch.unwrap();
+            let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+            let floats = |name: &str| {
+                column(name)
+                    .as_any()
+                    .downcast_ref::<Float64Array>()
+                    .unwrap()
+                    .clone()
+            };
+            let ints = |name: &str| {
+                column(name)
+                    .as_any()
+                    .downcast_ref::<UInt64Array>()
+                    .unwrap()
+                    .clone()
+            };
+            let (tick, time, id, school) = (
+                ints("tick"),
+                floats("server_time_ms"),
+                ints("id"),
+                ints("school_id"),
+            );
+            let (lon, lat, rotation, heading, speed) = (
+                floats("lon"),
+                floats("lat"),
+                floats("rotation_rad"),
+                floats("reported_rotation_rad"),
+                floats("speed"),
+            );
+            let species = column("species")
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .clone();
+            let informed = column("informed")
+                .as_any()
+                .downcast_ref::<BooleanArray>()
+                .unwrap()
+                .clone();
+            let sharks: Vec<Value> = (0..batch.num_rows())
+                .map(|i| {
+                    assert_eq!(tick.value(i), simulation.tick);
+                    assert_eq!(time.value(i), simulation.server_time_ms);
+                    json!({
+                        "id": id.value(i),
+                        "species": species.value(i),
+                        "position": {"x": lon.value(i), "y": lat.value(i)},
+                        "rotation_rad": rotation.value(i),
+                        "reported_rotation_rad": heading.value(i),
+                        "speed": speed.value(i),
+                        "school_id": (!school.is_null(i)).then(|| school.value(i)),
+                        "informed": informed.value(i),
+                    })
+                })
+                .collect();
+            assert_lossless(
+                &json!({ "sharks": sharks }),
+                &project(&reference(simulation), &fields, false),
+            );
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::{Bytes, Utf8Bytes};
+
+use crate::{
+    FieldMask, Simulation, Snapshots, pack_columnar, pack_snapshot, serialize_columnar,
+    serialize_events, serialize_snapshot,
+};
+
+/// Frames a connection may fall behind before it starts missing them
+pub const FRAME_BUFFER: usize = 16;
+
+/// A published snapshot along with its encodings every client can share.
+/// Clients with narrower field masks, deltas or a reduced QoS level still
+/// serialize their own from `snapshot`.
+#[derive(Debug)]
+pub struct Frame {
+    pub snapshot: Arc<Simulation>,
+    /// `serialize_snapshot` with the full field mask
+    pub json: Utf8Bytes,
+    /// `pack_snapshot`
+    pub packed: Bytes,
+    /// `serialize_events` of the snapshot's events, `None` without any
+    pub events: Option<Utf8Bytes>,
+    /// Left to the first client that asks, few do
+    columnar: OnceLock<Utf8Bytes>,
+    columnar_packed: OnceLock<Bytes>,
+}
+
+impl Frame {
+    pub fn new(snapshot: Arc<Simulation>) -> Self {
+        Self {
+            json: serialize_snapshot(&snapshot, &FieldMask::all())
+                .unwrap()
+                .into(),
+            packed: pack_snapshot(&snapshot).into(),
+            events: (!snapshot.events.is_empty()).then(|| {
+                serialize_events(snapshot.frame, snapshot.tick, &snapshot.events, &[])
+                    .unwrap()
+                    .into()
+            }),
+            columnar: OnceLock::new(),
+            columnar_packed: OnceLock::new(),
+            snapshot,
+        }
+    }
+
+    /// `serialize_columnar`
+    pub fn columnar(&self) -> Utf8Bytes {
+        self.columnar
+            .get_or_init(|| serialize_columnar(&self.snapshot).unwrap().into())
+            .clone()
+    }
+
+    /// `pack_columnar`
+    pub fn columnar_packed(&self) -> Bytes {
+        self.columnar_packed
+            .get_or_init(|| pack_columnar(&self.snapshot).into())
+            .clone()
+    }
+}
+
+/// Encodes every snapshot once and broadcasts it to the connections, so the
+/// cost of a frame doesn't grow with the number of clients. Connections
+/// subscribe through the returned handle, which stops upgrading and their
+/// receivers close once the physics thread stops.
+pub fn spawn_frame_publisher(mut snapshots: Snapshots) -> broadcast::WeakSender<Arc<Frame>> {
+    let (publisher, _) = broadcast::channel(FRAME_BUFFER);
+    let frames = publisher.downgrade();
+    tokio::spawn(async move {
+        // only ends when the physics thread stops
+        while let Some(snapshot) = snapshots.changed().await {
+            // nobody listening is fine, clients come and go
+            let _ = publisher.send(Arc::new(Frame::new(snapshot)));
+        }
+    });
+    frames
+}
@@ -7,16 +7,29 @@ pub fn random_point<R: Rng>(rng: &mut R) -> Point<f64> {
     Point::new(lon, lat)
 }
 
-pub fn random_point_in_water<R: Rng>(rng: &mut R, land_polygons: &[Polygon<f64>]) -> Point<f64> {
-    loop {
+/// Random points tried before concluding there is no water
+const MAX_WATER_TRIES: usize = 100_000;
+
+/// A random point clear of every polygon. Fails when `MAX_WATER_TRIES` points
+/// in a row all land on or next to land.
+pub fn random_point_in_water<R: Rng>(
+    rng: &mut R,
+    land_polygons: &[Polygon<f64>],
+) -> Result<Point<f64>, String> {
+    for _ in 0..MAX_WATER_TRIES {
         let random_point = random_point(rng);
+        // scaled about its centroid a concave coast also shifts, so the
+        // polygon itself has to be checked besides the margin around it
         let is_in_water = !land_polygons.iter().any(|poly| {
-            let poly = poly.scale_xy(1.1, 1.1);
-            poly.contains(&random_point)
+            poly.contains(&random_point) || poly.scale_xy(1.1, 1.1).contains(&random_point)
         });
 
         if is_in_water {
-            return random_point;
+            return Ok(random_point);
         }
     }
+    Err(format!(
+        "no water in {} random points, the land covers the whole map",
+        MAX_WATER_TRIES
+    ))
 }
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::tick::broadcast_rate;
+use crate::tracks::decimated_tracks;
+use crate::{Simulation, Track, TrackDecimation};
+
+/// Most seconds of history a client may ask for, far beyond any kept
+pub const MAX_HISTORY_SECONDS: u64 = 24 * 60 * 60;
+
+/// The most recent published frames, so new clients can catch up on what just happened.
+#[derive(Debug)]
+pub struct FrameHistory {
+    frames: VecDeque<Arc<Simulation>>,
+    capacity: usize,
+    /// Applied to the tracks handed out, see `tracks`
+    decimation: TrackDecimation,
+}
+
+/// Shared between the physics thread, which records, and the connections, which read.
+pub type SharedHistory = Arc<Mutex<FrameHistory>>;
+
+impl FrameHistory {
+    /// Keeps the last `seconds` worth of broadcast frames, 0 keeps nothing.
+    pub fn new(seconds: u64, decimation: TrackDecimation) -> Self {
+        let capacity = seconds.saturating_mul(broadcast_rate()) as usize;
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            decimation,
+        }
+    }
+
+    pub fn push(&mut self, frame: Arc<Simulation>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Frames from the last `seconds` up to and including `until_tick`, oldest
+    /// first, every kept one without `seconds`.
+    pub fn window(&self, seconds: Option<u64>, until_tick: u64) -> Vec<Arc<Simulation>> {
+        let frames: Vec<_> = self
+            .frames
+            .iter()
+            .filter(|frame| frame.tick <= until_tick)
+            .cloned()
+            .collect();
+        let skip = match seconds {
+            Some(seconds) => {
+                let wanted = seconds.saturating_mul(broadcast_rate()) as usize;
+                frames.len().saturating_sub(wanted)
+            }
+            None => 0,
+        };
+        frames.into_iter().skip(skip).collect()
+    }
+
+    /// The sharks' paths over the same frames as `window`, decimated, and
+    /// densified along great circles every `great_circle_km` when given.
+    pub fn tracks(
+        &self,
+        seconds: Option<u64>,
+        until_tick: u64,
+        great_circle_km: Option<f64>,
+    ) -> Vec<Track> {
+        let mut tracks = decimated_tracks(&self.window(seconds, until_tick), &self.decimation);
+        if let Some(step_km) = great_circle_km {
+            for track in &mut tracks {
+                track.follow_great_circles(step_km);
+            }
+        }
+        tracks
+    }
+}
@@ -0,0 +1,227 @@
+use geo::Point;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+use crate::{AttractionPoint, EventKind, EventLog, Km, SharkRng, random_point};
+
+/// Spots tried for an event before giving up on it until the next one is due
+const PLACEMENT_TRIES: usize = 10;
+
+/// Settings for `HotEvents`, e.g. `{"per_minute": 2, "kinds": ["bait ball"],
+/// "regions": [{"lon": -64, "lat": 32, "radius_km": 600, "weight": 2}]}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HotEventConfig {
+    /// Events starting per minute of simulation, on average
+    pub per_minute: f64,
+    /// Most events going on at once
+    pub max_active: usize,
+    /// Seconds an event lasts, picked between the two
+    pub duration_secs: (f64, f64),
+    /// Multiplies the goal seeking force towards an event, see `AttractionPoint::strength`
+    pub strength: f64,
+    /// What an event is, one picked at random each time
+    pub kinds: Vec<String>,
+    /// Where events happen, see `HotEventRegion`. Without any, within
+    /// `site_spread_km` of a goal or hotspot, or anywhere without those.
+    pub regions: Vec<HotEventRegion>,
+    pub site_spread_km: f64,
+}
+
+impl Default for HotEventConfig {
+    fn default() -> Self {
+        Self {
+            per_minute: 2.0,
+            max_active: 3,
+            duration_secs: (30.0, 90.0),
+            strength: 5.0,
+            kinds: vec!["bait ball".to_string(), "whale fall".to_string()],
+            regions: Vec::new(),
+            site_spread_km: 1000.0,
+        }
+    }
+}
+
+/// A disc events are placed in, evenly over its area, each region as often
+/// as its `weight` says relative to the others.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HotEventRegion {
+    pub lon: f64,
+    pub lat: f64,
+    pub radius_km: f64,
+    #[serde(default = "one")]
+    pub weight: f64,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+/// A short-lived spot sharks are drawn to much harder than to any goal, and
+/// feed at like at one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotEvent {
+    pub id: u64,
+    /// One of `HotEventConfig::kinds`
+    pub kind: String,
+    #[serde(flatten)]
+    pub position: Point<f64>,
+    pub strength: f64,
+    /// Simulated seconds until it's over
+    pub remaining_secs: f64,
+}
+
+impl HotEvent {
+    /// E.g. "bait ball near 32N 64W".
+    pub fn description(&self) -> String {
+        let (lon, lat) = (self.position.x(), self.position.y());
+        format!(
+            "{} near {:.0}{} {:.0}{}",
+            self.kind,
+            lat.abs(),
+            if lat < 0.0 { 'S' } else { 'N' },
+            lon.abs(),
+            if lon < 0.0 { 'W' } else { 'E' }
+        )
+    }
+
+    pub fn attraction_point(&self) -> AttractionPoint {
+        AttractionPoint {
+            name: self.description(),
+            category: Some(self.kind.clone()),
+            strength: self.strength,
+            ..AttractionPoint::at(self.position)
+        }
+    }
+}
+
+/// Random events that make sharks gather somewhere for a while without
+/// anyone adding a goal, e.g. for demos. Each start and end goes out as an
+/// event on the `hot_events` stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotEvents {
+    #[serde(skip)]
+    config: HotEventConfig,
+    pub active: Vec<HotEvent>,
+    #[serde(skip)]
+    next_id: u64,
+    /// Fraction of an event owed to the next start
+    #[serde(skip)]
+    start_credit: f64,
+}
+
+impl HotEvents {
+    pub fn new(config: HotEventConfig) -> Self {
+        Self {
+            config,
+            active: Vec::new(),
+            next_id: 0,
+            start_credit: 0.0,
+        }
+    }
+
+    /// Takes over the events going on elsewhere, e.g. a primary being
+    /// mirrored, with their ids. New events are numbered after the highest.
+    pub fn adopt_events(&mut self, active: Vec<HotEvent>) {
+        let after_highest = active.iter().map(|event| event.id + 1).max().unwrap_or(0);
+        self.next_id = self.next_id.max(after_highest);
+        self.active = active;
+    }
+
+    /// Ends the events whose time is up and starts new ones, around `sites`
+    /// unless regions are configured. `in_water` keeps them off land.
+    pub fn update(
+        &mut self,
+        sites: &[Point<f64>],
+        in_water: impl Fn(Point<f64>) -> bool,
+        dt: f64,
+        tick: u64,
+        events: &mut EventLog,
+        rng: &mut SharkRng,
+    ) {
+        for event in &mut self.active {
+            event.remaining_secs -= dt;
+        }
+        self.active.retain(|event| {
+            let over = event.remaining_secs <= 0.0;
+            if over {
+                events.emit(
+                    tick,
+                    EventKind::HotEventEnded {
+                        id: event.id,
+                        kind: event.kind.clone(),
+                    },
+                );
+            }
+            !over
+        });
+
+        self.start_credit += self.config.per_minute / 60.0 * dt;
+        while self.start_credit >= 1.0 && self.active.len() < self.config.max_active {
+            self.start_credit -= 1.0;
+            let position = (0..PLACEMENT_TRIES)
+                .map(|_| self.place(sites, rng))
+                .find(|p| in_water(*p));
+            let (Some(position), Some(kind)) = (position, self.pick_kind(rng)) else {
+                continue;
+            };
+            let (shortest, longest) = self.config.duration_secs;
+            let event = HotEvent {
+                id: self.next_id,
+                kind,
+                position,
+                strength: self.config.strength,
+                remaining_secs: rng.random_range(shortest..=longest.max(shortest)),
+            };
+            self.next_id += 1;
+            events.emit(
+                tick,
+                EventKind::HotEventStarted {
+                    id: event.id,
+                    kind: event.kind.clone(),
+                    description: event.description(),
+                    position: event.position,
+                    duration_secs: event.remaining_secs,
+                },
+            );
+            self.active.push(event);
+        }
+        // don't save up while at the limit
+        self.start_credit = self.start_credit.min(1.0);
+    }
+
+    fn pick_kind(&self, rng: &mut SharkRng) -> Option<String> {
+        let kinds = &self.config.kinds;
+        (!kinds.is_empty()).then(|| kinds[rng.random_range(0..kinds.len())].clone())
+    }
+
+    /// A random spot in a region, or near a site without regions.
+    fn place(&self, sites: &[Point<f64>], rng: &mut SharkRng) -> Point<f64> {
+        let regions = &self.config.regions;
+        let total: f64 = regions.iter().map(|region| region.weight.max(0.0)).sum();
+        let (center, radius_km) = if total > 0.0 {
+            let mut pick = rng.random_range(0.0..total);
+            let region = regions
+                .iter()
+                .find(|region| {
+                    pick -= region.weight.max(0.0);
+                    pick < 0.0
+                })
+                .unwrap_or(&regions[regions.len() - 1]);
+            (Point::new(region.lon, region.lat), region.radius_km)
+        } else if !sites.is_empty() {
+            let site = sites[rng.random_range(0..sites.len())];
+            (site, self.config.site_spread_km)
+        } else {
+            return random_point(rng);
+        };
+        // the square root spreads them evenly over the disc, not bunched in the middle
+        let distance = Km(radius_km).to_degrees() * rng.random_range(0.0..1.0f64).sqrt();
+        let angle = rng.random_range(0.0..(2.0 * PI));
+        Point::new(
+            center.x() + distance * angle.cos(),
+            center.y() + distance * angle.sin(),
+        )
+    }
+}
@@ -0,0 +1,441 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::stream;
+use geo::{Rect, Simplify, coord};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio::net::TcpListener;
+use utoipa::{IntoParams, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::arrow_stream::ARROW_STREAM_MIME;
+use crate::boundary::{BoundaryStats, EdgeCounts};
+use crate::history::MAX_HISTORY_SECONDS;
+use crate::neighbor_graph::NeighborList;
+use crate::summary::{GoalVisitors, PointSchema};
+use crate::{
+    Admin, AdminCommand, AdminReply, AdminRequest, ArrowStream, AttractionPoint, Km, Months,
+    NeighborGraph, PhysicsHandle, Resolution, SharedHistory, Shark, Shutdown, Snapshots, Species,
+    StatsSample, StatsSeries, Track, WorldSummary, land_to_geojson,
+};
+
+/// How often the cached `/summary` is recomputed
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(1);
+
+type SharedSummary = Arc<RwLock<WorldSummary>>;
+type SharedStats = Arc<RwLock<StatsSeries>>;
+
+#[derive(Clone)]
+struct ApiState {
+    summary: SharedSummary,
+    stats: SharedStats,
+    snapshots: Snapshots,
+    perception_radius: Km,
+    history: SharedHistory,
+    admin: Admin,
+    /// Ends the streaming responses, which graceful shutdown would wait on forever
+    shutdown: Shutdown,
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Shark simulation API"),
+    paths(
+        get_summary,
+        get_stats,
+        get_neighbors,
+        get_tracks,
+        get_arrow,
+        get_sharks,
+        get_goals,
+        post_goal,
+        get_land,
+        get_config,
+        patch_config,
+        post_admin
+    ),
+    components(schemas(
+        WorldSummary,
+        GoalVisitors,
+        StatsSample,
+        Resolution,
+        PointSchema,
+        BoundaryStats,
+        EdgeCounts,
+        NeighborGraph,
+        NeighborList,
+        Track,
+        Shark,
+        Species,
+        AttractionPoint,
+        Months,
+        AdminCommand,
+        AdminReply
+    ))
+)]
+struct ApiDoc;
+
+/// Plain HTTP endpoints for clients that only poll, next to the WebSocket stream.
+pub async fn serve_http(
+    addr: SocketAddr,
+    physics: PhysicsHandle,
+    perception_radius: Km,
+    admin: Admin,
+    mut shutdown: Shutdown,
+) -> std::io::Result<()> {
+    let summary = Arc::new(RwLock::new(WorldSummary::new(&physics.snapshots.latest())));
+    let stats = SharedStats::default();
+    tokio::spawn(refresh_summary(
+        summary.clone(),
+        stats.clone(),
+        physics.snapshots.clone(),
+    ));
+
+    let state = ApiState {
+        summary,
+        stats,
+        snapshots: physics.snapshots.clone(),
+        perception_radius,
+        history: physics.history.clone(),
+        admin,
+        shutdown: shutdown.clone(),
+    };
+    let app = Router::new()
+        .route("/summary", get(get_summary))
+        .route("/stats", get(get_stats))
+        .route("/neighbors", get(get_neighbors))
+        .route("/tracks", get(get_tracks))
+        .route("/arrow", get(get_arrow))
+        .route("/sharks", get(get_sharks))
+        .route("/goals", get(get_goals).post(post_goal))
+        .route("/land", get(get_land))
+        .route("/config", get(get_config).patch(patch_config))
+        .route("/admin", post(post_admin))
+        .with_state(state)
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()));
+
+    let listener = TcpListener::bind(addr).await?;
+    println!("http listening on {}", addr);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await
+}
+
+/// World summary, recomputed once per second
+#[utoipa::path(
+    get,
+    path = "/summary",
+    responses((status = 200, description = "Current world summary", body = WorldSummary))
+)]
+async fn get_summary(State(state): State<ApiState>) -> Json<WorldSummary> {
+    Json(state.summary.read().unwrap().clone())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct StatsQuery {
+    /// `1s` (the default, kept for an hour), `1min` (a day) or `10min` (a week)
+    #[param(inline)]
+    resolution: Option<Resolution>,
+    /// How far back to go, defaults to everything kept
+    seconds: Option<f64>,
+}
+
+/// History of the world summary numbers for plotting, averaged to the resolution
+#[utoipa::path(
+    get,
+    path = "/stats",
+    params(StatsQuery),
+    responses((status = 200, description = "Samples oldest first", body = Vec<StatsSample>))
+)]
+async fn get_stats(
+    State(state): State<ApiState>,
+    Query(query): Query<StatsQuery>,
+) -> Json<Vec<StatsSample>> {
+    let resolution = query.resolution.unwrap_or_default();
+    let seconds = query.seconds.unwrap_or(f64::INFINITY);
+    Json(state.stats.read().unwrap().range(resolution, seconds))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct NeighborQuery {
+    /// Link distance, defaults to the perception radius
+    radius_km: Option<f64>,
+    /// `min_lon,min_lat,max_lon,max_lat` to limit the graph to
+    bbox: Option<String>,
+}
+
+/// Proximity graph of the latest frame as an adjacency list
+#[utoipa::path(
+    get,
+    path = "/neighbors",
+    params(NeighborQuery),
+    responses(
+        (status = 200, description = "Sharks within the radius of each other", body = NeighborGraph),
+        (status = 400, description = "Malformed bbox or radius")
+    )
+)]
+async fn get_neighbors(
+    State(state): State<ApiState>,
+    Query(query): Query<NeighborQuery>,
+) -> Result<Json<NeighborGraph>, (StatusCode, String)> {
+    let bbox = match &query.bbox {
+        Some(bbox) => Some(parse_bbox(bbox).ok_or((
+            StatusCode::BAD_REQUEST,
+            "bbox must be min_lon,min_lat,max_lon,max_lat".to_string(),
+        ))?),
+        None => None,
+    };
+    if let Some(radius_km) = query.radius_km
+        && !(radius_km.is_finite() && radius_km > 0.0)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "radius_km must be a number above 0".to_string(),
+        ));
+    }
+    let radius = query.radius_km.map(Km).unwrap_or(state.perception_radius);
+    let snapshot = state.snapshots.latest();
+    Ok(Json(NeighborGraph::new(&snapshot, radius, bbox)))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct TrackQuery {
+    /// How far back to go, at most a day, defaults to the whole kept history
+    seconds: Option<u64>,
+    /// Adds points along great circles this many km apart and the bearing
+    /// at each, for drawing long tracks in a GIS
+    great_circle_km: Option<f64>,
+}
+
+/// Paths of the sharks over the kept history, decimated as set by `track_decimation`
+#[utoipa::path(
+    get,
+    path = "/tracks",
+    params(TrackQuery),
+    responses(
+        (status = 200, description = "One track per shark", body = Vec<Track>),
+        (status = 400, description = "seconds is more than a day")
+    )
+)]
+async fn get_tracks(
+    State(state): State<ApiState>,
+    Query(query): Query<TrackQuery>,
+) -> Result<Json<Vec<Track>>, (StatusCode, String)> {
+    if query
+        .seconds
+        .is_some_and(|seconds| seconds > MAX_HISTORY_SECONDS)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("seconds can be at most {}", MAX_HISTORY_SECONDS),
+        ));
+    }
+    let until_tick = state.snapshots.latest().tick;
+    let history = state.history.lock().unwrap();
+    Ok(Json(history.tracks(
+        query.seconds,
+        until_tick,
+        query.great_circle_km,
+    )))
+}
+
+/// Live Arrow IPC stream with a record batch of every shark per frame, e.g.
+/// `pyarrow.ipc.open_stream(urlopen(".../arrow"))`. Ends on shutdown.
+#[utoipa::path(
+    get,
+    path = "/arrow",
+    responses((status = 200, description = "Arrow IPC stream", content_type = "application/vnd.apache.arrow.stream"))
+)]
+async fn get_arrow(State(state): State<ApiState>) -> impl IntoResponse {
+    let encoder = ArrowStream::new().unwrap();
+    let mut snapshots = state.snapshots;
+    // the frame current on connect goes out first
+    snapshots.mark_changed();
+    let batches = stream::unfold(
+        (snapshots, encoder, state.shutdown),
+        |(mut snapshots, mut encoder, mut shutdown)| async move {
+            let snapshot = tokio::select! {
+                changed = snapshots.changed() => changed?,
+                _ = shutdown.wait() => return None,
+            };
+            let bytes = encoder.encode(&snapshot).map_err(std::io::Error::other);
+            Some((bytes, (snapshots, encoder, shutdown)))
+        },
+    );
+    (
+        [(header::CONTENT_TYPE, ARROW_STREAM_MIME)],
+        Body::from_stream(batches),
+    )
+}
+
+/// Every shark in the latest frame
+#[utoipa::path(
+    get,
+    path = "/sharks",
+    responses((status = 200, description = "Sharks as in frames", body = Vec<Shark>))
+)]
+async fn get_sharks(State(state): State<ApiState>) -> Json<Vec<Shark>> {
+    Json(state.snapshots.latest().sharks.clone())
+}
+
+/// Attraction points with their metadata, in the order of `goals` in frames
+#[utoipa::path(
+    get,
+    path = "/goals",
+    responses((status = 200, description = "Attraction points", body = Vec<AttractionPoint>))
+)]
+async fn get_goals(State(state): State<ApiState>) -> Json<Vec<AttractionPoint>> {
+    Json(state.snapshots.latest().goals.clone())
+}
+
+/// Adds an attraction point for good, e.g. `{"x": 136.1, "y": -35.3, "name": "Neptune Islands"}`
+#[utoipa::path(
+    post,
+    path = "/goals",
+    request_body = AttractionPoint,
+    responses(
+        (status = 200, description = "The new point's `index` in `goals`", body = Object),
+        (status = 503, description = "Physics thread stopped")
+    )
+)]
+async fn post_goal(
+    State(state): State<ApiState>,
+    Json(goal): Json<AttractionPoint>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let added = state.admin.add_goal(goal).await;
+    added
+        .map(Json)
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct LandQuery {
+    /// Furthest the outlines may stray from the land the sharks steer
+    /// around, in degrees, defaults to about 5 km; 0 for every point
+    tolerance_deg: Option<f64>,
+}
+
+/// The land the simulation runs on as a GeoJSON `FeatureCollection`, for
+/// clients to draw the very coast the sharks avoid instead of bundling their
+/// own. Changes when an admin loads another world, see `land_bounds` in frames.
+#[utoipa::path(
+    get,
+    path = "/land",
+    params(LandQuery),
+    responses((status = 200, description = "One `Polygon` feature per land polygon", body = Object))
+)]
+async fn get_land(State(state): State<ApiState>, Query(query): Query<LandQuery>) -> Json<Value> {
+    let snapshot = state.snapshots.latest();
+    Json(match query.tolerance_deg {
+        None => land_to_geojson(snapshot.coarse_land()),
+        Some(tolerance) => {
+            let land: Vec<_> = snapshot
+                .land()
+                .iter()
+                .map(|polygon| polygon.simplify(tolerance))
+                .collect();
+            land_to_geojson(&land)
+        }
+    })
+}
+
+/// Steering parameters in use, keys as in the simulation file
+#[utoipa::path(
+    get,
+    path = "/config",
+    responses((status = 200, description = "Current parameters", body = Object))
+)]
+async fn get_config(State(state): State<ApiState>) -> Result<Json<Value>, (StatusCode, String)> {
+    run_admin(&state, AdminCommand::GetParams).await
+}
+
+/// Overrides some steering parameters, like the `set_params` admin command,
+/// e.g. `{"cohesion_strength": 0.2}`
+#[utoipa::path(
+    patch,
+    path = "/config",
+    request_body = Object,
+    responses(
+        (status = 200, description = "The full new set of parameters", body = Object),
+        (status = 400, description = "Unknown parameter or bad value")
+    )
+)]
+async fn patch_config(
+    State(state): State<ApiState>,
+    Json(params): Json<Map<String, Value>>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    run_admin(&state, AdminCommand::SetParams { params }).await
+}
+
+/// Runs an admin command, `{"command": "help"}` lists them all
+#[utoipa::path(
+    post,
+    path = "/admin",
+    request_body = AdminCommand,
+    responses(
+        (status = 200, description = "Command carried out", body = AdminReply),
+        (status = 400, description = "Command refused", body = AdminReply)
+    )
+)]
+async fn post_admin(
+    State(state): State<ApiState>,
+    Json(request): Json<AdminRequest>,
+) -> (StatusCode, Json<AdminReply>) {
+    let reply = state.admin.execute(request).await;
+    let status = if reply.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    (status, Json(reply))
+}
+
+/// Runs `command` like `POST /admin` does, replying with just its result.
+async fn run_admin(
+    state: &ApiState,
+    command: AdminCommand,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let request = AdminRequest {
+        version: None,
+        session: None,
+        command,
+    };
+    let reply = state.admin.execute(request).await;
+    match reply.error {
+        Some(error) => Err((StatusCode::BAD_REQUEST, error)),
+        None => Ok(Json(reply.result.unwrap_or(Value::Null))),
+    }
+}
+
+fn parse_bbox(bbox: &str) -> Option<Rect<f64>> {
+    let values: Vec<f64> = bbox
+        .split(',')
+        .map(|value| value.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let [min_x, min_y, max_x, max_y] = values[..] else {
+        return None;
+    };
+    Some(Rect::new(
+        coord! { x: min_x, y: min_y },
+        coord! { x: max_x, y: max_y },
+    ))
+}
+
+/// Also feeds the stats history, `SUMMARY_INTERVAL` being the 1s it expects.
+async fn refresh_summary(summary: SharedSummary, stats: SharedStats, snapshots: Snapshots) {
+    let mut interval = tokio::time::interval(SUMMARY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let snapshot = snapshots.latest();
+        *summary.write().unwrap() = WorldSummary::new(&snapshot);
+        stats.write().unwrap().push(StatsSample::new(&snapshot));
+    }
+}
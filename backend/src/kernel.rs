@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// How much a neighbor counts depending on its distance, relative to the radius
+/// it was found in. Anything but `Uniform` fades out towards the radius, so
+/// neighbors don't pop in and out of the flocking forces at the boundary.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightKernel {
+    /// Every neighbor within the radius counts fully
+    #[default]
+    Uniform,
+    /// Falls off linearly to 0 at the radius
+    Linear,
+    /// `(1 - (d/r)^2)^2`, flat up close and smooth at both ends
+    Smooth,
+}
+
+impl WeightKernel {
+    pub fn weight(&self, dist: f64, radius: f64) -> f64 {
+        if radius <= 0.0 || dist >= radius {
+            return 0.0;
+        }
+        let ratio = dist / radius;
+        match self {
+            WeightKernel::Uniform => 1.0,
+            WeightKernel::Linear => 1.0 - ratio,
+            WeightKernel::Smooth => (1.0 - ratio * ratio).powi(2),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use geo::{BoundingRect, Point, Polygon};
+
+/// Spacing of the `LandField` grid in degrees (about 28 km)
+pub const LAND_FIELD_CELL: f64 = 0.25;
+/// Closer than this to the coast, in degrees, the grid is too coarse to go by
+/// and only the polygons will do
+pub const EXACT_BAND: f64 = 2.0 * LAND_FIELD_CELL;
+/// How far the grid reaches past the land, in degrees, a good way beyond the
+/// default `land_avoid_radius`
+const FIELD_MARGIN: f64 = 20.0;
+
+/// Signed distance to the nearest coast sampled on a grid around the land,
+/// positive at sea and negative on land. Built once per world, so land
+/// avoidance away from the coast is a lookup instead of a pass over every
+/// polygon. Off by at most a cell, islands smaller than one included.
+#[derive(Debug, Clone, Default)]
+pub struct LandField {
+    /// Position of the first grid point
+    origin: (f64, f64),
+    width: usize,
+    height: usize,
+    /// Degrees, row by row from the south
+    distances: Vec<f32>,
+}
+
+impl LandField {
+    /// An empty field for no land, see `sample`.
+    pub fn new(land: &[Polygon<f64>]) -> Self {
+        let Some((min_x, min_y, max_x, max_y)) = land
+            .iter()
+            .filter_map(|polygon| polygon.bounding_rect())
+            .map(|rect| (rect.min().x, rect.min().y, rect.max().x, rect.max().y))
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+        else {
+            return Self::default();
+        };
+        let origin = (min_x - FIELD_MARGIN, min_y - FIELD_MARGIN);
+        let width = ((max_x - min_x + 2.0 * FIELD_MARGIN) / LAND_FIELD_CELL).ceil() as usize + 1;
+        let height = ((max_y - min_y + 2.0 * FIELD_MARGIN) / LAND_FIELD_CELL).ceil() as usize + 1;
+        let mut field = Self {
+            origin,
+            width,
+            height,
+            distances: Vec::new(),
+        };
+
+        let mut coast = vec![false; width * height];
+        let mut inside = vec![false; width * height];
+        for polygon in land {
+            field.rasterize(polygon, &mut coast, &mut inside);
+        }
+
+        let squared = distance_transform(&coast, width, height);
+        field.distances = squared
+            .iter()
+            .zip(&inside)
+            .map(|(squared, &inside)| {
+                let distance = squared.sqrt() * LAND_FIELD_CELL;
+                (if inside { -distance } else { distance }) as f32
+            })
+            .collect();
+        field
+    }
+
+    /// Marks the grid points next to `polygon`'s rings in `coast` and those
+    /// it covers in `inside`.
+    fn rasterize(&self, polygon: &Polygon<f64>, coast: &mut [bool], inside: &mut [bool]) {
+        let Some(bounds) = polygon.bounding_rect() else {
+            return;
+        };
+        let first_row = ((bounds.min().y - self.origin.1) / LAND_FIELD_CELL).ceil() as usize;
+        let last_row = ((bounds.max().y - self.origin.1) / LAND_FIELD_CELL).floor() as usize;
+        let mut crossings: Vec<Vec<f64>> =
+            vec![Vec::new(); (last_row + 1).saturating_sub(first_row)];
+
+        for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+            for line in ring.lines() {
+                let (a, b) = (line.start, line.end);
+                // every grid point the edge passes, close enough for the field
+                let steps = ((b.x - a.x).hypot(b.y - a.y) / (LAND_FIELD_CELL / 2.0)).ceil();
+                for step in 0..=steps as usize {
+                    let t = if steps > 0.0 {
+                        step as f64 / steps
+                    } else {
+                        0.0
+                    };
+                    let (x, y) = (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+                    let column = ((x - self.origin.0) / LAND_FIELD_CELL).round() as usize;
+                    let row = ((y - self.origin.1) / LAND_FIELD_CELL).round() as usize;
+                    coast[row * self.width + column] = true;
+                }
+                // where the edge crosses the rows, for the even-odd fill below
+                let rows = (
+                    ((a.y.min(b.y) - self.origin.1) / LAND_FIELD_CELL).ceil() as usize,
+                    ((a.y.max(b.y) - self.origin.1) / LAND_FIELD_CELL).floor() as usize,
+                );
+                for row in rows.0.max(first_row)..=rows.1.min(last_row) {
+                    let y = self.origin.1 + row as f64 * LAND_FIELD_CELL;
+                    if (a.y <= y) != (b.y <= y) {
+                        let x = a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+                        crossings[row - first_row].push(x);
+                    }
+                }
+            }
+        }
+
+        for (row, xs) in crossings.iter_mut().enumerate() {
+            xs.sort_by(f64::total_cmp);
+            let row_start = (first_row + row) * self.width;
+            for pair in xs.chunks_exact(2) {
+                let from = ((pair[0] - self.origin.0) / LAND_FIELD_CELL).ceil() as usize;
+                let to = ((pair[1] - self.origin.0) / LAND_FIELD_CELL).floor() as usize;
+                for column in from..=to.min(self.width - 1) {
+                    inside[row_start + column] = true;
+                }
+            }
+        }
+    }
+
+    /// The signed distance to the coast at `point` and its gradient, which
+    /// points out to sea on either side of the coast. `None` outside the
+    /// grid or without any land.
+    pub fn sample(&self, point: Point<f64>) -> Option<(f64, Point<f64>)> {
+        let x = (point.x() - self.origin.0) / LAND_FIELD_CELL;
+        let y = (point.y() - self.origin.1) / LAND_FIELD_CELL;
+        if !(x >= 0.0 && y >= 0.0) {
+            return None;
+        }
+        let (column, row) = (x as usize, y as usize);
+        if column + 1 >= self.width || row + 1 >= self.height {
+            return None;
+        }
+        let (tx, ty) = (x - column as f64, y - row as f64);
+        let at = |column: usize, row: usize| self.distances[row * self.width + column] as f64;
+        let (d00, d10) = (at(column, row), at(column + 1, row));
+        let (d01, d11) = (at(column, row + 1), at(column + 1, row + 1));
+
+        let distance =
+            (d00 * (1.0 - tx) + d10 * tx) * (1.0 - ty) + (d01 * (1.0 - tx) + d11 * tx) * ty;
+        let gradient = Point::new(
+            ((d10 - d00) * (1.0 - ty) + (d11 - d01) * ty) / LAND_FIELD_CELL,
+            ((d01 - d00) * (1.0 - tx) + (d11 - d10) * tx) / LAND_FIELD_CELL,
+        );
+        Some((distance, gradient))
+    }
+}
+
+/// Squared distance in grid steps from every grid point to the nearest one
+/// set in `seeds`, exact, with the two-pass algorithm of Felzenszwalb and
+/// Huttenlocher. Infinite with no seeds at all.
+fn distance_transform(seeds: &[bool], width: usize, height: usize) -> Vec<f64> {
+    let mut squared: Vec<f64> = seeds
+        .iter()
+        .map(|&seed| if seed { 0.0 } else { f64::INFINITY })
+        .collect();
+    let mut line = Vec::new();
+    for column in 0..width {
+        line.clear();
+        line.extend((0..height).map(|row| squared[row * width + column]));
+        for (row, value) in distance_transform_1d(&line).into_iter().enumerate() {
+            squared[row * width + column] = value;
+        }
+    }
+    for row in squared.chunks_exact_mut(width) {
+        let transformed = distance_transform_1d(row);
+        row.copy_from_slice(&transformed);
+    }
+    squared
+}
+
+/// One pass of `distance_transform`: the lower envelope of the parabolas
+/// rooted at each sample.
+fn distance_transform_1d(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    let mut distances = vec![f64::INFINITY; n];
+    // roots of the parabolas on the envelope and where each takes over
+    let mut roots = Vec::with_capacity(n);
+    let mut starts = Vec::with_capacity(n + 1);
+    for q in (0..n).filter(|&q| f[q].is_finite()) {
+        let parabola = |p: usize| f[p] + (p * p) as f64;
+        loop {
+            let Some(&p) = roots.last() else {
+                roots.push(q);
+                starts.push(f64::NEG_INFINITY);
+                break;
+            };
+            let s = (parabola(q) - parabola(p)) / (2.0 * (q - p) as f64);
+            if s <= *starts.last().unwrap() {
+                roots.pop();
+                starts.pop();
+            } else {
+                roots.push(q);
+                starts.push(s);
+                break;
+            }
+        }
+    }
+    if roots.is_empty() {
+        return distances;
+    }
+    let mut k = 0;
+    for (q, distance) in distances.iter_mut().enumerate() {
+        while k + 1 < roots.len() && starts[k + 1] < q as f64 {
+            k += 1;
+        }
+        let p = roots[k];
+        *distance = (q as f64 - p as f64).powi(2) + f[p];
+    }
+    distances
+}
@@ -0,0 +1,25 @@
+use geo::{LineString, Polygon};
+use serde_json::{Value, json};
+
+/// `land` as a GeoJSON `FeatureCollection` with a `Polygon` feature per
+/// polygon, in the same order, which `load_land_geojson` reads back.
+pub fn land_to_geojson(land: &[Polygon<f64>]) -> Value {
+    let ring = |ring: &LineString<f64>| -> Vec<[f64; 2]> {
+        ring.coords().map(|coord| [coord.x, coord.y]).collect()
+    };
+    let features: Vec<Value> = land
+        .iter()
+        .map(|polygon| {
+            let rings: Vec<Vec<[f64; 2]>> = std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .map(ring)
+                .collect();
+            json!({
+                "type": "Feature",
+                "properties": {},
+                "geometry": {"type": "Polygon", "coordinates": rings},
+            })
+        })
+        .collect();
+    json!({"type": "FeatureCollection", "features": features})
+}
@@ -0,0 +1,86 @@
+use geo::orient::{Direction, Orient};
+use geo::{
+    Area, BooleanOps, LineString, MultiPolygon, Polygon, RemoveRepeatedPoints, Validation, Winding,
+};
+use std::fmt::Display;
+
+/// What `repair_polygon` had to change, added up over a whole dataset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RepairReport {
+    /// Polygons with an exterior not counter-clockwise or holes not clockwise
+    pub reoriented: usize,
+    /// Holes without area, or with too few points or coordinates that aren't numbers
+    pub dropped_holes: usize,
+    /// Polygons crossing themselves or with holes poking out, rebuilt from their outline
+    pub rebuilt: usize,
+    /// Polygons that became nothing once rebuilt, e.g. slivers folding onto themselves
+    pub dropped: usize,
+}
+
+impl RepairReport {
+    pub fn any(&self) -> bool {
+        self.reoriented + self.dropped_holes + self.rebuilt + self.dropped > 0
+    }
+}
+
+impl Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} reoriented, {} degenerate holes dropped, {} self-intersecting rebuilt, {} dropped",
+            self.reoriented, self.dropped_holes, self.rebuilt, self.dropped
+        )
+    }
+}
+
+/// Too few points for a ring, no area, or coordinates that aren't numbers.
+pub fn is_degenerate(polygon: &Polygon<f64>) -> bool {
+    is_degenerate_ring(polygon.exterior())
+}
+
+fn is_degenerate_ring(ring: &LineString<f64>) -> bool {
+    ring.0.len() < 4
+        || Polygon::new(ring.clone(), vec![]).unsigned_area() == 0.0
+        || ring
+            .coords()
+            .any(|coord| !coord.x.is_finite() || !coord.y.is_finite())
+}
+
+/// Makes a polygon safe for `Contains` and `closest_point`: drops repeated
+/// points and degenerate holes, orients the exterior counter-clockwise and
+/// the holes clockwise, and rebuilds a polygon that isn't valid from its
+/// rings the way `buffer(0)` would, which may split it in several or none.
+/// Expects an exterior that isn't degenerate, see `is_degenerate`.
+pub fn repair_polygon(polygon: Polygon<f64>, report: &mut RepairReport) -> Vec<Polygon<f64>> {
+    let (exterior, interiors) = polygon.remove_repeated_points().into_inner();
+    let holes = interiors.len();
+    let interiors: Vec<_> = interiors
+        .into_iter()
+        .filter(|ring| !is_degenerate_ring(ring))
+        .collect();
+    report.dropped_holes += holes - interiors.len();
+
+    let polygon = Polygon::new(exterior, interiors);
+    let polygon =
+        if polygon.exterior().is_ccw() && polygon.interiors().iter().all(|ring| ring.is_cw()) {
+            polygon
+        } else {
+            report.reoriented += 1;
+            polygon.orient(Direction::Default)
+        };
+    if polygon.is_valid() {
+        return vec![polygon];
+    }
+
+    report.rebuilt += 1;
+    let rebuilt: Vec<_> = polygon
+        .union(&MultiPolygon::<f64>::new(vec![]))
+        .into_iter()
+        .map(|part| part.orient(Direction::Default))
+        .filter(|part| !is_degenerate(part))
+        .collect();
+    if rebuilt.is_empty() {
+        report.dropped += 1;
+    }
+    rebuilt
+}
@@ -0,0 +1,88 @@
+use geo::{BoundingRect, ChamberlainDuquetteArea, Polygon, Rect};
+use std::fmt::Display;
+
+/// Surface of the Earth in km²
+const EARTH_AREA_KM2: f64 = 510_072_000.0;
+/// Share of the globe beyond which land leaves too little water to swim in
+const MOSTLY_LAND: f64 = 0.9;
+/// Leeway on the lon/lat limits for rounding in the data, in degrees
+const LIMIT_TOLERANCE: f64 = 1e-6;
+
+/// What the loaded world looks like, printed on startup so a broken land
+/// dataset shows up before the sharks behave oddly.
+#[derive(Debug, Clone)]
+pub struct LandSummary {
+    pub polygons: usize,
+    /// `None` without land
+    pub bounds: Option<Rect<f64>>,
+    /// Total land area, ignoring overlaps
+    pub area_km2: f64,
+}
+
+impl LandSummary {
+    pub fn new(polygons: &[Polygon<f64>]) -> Self {
+        let bounds = polygons
+            .iter()
+            .filter_map(|polygon| polygon.bounding_rect())
+            .reduce(|a, b| {
+                Rect::new(
+                    (a.min().x.min(b.min().x), a.min().y.min(b.min().y)),
+                    (a.max().x.max(b.max().x), a.max().y.max(b.max().y)),
+                )
+            });
+        let area_m2: f64 = polygons
+            .iter()
+            .map(|polygon| polygon.chamberlain_duquette_unsigned_area())
+            .sum();
+        Self {
+            polygons: polygons.len(),
+            bounds,
+            area_km2: area_m2 / 1e6,
+        }
+    }
+
+    /// Things about the land that are most likely mistakes.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        match self.bounds {
+            None => warnings.push("there is no land at all, sharks can swim anywhere".to_string()),
+            Some(bounds)
+                if bounds.min().x < -180.0 - LIMIT_TOLERANCE
+                    || bounds.max().x > 180.0 + LIMIT_TOLERANCE
+                    || bounds.min().y < -90.0 - LIMIT_TOLERANCE
+                    || bounds.max().y > 90.0 + LIMIT_TOLERANCE =>
+            {
+                warnings.push(
+                    "land reaches past longitude ±180 or latitude ±90, the data may not be in WGS84 degrees"
+                        .to_string(),
+                );
+            }
+            Some(_) => {}
+        }
+        if self.area_km2 > EARTH_AREA_KM2 * MOSTLY_LAND {
+            warnings.push(format!(
+                "land covers {:.0}% of the globe, there is hardly any water to spawn sharks in",
+                self.area_km2 / EARTH_AREA_KM2 * 100.0
+            ));
+        }
+        warnings
+    }
+}
+
+impl Display for LandSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} land polygons", self.polygons)?;
+        if let Some(bounds) = self.bounds {
+            write!(
+                f,
+                " within ({:.1}, {:.1}) to ({:.1}, {:.1}), {:.0} km²",
+                bounds.min().x,
+                bounds.min().y,
+                bounds.max().x,
+                bounds.max().y,
+                self.area_km2
+            )?;
+        }
+        Ok(())
+    }
+}
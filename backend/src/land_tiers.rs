@@ -0,0 +1,36 @@
+use geo::{Closest, ClosestPoint, Distance, Euclidean, Point, Polygon, Simplify};
+
+/// Furthest the simplified coast strays from the real one, in degrees (about 5 km)
+pub const COARSE_TOLERANCE: f64 = 0.05;
+/// Closer than this to the simplified coast, in degrees, only the real one is
+/// precise enough
+pub const NEAR_COAST: f64 = 4.0 * COARSE_TOLERANCE;
+
+/// `land` simplified to within `COARSE_TOLERANCE`, in the same order, for
+/// checks far from the coast where the detail makes no difference.
+pub fn coarse_land(land: &[Polygon<f64>]) -> Vec<Polygon<f64>> {
+    land.iter()
+        .map(|polygon| polygon.simplify(COARSE_TOLERANCE))
+        .collect()
+}
+
+/// The point of the coast nearest `point` and how far away it is, from
+/// `coarse` when that is beyond `NEAR_COAST` and from `full` otherwise. Out
+/// at sea the answer is off by at most `COARSE_TOLERANCE`, next to the coast
+/// it's exact. Inside the land the point itself comes back, 0 away.
+pub fn closest_coast(
+    full: &Polygon<f64>,
+    coarse: &Polygon<f64>,
+    point: &Point<f64>,
+) -> Option<(Point<f64>, f64)> {
+    let closest = |polygon: &Polygon<f64>| match polygon.closest_point(point) {
+        Closest::Indeterminate => None,
+        Closest::Intersection(p) | Closest::SinglePoint(p) => {
+            Some((p, Euclidean.distance(*point, p)))
+        }
+    };
+    match closest(coarse) {
+        Some((p, distance)) if distance >= NEAR_COAST => Some((p, distance)),
+        _ => closest(full),
+    }
+}
@@ -0,0 +1,35 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::Shark;
+
+/// Collective navigation with few informed leaders (Couzin et al. 2005): only the
+/// informed sharks know about goals, and they trade alignment for goal seeking.
+/// Everyone else just flocks, so a school ends up following its informed members.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Leadership {
+    /// Share of sharks that know about goals, 0..1
+    pub informed_fraction: f64,
+    /// Multiplier on the alignment strength of informed sharks
+    pub alignment_factor: f64,
+    /// Multiplier on the goal-seeking strength of informed sharks
+    pub goal_factor: f64,
+}
+
+impl Leadership {
+    /// Marks a random `informed_fraction` of the sharks as informed.
+    pub fn assign_informed<R: Rng>(&self, rng: &mut R, sharks: &mut [Shark]) {
+        for shark in sharks {
+            shark.informed = rng.random_bool(self.informed_fraction.clamp(0.0, 1.0));
+        }
+    }
+
+    /// Alignment and goal-seeking multipliers for one shark.
+    pub fn factors(&self, shark: &Shark) -> (f64, f64) {
+        if shark.informed {
+            (self.alignment_factor, self.goal_factor)
+        } else {
+            (1.0, 0.0)
+        }
+    }
+}
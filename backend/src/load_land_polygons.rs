@@ -1,48 +1,244 @@
+use flate2::read::GzDecoder;
 use geo::LineString;
 use geo::Polygon;
+use geo::{Contains, Point};
+use serde_json::Value;
+use shapefile::PolygonRing;
 use shapefile::Reader;
 use shapefile::Shape;
+use shapefile::record::polygon::GenericPolygon;
+use shapefile::record::traits::HasXY;
 use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 
-pub fn load_land_polygons(shapefile_path: &str) -> Result<Vec<Polygon<f64>>, Box<dyn Error>> {
+use crate::{RepairReport, is_degenerate, repair_polygon};
+
+/// Very coarse land outlines (gzipped JSON list of exterior rings),
+/// simplified from the Natural Earth 110m shapefile.
+const EMBEDDED_LAND: &[u8] = include_bytes!("../land/coarse_land.json.gz");
+
+/// Reads land from a GeoJSON file when its name ends in `.geojson` or
+/// `.json`, from a shapefile otherwise.
+pub fn load_land(path: impl AsRef<Path>) -> Result<Vec<Polygon<f64>>, Box<dyn Error>> {
+    let path = path.as_ref();
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension)
+            if extension.eq_ignore_ascii_case("geojson")
+                || extension.eq_ignore_ascii_case("json") =>
+        {
+            load_land_geojson(path)
+        }
+        _ => load_land_polygons(path),
+    }
+}
+
+/// Reads the polygons of a shapefile, Z and M ones included, one per part,
+/// see `polygon_parts`, skipping other shapes and polygons
+/// without area and repairing the rest, see `repair_polygon`. Fails when
+/// nothing usable is left.
+pub fn load_land_polygons(
+    shapefile_path: impl AsRef<Path>,
+) -> Result<Vec<Polygon<f64>>, Box<dyn Error>> {
+    let shapefile_path = shapefile_path.as_ref();
     let mut reader = Reader::from_path(shapefile_path)?;
-    let mut polygons = Vec::new();
+    let mut land = LandLoad::default();
 
     for record in reader.iter_shapes_and_records() {
         let (shape, _) = record?;
+        land.shapes += 1;
 
-        match shape {
-            Shape::Polygon(p) => {
-                // exterior ring
-                let exterior = LineString::from(
-                    p.rings()[0]
-                        .points()
-                        .iter()
-                        .map(|pt| (pt.x, pt.y))
-                        .collect::<Vec<_>>(),
-                );
-
-                // interior rings (holes)
-                let interiors = p.rings()[1..]
-                    .iter()
-                    .map(|ring| {
-                        LineString::from(
-                            ring.points()
-                                .iter()
-                                .map(|pt| (pt.x, pt.y))
-                                .collect::<Vec<_>>(),
-                        )
-                    })
-                    .collect::<Vec<_>>();
-
-                let poly = Polygon::new(exterior, interiors);
-                polygons.push(poly);
-            }
+        let parts = match shape {
+            Shape::Polygon(p) => polygon_parts(&p, &mut land.orphan_holes),
+            Shape::PolygonM(p) => polygon_parts(&p, &mut land.orphan_holes),
+            Shape::PolygonZ(p) => polygon_parts(&p, &mut land.orphan_holes),
             _ => {
-                // skip non-polygons
+                land.not_polygons += 1;
+                continue;
+            }
+        };
+        land.add(parts);
+    }
+    land.finish(shapefile_path)
+}
+
+/// Reads the `Polygon` and `MultiPolygon` geometries of a GeoJSON file, in
+/// a `FeatureCollection`, a `Feature`, a `GeometryCollection` or bare, the
+/// same way `load_land_polygons` reads a shapefile. Coordinates are
+/// longitude and latitude, as the format has it; altitudes are ignored.
+pub fn load_land_geojson(path: impl AsRef<Path>) -> Result<Vec<Polygon<f64>>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let geojson: Value = serde_json::from_reader(BufReader::new(File::open(path)?))
+        .map_err(|e| format!("{} isn't JSON: {}", path.display(), e))?;
+    let mut land = LandLoad::default();
+    read_geojson(&geojson, &mut land).map_err(|e| format!("{}: {}", path.display(), e))?;
+    land.finish(path)
+}
+
+fn read_geojson(object: &Value, land: &mut LandLoad) -> Result<(), Box<dyn Error>> {
+    match object["type"].as_str() {
+        Some("FeatureCollection") => {
+            let features = object["features"]
+                .as_array()
+                .ok_or("FeatureCollection without features")?;
+            for feature in features {
+                read_geojson(feature, land)?;
+            }
+        }
+        // features without a geometry are allowed, and have no land
+        Some("Feature") if object["geometry"].is_null() => {
+            land.shapes += 1;
+            land.not_polygons += 1;
+        }
+        Some("Feature") => read_geojson(&object["geometry"], land)?,
+        Some("GeometryCollection") => {
+            let geometries = object["geometries"]
+                .as_array()
+                .ok_or("GeometryCollection without geometries")?;
+            for geometry in geometries {
+                read_geojson(geometry, land)?;
+            }
+        }
+        Some("Polygon") => {
+            land.shapes += 1;
+            let rings = serde_json::from_value(object["coordinates"].clone())?;
+            land.add(geojson_polygon(rings)?);
+        }
+        Some("MultiPolygon") => {
+            land.shapes += 1;
+            let polygons: Vec<Vec<Vec<Vec<f64>>>> =
+                serde_json::from_value(object["coordinates"].clone())?;
+            let mut parts = Vec::new();
+            for rings in polygons {
+                parts.extend(geojson_polygon(rings)?);
+            }
+            land.add(parts);
+        }
+        Some(_) => {
+            land.shapes += 1;
+            land.not_polygons += 1;
+        }
+        None => return Err("GeoJSON object without a type".into()),
+    }
+    Ok(())
+}
+
+/// The exterior ring comes first in GeoJSON, then the holes. `None` without
+/// any ring.
+fn geojson_polygon(rings: Vec<Vec<Vec<f64>>>) -> Result<Option<Polygon<f64>>, Box<dyn Error>> {
+    let mut rings = rings.into_iter().map(|ring| {
+        ring.into_iter()
+            .map(|position| match position[..] {
+                [x, y, ..] => Ok((x, y)),
+                _ => Err(format!(
+                    "position {:?} without longitude and latitude",
+                    position
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(LineString::from)
+    });
+    let Some(exterior) = rings.next() else {
+        return Ok(None);
+    };
+    Ok(Some(Polygon::new(
+        exterior?,
+        rings.collect::<Result<_, _>>()?,
+    )))
+}
+
+/// What `load_land_polygons` and `load_land_geojson` kept and skipped.
+#[derive(Default)]
+struct LandLoad {
+    polygons: Vec<Polygon<f64>>,
+    shapes: usize,
+    not_polygons: usize,
+    degenerate: usize,
+    orphan_holes: usize,
+    repairs: RepairReport,
+}
+
+impl LandLoad {
+    /// Keeps the parts of one shape, a shape without any counting as
+    /// degenerate.
+    fn add(&mut self, parts: impl IntoIterator<Item = Polygon<f64>>) {
+        let mut any = false;
+        for poly in parts {
+            any = true;
+            if is_degenerate(&poly) {
+                self.degenerate += 1;
+            } else {
+                self.polygons
+                    .extend(repair_polygon(poly, &mut self.repairs));
             }
         }
+        if !any {
+            self.degenerate += 1;
+        }
+    }
+
+    /// The polygons kept, after reporting what was skipped or repaired.
+    fn finish(self, path: &Path) -> Result<Vec<Polygon<f64>>, Box<dyn Error>> {
+        let skipped = format!(
+            "{} shapes read, {} not polygons, {} degenerate, {} holes outside any part",
+            self.shapes, self.not_polygons, self.degenerate, self.orphan_holes
+        );
+        if self.polygons.is_empty() {
+            return Err(format!("no usable polygons in {}: {}", path.display(), skipped).into());
+        }
+        if self.not_polygons + self.degenerate + self.orphan_holes > 0 {
+            eprintln!("skipped shapes in {}: {}", path.display(), skipped);
+        }
+        if self.repairs.any() {
+            eprintln!("repaired polygons in {}: {}", path.display(), self.repairs);
+        }
+        Ok(self.polygons)
+    }
+}
+
+/// Splits a shape into one polygon per outer ring, going by the shapefile's
+/// ring orientation rather than ring order. Each hole goes to the part that
+/// contains it, or to the part before it when none does, as files written by
+/// some tools hold slightly misplaced holes; holes before any part are counted
+/// in `orphan_holes` and dropped. Z and M values are ignored.
+fn polygon_parts<P: HasXY>(
+    shape: &GenericPolygon<P>,
+    orphan_holes: &mut usize,
+) -> Vec<Polygon<f64>> {
+    let ring = |points: &[P]| {
+        LineString::from(points.iter().map(|pt| (pt.x(), pt.y())).collect::<Vec<_>>())
+    };
+    let mut parts: Vec<Polygon<f64>> = Vec::new();
+    let mut holes = Vec::new();
+    for r in shape.rings() {
+        match r {
+            PolygonRing::Outer(points) => parts.push(Polygon::new(ring(points), vec![])),
+            PolygonRing::Inner(points) => holes.push((parts.len(), ring(points))),
+        }
     }
 
-    Ok(polygons)
+    for (parts_before, hole) in holes {
+        let containing = hole.0.first().and_then(|first| {
+            parts
+                .iter()
+                .rposition(|part| part.contains(&Point::from(*first)))
+        });
+        match containing.or(parts_before.checked_sub(1)) {
+            Some(part) => parts[part].interiors_push(hole),
+            None => *orphan_holes += 1,
+        }
+    }
+    parts
+}
+
+/// Loads the coastline compiled into the binary, used when no shapefile is available.
+pub fn load_embedded_land_polygons() -> Vec<Polygon<f64>> {
+    let rings: Vec<Vec<(f64, f64)>> = serde_json::from_reader(GzDecoder::new(EMBEDDED_LAND))
+        .expect("embedded land data is valid");
+
+    rings
+        .into_iter()
+        .map(|ring| Polygon::new(LineString::from(ring), vec![]))
+        .collect()
 }
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use futures_util::future::join_all;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::tick::broadcast_rate;
+
+/// `--mode loadtest` settings, e.g.
+/// `--clients 500 --server ws://host:25555 --duration 30 --mix full=1 --mix position=4`
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Load test, with --mode loadtest")]
+pub struct LoadTestOptions {
+    /// Server to connect the clients to
+    #[arg(long, default_value = "ws://127.0.0.1:25555")]
+    pub server: String,
+    #[arg(long, default_value_t = 100)]
+    pub clients: usize,
+    /// Seconds to run for
+    #[arg(long, default_value = "30", value_parser = parse_seconds)]
+    pub duration: Duration,
+    /// Subscriptions to spread the clients over, by weight. `full` takes every
+    /// field, anything else is passed on as the `fields` query parameter.
+    #[arg(long, default_value = "full=1", value_parser = parse_mix)]
+    pub mix: Vec<(String, u32)>,
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    let secs = text.parse().map_err(|e| format!("{}", e))?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Reads `fields=weight`, e.g. `position=4`.
+fn parse_mix(entry: &str) -> Result<(String, u32), String> {
+    let (fields, weight) = entry
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected fields=weight, got {}", entry))?;
+    let weight = weight.parse().map_err(|e| format!("bad weight: {}", e))?;
+    Ok((fields.to_string(), weight))
+}
+
+impl LoadTestOptions {
+    /// Which subscription client `i` uses, spread proportionally to the weights.
+    fn subscription(&self, i: usize) -> usize {
+        let total: u32 = self.mix.iter().map(|(_, weight)| weight).sum();
+        let mut slot = (i as u32) % total.max(1);
+        for (index, (_, weight)) in self.mix.iter().enumerate() {
+            if slot < *weight {
+                return index;
+            }
+            slot -= weight;
+        }
+        0
+    }
+}
+
+#[derive(Debug, Default)]
+struct ClientReport {
+    connect: Option<Duration>,
+    /// Delay behind the first client that received the same tick, full subscriptions only
+    lags: Vec<Duration>,
+    frames: u64,
+    /// Time from the first frame to the end of the run
+    receiving: Duration,
+}
+
+/// Connects many WebSocket consumers to a running server and reports how well
+/// it keeps up with them.
+pub async fn run_loadtest(options: LoadTestOptions) {
+    println!(
+        "load testing {} with {} clients for {:?}",
+        options.server, options.clients, options.duration
+    );
+    let deadline = Instant::now() + options.duration;
+    let first_seen = Arc::new(Mutex::new(HashMap::new()));
+
+    let clients = (0..options.clients).map(|i| {
+        let subscription = options.subscription(i);
+        let fields = &options.mix[subscription].0;
+        let url = if fields == "full" {
+            options.server.clone()
+        } else {
+            format!(
+                "{}/?fields={}",
+                options.server.trim_end_matches('/'),
+                fields
+            )
+        };
+        let first_seen = first_seen.clone();
+        async move {
+            let report = run_client(url, fields == "full", deadline, first_seen).await;
+            (subscription, report)
+        }
+    });
+    let reports = join_all(clients).await;
+
+    for (index, (fields, _)) in options.mix.iter().enumerate() {
+        let reports: Vec<_> = reports
+            .iter()
+            .filter(|(subscription, _)| *subscription == index)
+            .map(|(_, report)| report)
+            .collect();
+        let connected = reports.iter().filter(|r| r.connect.is_some()).count();
+        let connects: Vec<_> = reports.iter().filter_map(|r| r.connect).collect();
+        let lags: Vec<_> = reports
+            .iter()
+            .flat_map(|r| r.lags.iter().copied())
+            .collect();
+        let frames: u64 = reports.iter().map(|r| r.frames).sum();
+        let expected: f64 = reports
+            .iter()
+            .map(|r| r.receiving.as_secs_f64() * broadcast_rate() as f64)
+            .sum();
+        let drop_rate = if expected > 0.0 {
+            (1.0 - frames as f64 / expected).max(0.0)
+        } else {
+            0.0
+        };
+
+        println!("subscription {}:", fields);
+        println!(
+            "  clients: {} connected, {} failed",
+            connected,
+            reports.len() - connected
+        );
+        println!("  connect ms p50/p95/p99: {}", percentiles(connects));
+        if !lags.is_empty() {
+            println!("  fan-out lag ms p50/p95/p99: {}", percentiles(lags));
+        }
+        // a server running behind its tick rate shows up here as well
+        println!(
+            "  frames: {}, dropped vs {} per second: {:.1}%",
+            frames,
+            broadcast_rate(),
+            drop_rate * 100.0
+        );
+    }
+}
+
+async fn run_client(
+    url: String,
+    full: bool,
+    deadline: Instant,
+    first_seen: Arc<Mutex<HashMap<u64, Instant>>>,
+) -> ClientReport {
+    let mut report = ClientReport::default();
+    let started = Instant::now();
+    let mut stream = match connect_async(&url).await {
+        Ok((stream, _)) => stream,
+        Err(e) => {
+            eprintln!("client failed to connect: {}", e);
+            return report;
+        }
+    };
+    report.connect = Some(started.elapsed());
+
+    let mut first_frame = None;
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let _ = tokio::time::timeout(remaining, async {
+        while let Some(Ok(message)) = stream.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            // the events following a frame, see `serialize_events`
+            if text.starts_with("{\"events\"") {
+                continue;
+            }
+            let now = Instant::now();
+            first_frame.get_or_insert(now);
+            report.frames += 1;
+            if full && let Some(tick) = frame_tick(&text) {
+                let first = *first_seen.lock().unwrap().entry(tick).or_insert(now);
+                report.lags.push(now - first);
+            }
+        }
+    })
+    .await;
+    if let Some(first_frame) = first_frame {
+        report.receiving = Instant::now() - first_frame;
+    }
+    report
+}
+
+/// Pulls the tick out of a full frame without parsing all the sharks before it.
+/// The simulation tick is the first one, `state_hash` has another further on.
+fn frame_tick(frame: &str) -> Option<u64> {
+    let start = frame.find("\"tick\":")? + "\"tick\":".len();
+    let digits: String = frame[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+fn percentiles(mut samples: Vec<Duration>) -> String {
+    if samples.is_empty() {
+        return "-".to_string();
+    }
+    samples.sort();
+    let at = |p: f64| {
+        let index = ((samples.len() - 1) as f64 * p).round() as usize;
+        samples[index].as_secs_f64() * 1000.0
+    };
+    format!("{:.1}/{:.1}/{:.1}", at(0.5), at(0.95), at(0.99))
+}
@@ -1,153 +1,434 @@
 mod generate_point;
+use std::error::Error;
 use std::net::SocketAddr;
-use std::ops::Deref;
 use std::sync::Arc;
-use std::time::Duration;
 
-use futures_util::SinkExt;
-use futures_util::StreamExt;
 pub use generate_point::random_point;
 pub use generate_point::random_point_in_water;
 
 mod tick;
 
+mod species;
+pub use species::{Species, SpeciesParams};
+
+mod attraction_points;
+pub use attraction_points::{AttractionPoint, CategoryAffinity, GoalCatalog, Months};
+
 mod shark;
-use geo::Point;
-use geo::Polygon;
 pub use shark::Shark;
 
+mod kernel;
+pub use kernel::WeightKernel;
+
+mod schools;
+pub use schools::SchoolStats;
+pub use schools::SchoolTracker;
+
+mod leadership;
+pub use leadership::Leadership;
+
+mod shark_rng;
+pub use shark_rng::SharkRng;
+
+mod units;
+pub use units::{Km, KmPerHour, RadPerSec};
+
+mod frame_budget;
+pub use frame_budget::{FrameAction, FrameBudget, FrameStats, OverrunPolicy};
+
+mod tracks;
+pub use tracks::{Track, TrackDecimation};
+
+mod history;
+pub use history::{FrameHistory, SharedHistory};
+
+#[cfg(feature = "chaos")]
+mod chaos;
+#[cfg(feature = "chaos")]
+pub use chaos::Chaos;
+
+mod boundary;
+pub use boundary::{Boundary, BoundaryStats, Edge, EdgeCounts};
+
+mod steering;
+pub use steering::SteeringScheme;
+
+mod spatial;
+pub use spatial::SpatialGrid;
+
+mod events;
+pub use events::{
+    Event, EventCursor, EventGap, EventKind, EventLog, EventStream, serialize_events,
+};
+
+mod buoys;
+pub use buoys::{Buoy, BuoyConfig};
+
+mod contacts;
+pub use contacts::{ContactConfig, ContactTracker};
+
+mod user_goals;
+pub use user_goals::{UserGoal, UserGoalLimits, UserGoals};
+
+mod environment;
+pub use environment::{CurrentField, Environment, Raster};
+
+mod productivity;
+pub use productivity::ProductivityConfig;
+
+mod day_night;
+pub use day_night::SimClock;
+
+mod ranges;
+pub use ranges::{RangeMode, SpeciesRange, SpeciesRangeConfig};
+
+mod prey;
+pub use prey::{PreyConfig, PreyField, PreySchool};
+
+mod hot_events;
+pub use hot_events::{HotEvent, HotEventConfig, HotEventRegion, HotEvents};
+
+mod simulation_config;
+pub use simulation_config::SimulationConfig;
+
 mod simulation;
 pub use simulation::Simulation;
 
+mod simulation_state;
+pub use simulation_state::{SimulationScratch, SimulationState};
+
 mod load_land_polygons;
+pub use load_land_polygons::load_embedded_land_polygons;
 pub use load_land_polygons::load_land_polygons;
+pub use load_land_polygons::{load_land, load_land_geojson};
 
-use tokio::net::TcpListener;
-use tokio::net::TcpStream;
-use tokio::sync::RwLock;
-use tokio_tungstenite::accept_async;
-use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::tungstenite::Result;
+mod land_geojson;
+pub use land_geojson::land_to_geojson;
+
+mod land_tiers;
+
+mod land_field;
+
+mod land_repair;
+pub use land_repair::{RepairReport, is_degenerate, repair_polygon};
+
+mod land_summary;
+pub use land_summary::LandSummary;
+
+mod snapshot;
+pub use snapshot::FieldMask;
+pub use snapshot::pack_columnar;
+pub use snapshot::pack_snapshot;
+pub use snapshot::serialize_aggregated;
+pub use snapshot::serialize_batch;
+pub use snapshot::serialize_columnar;
+pub use snapshot::serialize_delta;
+pub use snapshot::serialize_history;
+pub use snapshot::serialize_quantized;
+pub use snapshot::serialize_snapshot;
+
+#[cfg(test)]
+mod frame_diff;
+
+mod config;
+pub use config::CONFIG_PATH;
+pub use config::Config;
+
+mod data_dir;
+pub use data_dir::DataDir;
+
+mod archipelago;
+pub use archipelago::generate_archipelago;
+
+mod world;
+pub use world::WorldPreset;
+
+mod state_hash;
+pub use state_hash::StateHash;
+
+mod snapshots;
+pub use snapshots::{Snapshot, SnapshotPublisher, Snapshots, snapshot_channel};
+
+mod physics;
+pub use physics::Command;
+pub use physics::PhysicsHandle;
+pub use physics::SharedConfig;
+pub use physics::spawn_physics_thread;
 
-use crate::tick::TPS;
+mod recorder;
+pub use recorder::{RecordedFrame, Recorder, RecorderConfig};
 
+mod stats_series;
+pub use stats_series::{Resolution, StatsSample, StatsSeries};
+
+mod summary;
+pub use summary::WorldSummary;
+
+mod neighbor_graph;
+pub use neighbor_graph::NeighborGraph;
+
+mod views;
+pub use views::{SavedView, ViewStore};
+
+mod admin;
+pub use admin::{Admin, AdminCommand, AdminReply, AdminRequest};
+
+mod arrow_stream;
+pub use arrow_stream::ArrowStream;
+
+mod http_api;
+
+mod standby;
+
+mod loadtest;
+pub use loadtest::{LoadTestOptions, run_loadtest};
+
+mod replay;
+pub use replay::{Playback, Recording, ReplayOptions, spawn_replay_thread};
+
+mod args;
+pub use args::{Args, Mode};
+
+mod self_test;
+pub use self_test::{MAX_ON_LAND_SHARE, SELF_TEST_TICKS, run_self_test};
+
+mod qos;
+pub use qos::{QosController, QosLevel, QosReport};
+
+mod shutdown;
+pub use shutdown::{SHUTDOWN_GRACE, Shutdown};
+
+mod frames;
+pub use frames::{FRAME_BUFFER, Frame, spawn_frame_publisher};
+
+mod server;
+pub use server::ListenAddr;
+pub use server::MAX_CONNECTIONS;
+
+use clap::Parser;
+use futures_util::future::try_join_all;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use tokio::sync::Semaphore;
+use tokio_tungstenite::tungstenite::Result;
+
+/// Relative to the data directory, see `DataDir`
 pub const SHAPEFILE_PATH: &'static str = "land/ne_110m_land.shp";
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let mut rng = rand::rng();
-    let mut attraction_points = vec![];
-
-    attraction_points.push(Point::new(167.0, -28.299544));
-    attraction_points.push(Point::new(41.202671, -39.916056));
-    attraction_points.push(Point::new(30.744196, 131.833367));
-    attraction_points.push(Point::new(39.361909, -21.325484));
-    attraction_points.push(Point::new(39.903416, -66.289550));
-    attraction_points.push(Point::new(36.666216, -148.250722));
-    attraction_points.push(Point::new(-143.194445, -18.377986));
-    attraction_points.push(Point::new(171.527249, -13.651325));
-    attraction_points.push(Point::new(186.228940, -26.049380));
-    attraction_points.push(Point::new(55.205441, -28.730335));
-    attraction_points.push(Point::new(52.043905, -36.138984));
-    attraction_points.push(Point::new(-24.249463, 36.257563));
-    attraction_points.push(Point::new(-48.143093, 44.800109));
-    attraction_points.push(Point::new(-64.082863, 37.817378));
-    attraction_points.push(Point::new(-68.246077, 32.671749));
-    attraction_points.push(Point::new(-136.808344, 37.278424));
-    attraction_points.push(Point::new(-142.077571, 28.743580));
-    attraction_points.push(Point::new(-160.695504, 20.771523));
-
-    let land_polygons = Arc::new(load_land_polygons(SHAPEFILE_PATH).unwrap());
-    let simulation = Arc::new(RwLock::new(Simulation::new(
-        300,
-        &mut rng,
-        &land_polygons,
-        attraction_points,
-    )));
-
-    tokio::spawn(rerender_loop(simulation.clone(), land_polygons.clone()));
+pub const WORKER_THREADS: usize = 4;
+pub const MAX_BLOCKING_THREADS: usize = 16;
 
-    println!("server is up vro");
-    let server = TcpListener::bind("0.0.0.0:25555")
-        .await
-        .expect("Failed to bind to address");
+fn main() -> Result<()> {
+    let args = Args::parse();
+    tick::set_rates(args.tps, args.broadcast_hz);
+    if args.self_test {
+        std::process::exit(if run_self_test(&args) { 0 } else { 1 });
+    }
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(WORKER_THREADS)
+        .max_blocking_threads(MAX_BLOCKING_THREADS)
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime");
 
-    loop {
-        let (stream, addr) = server.accept().await?;
-        tokio::spawn(handle_connection(stream, addr, simulation.clone()));
+    match args.mode {
+        Mode::Loadtest => {
+            runtime.block_on(run_loadtest(args.loadtest));
+            Ok(())
+        }
+        Mode::Serve => runtime.block_on(run(args)),
     }
 }
 
-async fn handle_connection(
-    stream: TcpStream,
-    addr: SocketAddr,
-    simulation: Arc<RwLock<Simulation>>,
-) -> Result<()> {
-    let ws_stream = accept_async(stream).await?;
-    println!("New WebSocket connection: {}", addr);
-
-    let (mut write, _read) = ws_stream.split();
-
-    loop {
-        tokio::time::sleep(Duration::from_millis(1000 / TPS)).await;
-        let simulation_json;
-        {
-            let sim = simulation.read().await;
-            simulation_json = serde_json::to_string(sim.deref()).unwrap();
+async fn run(args: Args) -> Result<()> {
+    let data_dir = DataDir::from_env();
+    let mut config = Config::load(&args.config, &data_dir).expect("Failed to load config file");
+    if !args.bind.is_empty() {
+        config.listeners = args.bind.clone();
+    }
+    let listen_addrs = config
+        .listeners
+        .iter()
+        .map(|addr| addr.parse::<ListenAddr>())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Invalid listener address in config");
+    let http_addr = config.http_listener.as_ref().map(|addr| {
+        addr.parse::<SocketAddr>()
+            .expect("Invalid HTTP listener address in config")
+    });
+
+    let (simulation, simulation_config) = build_simulation(&args, &config, &data_dir)
+        .unwrap_or_else(|e| panic!("Failed to set up the simulation: {}", e));
+    let perception_radius = simulation_config.perception_radius;
+    let history = FrameHistory::new(config.history_seconds, config.track_decimation);
+    let (physics, _physics_thread) = match &args.replay.path {
+        Some(path) => {
+            let recording = Recording::open(path).expect("Failed to open the recording");
+            spawn_replay_thread(
+                simulation,
+                simulation_config,
+                recording,
+                args.replay.speed,
+                history,
+            )
+            .expect("Failed to start the replay")
         }
+        None => spawn_physics_thread(
+            simulation,
+            simulation_config,
+            config.state_hash_interval,
+            FrameBudget::new(config.overrun_policy, config.physics_substeps),
+            history,
+            config
+                .recording
+                .clone()
+                .map(|recording| Recorder::spawn(recording).expect("Failed to start recording")),
+        ),
+    };
+
+    if let Some(productivity) = config.productivity.clone() {
+        tokio::spawn(productivity::refresh_hotspots(
+            productivity,
+            data_dir.clone(),
+            physics.clone(),
+        ));
+    }
 
-        // dbg!(&simulation_json);
+    let views = ViewStore::open(&config.views_file).expect("Failed to read saved views");
+    let admin = Admin::new(
+        physics.clone(),
+        config.simulation_file.clone(),
+        data_dir,
+        args.shapefile.clone(),
+        views,
+    );
 
-        write.send(Message::Text(simulation_json.into())).await?;
+    if let Some(primary) = config.replicate_from.clone() {
+        tokio::spawn(standby::follow_primary(primary, physics.clone()));
     }
-}
 
-// let lat = rng.random_range(-90.0..=90.0);
-//     let lon = rng.random_range(-180.0..=180.0);
-
-async fn rerender_loop(
-    simulation: Arc<RwLock<Simulation>>,
-    land_polygons: Arc<Vec<Polygon<f64>>>,
-) -> Result<()> {
-    let mut ticks = 0;
-    let map_bounds = (-180., -85., 180.0, 85.0);
-    loop {
-        ticks = ticks + 1;
-        print!("\x1B[2J\x1B[1;1H");
-        println!("ticks: {}", ticks);
-        {
-            let mut simulation = simulation.write().await;
-            simulation.step(
-                1.0 / TPS as f64, // dt
-                4.0,
-                2.0,
-                0.1,
-                0.1,
-                0.05,
-                &land_polygons,
-                map_bounds,
-                10.,
-                100.,
-                0.5,
-                6.0,
-                10.,
-                0.3,
-            );
+    println!("server is up vro");
+    let shutdown = Shutdown::on_ctrl_c();
+    let connection_slots = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    let frames = spawn_frame_publisher(physics.snapshots.clone());
+    let websockets = try_join_all(listen_addrs.into_iter().map(|listen_addr| {
+        server::serve(
+            listen_addr,
+            physics.clone(),
+            frames.clone(),
+            admin.clone(),
+            connection_slots.clone(),
+            shutdown.clone(),
+        )
+    }));
+    let http = async {
+        match http_addr {
+            Some(addr) => {
+                let shutdown = shutdown.clone();
+                http_api::serve_http(addr, physics.clone(), perception_radius, admin, shutdown)
+                    .await
+            }
+            None => Ok(()),
         }
-        tokio::time::sleep(Duration::from_millis(1000 / TPS)).await;
+    };
+    tokio::try_join!(websockets, http).expect("Listener failed");
+
+    // every connection hands its slot back once its close frame is out
+    let all_slots = connection_slots.acquire_many(MAX_CONNECTIONS as u32);
+    if tokio::time::timeout(SHUTDOWN_GRACE, all_slots)
+        .await
+        .is_err()
+    {
+        eprintln!(
+            "connections still open after {:?}, exiting anyway",
+            SHUTDOWN_GRACE
+        );
     }
+    Ok(())
 }
 
-//  &mut self,
-//         dt: f64,
-//         perception_radius: f64,
-//         separation_distance: f64,
-//         cohesion_strength: f64,
-//         separation_strength: f64,
-//         alignment_strength: f64,
-//         land_shape_file: &[Polygon<f64>],
-//         map_bounds: (f64, f64, f64, f64),
-//         land_avoid_radius: f64,
-//         land_avoid_strength: f64,
-//         border_margin: f64,
-//         border_strength: f64,
+/// Sets up the world of `config`: land, goals, data files and all. Fails on
+/// the first data file that is missing or doesn't parse.
+pub fn build_simulation(
+    args: &Args,
+    config: &Config,
+    data_dir: &DataDir,
+) -> Result<(Simulation, SimulationConfig), Box<dyn Error>> {
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let goals = match &config.goals_file {
+        Some(goals_file) => {
+            let path = data_dir.resolve(goals_file)?;
+            AttractionPoint::load(path)
+                .map_err(|e| format!("Failed to read the attraction points: {}", e))?
+        }
+        None => GoalCatalog {
+            points: AttractionPoint::builtin(),
+            ..GoalCatalog::default()
+        },
+    };
+    let land_polygons = config.world.land_polygons(data_dir, &args.shapefile);
+    let land_summary = LandSummary::new(&land_polygons);
+    println!("world: {}", land_summary);
+    for warning in land_summary.warnings() {
+        eprintln!("WARNING: {}", warning);
+    }
+    let land_polygons = Arc::new(land_polygons);
+    let simulation_config = match &config.simulation_file {
+        Some(path) => SimulationConfig::load(data_dir.resolve(path)?)
+            .map_err(|e| format!("Failed to load simulation config: {}", e))?,
+        None => SimulationConfig::default(),
+    };
+    let mut simulation = Simulation::new(args.sharks, &mut rng, land_polygons, goals.points)?;
+    simulation.category_affinity = Arc::new(goals.category_affinity);
+    simulation.heading_smoothing_secs = Some(0.3);
+    simulation.seed = seed;
+    simulation.replica = config.replicate_from.is_some();
+    simulation.boundary = config.boundary;
+    simulation.steering = config.steering;
+    simulation.contacts = config.contacts.clone().map(ContactTracker::new);
+    simulation.buoys = config.buoys.iter().map(Buoy::new).collect();
+    simulation.prey = config.prey.clone().map(PreyField::new);
+    simulation.hot_events = config.hot_events.clone().map(HotEvents::new);
+    let mut environment = Environment::default();
+    if let Some(sst_file) = &config.sst_file {
+        let path = data_dir.resolve(sst_file)?;
+        let sst = Raster::load_csv(path)
+            .map_err(|e| format!("Failed to read the sea surface temperature grid: {}", e))?;
+        environment.sst = Some(Arc::new(sst));
+    }
+    if let Some(currents_file) = &config.currents_file {
+        let path = data_dir.resolve(currents_file)?;
+        let currents = CurrentField::load_csv(path)
+            .map_err(|e| format!("Failed to read the current field: {}", e))?;
+        environment.currents = Some(Arc::new(currents));
+    }
+    if let Some(productivity) = &config.productivity {
+        let chlorophyll = productivity
+            .load_raster(data_dir)
+            .map_err(|e| format!("Failed to read the chlorophyll raster: {}", e))?;
+        // the hotspots take over from the built-in attraction points
+        simulation.goals.clear();
+        simulation.hotspots = productivity.pick_hotspots(&chlorophyll);
+        environment.chlorophyll = Some(Arc::new(chlorophyll));
+    }
+    simulation.environment = Arc::new(environment);
+    let ranges = config
+        .species_ranges
+        .iter()
+        .map(|range| SpeciesRange::load(range, data_dir))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read a species range: {}", e))?;
+    simulation.ranges = Arc::new(ranges);
+    #[cfg(feature = "chaos")]
+    {
+        simulation.chaos = config.chaos;
+    }
+    if let Some(leadership) = &config.leadership {
+        leadership.assign_informed(&mut rng, &mut simulation.sharks);
+    }
+    simulation.leadership = config.leadership;
+    simulation.user_goals = UserGoals::new(config.user_goals);
+    Ok((simulation, simulation_config))
+}
@@ -0,0 +1,63 @@
+use geo::{Contains, Rect};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{Km, Simulation, SpatialGrid};
+
+/// Which sharks are within a distance of each other, as an adjacency list.
+/// Sharks are identified by their `id`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NeighborGraph {
+    pub tick: u64,
+    pub radius_km: f64,
+    pub adjacency: Vec<NeighborList>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NeighborList {
+    pub shark: u64,
+    pub neighbors: Vec<u64>,
+}
+
+impl NeighborGraph {
+    /// Links every pair of sharks closer than `radius`. With a `bbox` only the
+    /// sharks inside it are included, and only the links among them.
+    pub fn new(simulation: &Simulation, radius: Km, bbox: Option<Rect<f64>>) -> Self {
+        let radius_deg = radius.to_degrees();
+        let included: Vec<bool> = simulation
+            .sharks
+            .iter()
+            .map(|shark| bbox.is_none_or(|bbox| bbox.contains(&shark.position)))
+            .collect();
+        let grid = SpatialGrid::new(
+            radius_deg,
+            simulation.sharks.iter().map(|shark| shark.position),
+        );
+
+        let adjacency = simulation
+            .sharks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| included[*i])
+            .map(|(i, shark)| {
+                let mut neighbors: Vec<u64> = grid
+                    .within(shark.position, radius_deg)
+                    .map(|(j, _)| j)
+                    .filter(|&j| j != i && included[j])
+                    .map(|j| simulation.sharks[j].id)
+                    .collect();
+                neighbors.sort_unstable();
+                NeighborList {
+                    shark: shark.id,
+                    neighbors,
+                }
+            })
+            .collect();
+
+        Self {
+            tick: simulation.tick,
+            radius_km: radius.0,
+            adjacency,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use tokio::sync::mpsc;
+
+use crate::tick::{Scheduler, server_time_ms, tps};
+use crate::{
+    FrameAction, FrameBudget, FrameHistory, FrameStats, Recorder, SharedHistory, Simulation,
+    SimulationConfig, SnapshotPublisher, Snapshots, StateHash, snapshot_channel,
+};
+
+/// A change to apply to the simulation between two steps.
+pub type Command = Box<dyn FnOnce(&mut Simulation, &mut SimulationConfig) + Send>;
+
+/// The steering parameters in use. The physics thread takes a copy at the
+/// start of every tick, so changes apply from the next one.
+pub type SharedConfig = Arc<RwLock<SimulationConfig>>;
+
+/// The async side's view of the physics thread: commands go in, snapshots come out.
+#[derive(Clone)]
+pub struct PhysicsHandle {
+    pub commands: mpsc::UnboundedSender<Command>,
+    pub snapshots: Snapshots,
+    /// Recently published snapshots, for clients that ask for a backlog on connect
+    pub history: SharedHistory,
+    pub config: SharedConfig,
+}
+
+/// Runs the simulation on its own OS thread so heavy steps never block the tokio workers.
+pub fn spawn_physics_thread(
+    simulation: Simulation,
+    config: SimulationConfig,
+    state_hash_interval: u64,
+    frame_budget: FrameBudget,
+    history: FrameHistory,
+    recorder: Option<Recorder>,
+) -> (PhysicsHandle, JoinHandle<()>) {
+    let (command_tx, command_rx) = mpsc::unbounded_channel();
+    let (snapshot_tx, snapshot_rx) = snapshot_channel(Arc::new(simulation.clone()));
+    let history = Arc::new(Mutex::new(history));
+    let thread_history = history.clone();
+    let config = Arc::new(RwLock::new(config));
+    let thread_config = config.clone();
+
+    let thread = std::thread::Builder::new()
+        .name("physics".to_string())
+        .spawn(move || {
+            physics_loop(
+                simulation,
+                thread_config,
+                state_hash_interval,
+                frame_budget,
+                command_rx,
+                snapshot_tx,
+                thread_history,
+                recorder,
+            )
+        })
+        .expect("Failed to spawn physics thread");
+
+    let handle = PhysicsHandle {
+        commands: command_tx,
+        snapshots: snapshot_rx,
+        history,
+        config,
+    };
+    (handle, thread)
+}
+
+#[allow(clippy::too_many_arguments)] // everything the thread owns, passed once
+fn physics_loop(
+    mut simulation: Simulation,
+    shared_config: SharedConfig,
+    state_hash_interval: u64,
+    mut frame_budget: FrameBudget,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    snapshots: SnapshotPublisher,
+    history: SharedHistory,
+    recorder: Option<Recorder>,
+) {
+    let mut scheduler = Scheduler::new();
+    loop {
+        let tick_started = Instant::now();
+        print!("\x1B[2J\x1B[1;1H");
+        println!("ticks: {}", simulation.tick);
+
+        let config = {
+            let mut config = shared_config.write().unwrap();
+            while let Ok(command) = commands.try_recv() {
+                command(&mut simulation, &mut config);
+            }
+            config.clone()
+        };
+        simulation.user_goals.expire(Instant::now());
+
+        let tick_before = simulation.tick;
+        // a replica gets its state from the primary instead
+        if !simulation.replica && !simulation.paused {
+            simulation.advance(1.0 / tps() as f64, frame_budget.substeps(), &config);
+        }
+
+        // with sub-steps the tick can jump over a multiple of the interval
+        if state_hash_interval > 0
+            && simulation.tick / state_hash_interval > tick_before / state_hash_interval
+        {
+            let state_hash = StateHash::new(simulation.tick, &simulation.sharks);
+            println!("state hash @ tick {}: {}", state_hash.tick, state_hash.hash);
+            simulation.state_hash = Some(state_hash);
+        }
+
+        #[cfg(feature = "chaos")]
+        if let Some(delay) = simulation.chaos.tick_delay() {
+            println!("chaos: stalling tick for {:?}", delay);
+            std::thread::sleep(delay);
+        }
+        #[cfg(feature = "chaos")]
+        let chaos_drop = simulation.chaos.drop_frame();
+        #[cfg(not(feature = "chaos"))]
+        let chaos_drop = false;
+
+        let action = frame_budget.record(tick_started.elapsed(), scheduler.tick_budget());
+        simulation.frame_stats = FrameStats {
+            measured_tps: scheduler.measured_tps(),
+            ..frame_budget.stats()
+        };
+        // every receiver is gone only once the server has shut down
+        if !scheduler.broadcast_due() {
+            // simulated only, in between two broadcasts
+        } else if action == FrameAction::BroadcastSkipped {
+            println!("tick over budget, skipping broadcast");
+        } else if chaos_drop {
+            println!("chaos: dropping frame");
+        } else {
+            simulation.server_time_ms = server_time_ms();
+            simulation.frame += 1;
+            // the events of ticks not broadcast wait for this frame
+            simulation.events = Arc::new(simulation.event_log.take());
+            let snapshot = Arc::new(simulation.clone());
+            history.lock().unwrap().push(snapshot.clone());
+            if let Some(recorder) = &recorder {
+                recorder.record(snapshot.clone());
+            }
+            if snapshots.publish(snapshot).is_err() {
+                return;
+            }
+        }
+        scheduler.wait_for_next_tick();
+    }
+}
@@ -0,0 +1,325 @@
+use geo::Point;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+use crate::shark::MAX_ENERGY;
+use crate::simulation::wrap_angle;
+use crate::{Km, KmPerHour, Shark, SharkRng, SpatialGrid, random_point};
+
+/// How far a school sees the others it flocks with
+const SCHOOL_PERCEPTION: Km = Km(400.0);
+/// Schools closer than this push apart
+const SCHOOL_SEPARATION: Km = Km(100.0);
+/// Largest turn of a school per second
+const SCHOOL_TURN_RATE: f64 = 1.5;
+/// Newly spawned schools appear up to this far from their site
+const SPAWN_SPREAD: Km = Km(300.0);
+/// Schools thinned out below this share of `PreyConfig::biomass` break up
+const MIN_BIOMASS_SHARE: f64 = 0.01;
+
+/// Settings for `PreyField`, e.g.
+/// `{"max_schools": 80, "spawn_per_sec": 2.0, "hunt_radius_km": 800}`.
+/// Prey takes over from the goals: sharks chase the closest school in range
+/// instead, and the goals and hotspots only decide where schools appear,
+/// the less grazed ones more often.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PreyConfig {
+    /// Most fish schools around at once
+    pub max_schools: usize,
+    /// New schools per second while there are fewer than `max_schools`
+    pub spawn_per_sec: f64,
+    pub speed: KmPerHour,
+    /// Sharks closer than this to a school chase it
+    pub hunt_radius_km: f64,
+    /// Sharks closer than this to a school feed on it
+    pub catch_radius_km: f64,
+    /// Food in a new school
+    pub biomass: f64,
+    /// Most food a feeding shark takes per second, however much prey is in
+    /// reach, scaled by `SpeciesParams::max_intake`
+    pub bite_per_sec: f64,
+    /// Food in reach at which a shark takes half of `bite_per_sec`, divided
+    /// by `SpeciesParams::attack_rate`
+    pub half_saturation: f64,
+    /// Seconds for a site to get over half of the grazing around it
+    pub site_recovery_secs: f64,
+    /// Energy a shark gains per unit of food, up to `MAX_ENERGY`
+    pub energy_per_biomass: f64,
+}
+
+impl Default for PreyConfig {
+    fn default() -> Self {
+        Self {
+            max_schools: 80,
+            spawn_per_sec: 2.0,
+            speed: KmPerHour(150_000.),
+            hunt_radius_km: 800.0,
+            catch_radius_km: 60.0,
+            biomass: 1.0,
+            bite_per_sec: 0.5,
+            half_saturation: 0.5,
+            site_recovery_secs: 60.0,
+            energy_per_biomass: 0.5,
+        }
+    }
+}
+
+impl PreyConfig {
+    /// Fails on a `biomass` that isn't above 0, which grazing and breaking
+    /// up schools are measured against.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.biomass > 0.0 && self.biomass.is_finite()) {
+            return Err(format!(
+                "prey.biomass must be above 0, not {}",
+                self.biomass
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A school of fish, moving as one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreySchool {
+    pub id: u64,
+    pub position: Point<f64>,
+    pub rotation_rad: f64,
+    /// Food left, the school is gone when it runs out
+    pub biomass: f64,
+    /// Where it was spawned around, grazing it counts against the site
+    #[serde(skip)]
+    pub site: Option<Point<f64>>,
+}
+
+/// The fish schools sharks hunt. They flock loosely among themselves, get
+/// eaten by sharks within `PreyConfig::catch_radius_km` and are replaced
+/// near productive waters.
+///
+/// A shark eats at a Holling type-II rate: in proportion to the food in
+/// reach while there is little, levelling off at `PreyConfig::bite_per_sec`.
+/// A crowd of sharks on a patch then strips it faster than any one of them
+/// eats more, and as sites get grazed new schools turn up at the others, so
+/// aggregations break up and the hotspots take turns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreyField {
+    #[serde(skip)]
+    config: PreyConfig,
+    pub schools: Vec<PreySchool>,
+    #[serde(skip)]
+    next_id: u64,
+    /// Fraction of a school owed to the next spawn
+    #[serde(skip)]
+    spawn_credit: f64,
+    /// Food eaten from the schools of each site, wearing off over
+    /// `PreyConfig::site_recovery_secs`
+    #[serde(skip)]
+    grazed: Vec<(Point<f64>, f64)>,
+}
+
+impl PreyField {
+    pub fn new(config: PreyConfig) -> Self {
+        Self {
+            config,
+            schools: Vec::new(),
+            next_id: 0,
+            spawn_credit: 0.0,
+            grazed: Vec::new(),
+        }
+    }
+
+    /// Sharks chase schools within this many degrees.
+    pub fn hunt_radius(&self) -> f64 {
+        Km(self.config.hunt_radius_km).to_degrees()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point<f64>> + '_ {
+        self.schools.iter().map(|school| school.position)
+    }
+
+    /// Takes over schools from elsewhere, e.g. a primary being mirrored, with
+    /// their ids. New schools are numbered after the highest of them.
+    pub fn adopt_schools(&mut self, schools: Vec<PreySchool>) {
+        let after_highest = schools
+            .iter()
+            .map(|school| school.id + 1)
+            .max()
+            .unwrap_or(0);
+        self.next_id = self.next_id.max(after_highest);
+        self.schools = schools;
+    }
+
+    /// Moves the schools, lets the sharks feed and spawns new schools around
+    /// `sites`, or anywhere in water without any. `in_water` keeps schools
+    /// off land.
+    pub fn update(
+        &mut self,
+        sharks: &mut [Shark],
+        sites: &[Point<f64>],
+        in_water: impl Fn(Point<f64>) -> bool,
+        dt: f64,
+        rng: &mut SharkRng,
+    ) {
+        self.recover_sites(sites, dt);
+        self.swim(&in_water, dt);
+        self.feed(sharks, dt);
+        self.spawn(&in_water, dt, rng);
+    }
+
+    fn swim(&mut self, in_water: &impl Fn(Point<f64>) -> bool, dt: f64) {
+        let perception = SCHOOL_PERCEPTION.to_degrees();
+        let separation = SCHOOL_SEPARATION.to_degrees();
+        let speed = self.config.speed.to_degrees_per_sec();
+        let grid = SpatialGrid::new(perception, self.positions());
+        let old = self.schools.clone();
+
+        for (i, school) in self.schools.iter_mut().enumerate() {
+            let (mut center, mut heading, mut apart, mut count) =
+                ((0.0, 0.0), (0.0, 0.0), (0.0, 0.0), 0.0);
+            for (j, dist) in grid.within(school.position, perception) {
+                if i == j {
+                    continue;
+                }
+                let other = &old[j];
+                center.0 += other.position.x();
+                center.1 += other.position.y();
+                heading.0 += other.rotation_rad.cos();
+                heading.1 += other.rotation_rad.sin();
+                if dist > 0.0 && dist < separation {
+                    apart.0 += (school.position.x() - other.position.x()) / dist;
+                    apart.1 += (school.position.y() - other.position.y()) / dist;
+                }
+                count += 1.0;
+            }
+
+            let mut desired = (school.rotation_rad.cos(), school.rotation_rad.sin());
+            if count > 0.0 {
+                desired.0 += (center.0 / count - school.position.x()) / perception
+                    + heading.0 / count
+                    + apart.0;
+                desired.1 += (center.1 / count - school.position.y()) / perception
+                    + heading.1 / count
+                    + apart.1;
+            }
+            let max_turn = SCHOOL_TURN_RATE * dt;
+            let turn = wrap_angle(desired.1.atan2(desired.0) - school.rotation_rad);
+            school.rotation_rad += turn.clamp(-max_turn, max_turn);
+
+            let next = Point::new(
+                school.position.x() + speed * school.rotation_rad.cos() * dt,
+                school.position.y() + speed * school.rotation_rad.sin() * dt,
+            );
+            if in_water(next) {
+                school.position = next;
+            } else {
+                // turn back from the coast
+                school.rotation_rad = wrap_angle(school.rotation_rad + PI);
+            }
+        }
+    }
+
+    /// Lets the grazing of `sites` wear off, forgetting sites no longer around.
+    fn recover_sites(&mut self, sites: &[Point<f64>], dt: f64) {
+        let recovery = 0.5_f64.powf(dt / self.config.site_recovery_secs);
+        self.grazed.retain(|(site, _)| sites.contains(site));
+        for site in sites {
+            match self.grazed.iter_mut().find(|(grazed, _)| grazed == site) {
+                Some((_, eaten)) => *eaten *= recovery,
+                None => self.grazed.push((*site, 0.0)),
+            }
+        }
+    }
+
+    /// Every shark within the catch radius of schools eats from all of them,
+    /// at the rate the food in reach allows, see `PreyField`.
+    fn feed(&mut self, sharks: &mut [Shark], dt: f64) {
+        let catch_radius = Km(self.config.catch_radius_km).to_degrees();
+        let grid = SpatialGrid::new(catch_radius, self.positions());
+        let mut in_reach = Vec::new();
+        for shark in sharks.iter_mut() {
+            in_reach.clear();
+            in_reach.extend(grid.within(shark.position, catch_radius).map(|(j, _)| j));
+            let food: f64 = in_reach.iter().map(|&j| self.schools[j].biomass).sum();
+            if food <= 0.0 {
+                continue;
+            }
+            let species = shark.species.params();
+            let half_saturation = self.config.half_saturation / species.attack_rate;
+            let rate =
+                self.config.bite_per_sec * species.max_intake * food / (half_saturation + food);
+            let intake = (rate * dt).min(food);
+            // from each school in proportion to its food
+            for &j in &in_reach {
+                let school = &mut self.schools[j];
+                let eaten = intake * school.biomass / food;
+                school.biomass = (school.biomass - eaten).max(0.0);
+                if let Some(site) = school.site
+                    && let Some((_, grazed)) = self.grazed.iter_mut().find(|(s, _)| *s == site)
+                {
+                    *grazed += eaten;
+                }
+            }
+            shark.energy = (shark.energy + intake * self.config.energy_per_biomass).min(MAX_ENERGY);
+        }
+        let min_biomass = self.config.biomass * MIN_BIOMASS_SHARE;
+        self.schools.retain(|school| school.biomass > min_biomass);
+    }
+
+    fn spawn(&mut self, in_water: &impl Fn(Point<f64>) -> bool, dt: f64, rng: &mut SharkRng) {
+        self.spawn_credit += self.config.spawn_per_sec * dt;
+        while self.spawn_credit >= 1.0 && self.schools.len() < self.config.max_schools {
+            self.spawn_credit -= 1.0;
+            // a few tries for a spot in water, the site may be near a coast
+            let spot = (0..10)
+                .map(|_| {
+                    let site = self.pick_site(rng);
+                    (site, spawn_point(site, rng))
+                })
+                .find(|(_, p)| in_water(*p));
+            let Some((site, position)) = spot else {
+                continue;
+            };
+            self.schools.push(PreySchool {
+                id: self.next_id,
+                position,
+                rotation_rad: rng.random_range(0.0..(2.0 * PI)),
+                biomass: self.config.biomass,
+                site,
+            });
+            self.next_id += 1;
+        }
+        // don't save up while full
+        self.spawn_credit = self.spawn_credit.min(1.0);
+    }
+
+    /// A random site, one grazed of a school's worth of food half as likely
+    /// as an untouched one. `None` without sites.
+    fn pick_site(&self, rng: &mut SharkRng) -> Option<Point<f64>> {
+        let weight = |eaten: f64| 1.0 / (1.0 + eaten / self.config.biomass);
+        let total: f64 = self.grazed.iter().map(|&(_, eaten)| weight(eaten)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = rng.random_range(0.0..total);
+        for &(site, eaten) in &self.grazed {
+            pick -= weight(eaten);
+            if pick < 0.0 {
+                return Some(site);
+            }
+        }
+        self.grazed.last().map(|&(site, _)| site)
+    }
+}
+
+/// Somewhere around `site`, or anywhere on the map without one.
+fn spawn_point(site: Option<Point<f64>>, rng: &mut SharkRng) -> Point<f64> {
+    let Some(site) = site else {
+        return random_point(rng);
+    };
+    let spread = SPAWN_SPREAD.to_degrees();
+    Point::new(
+        site.x() + rng.random_range(-spread..=spread),
+        site.y() + rng.random_range(-spread..=spread),
+    )
+}
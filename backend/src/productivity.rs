@@ -0,0 +1,74 @@
+use geo::Point;
+use serde::Deserialize;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{DataDir, Environment, Km, PhysicsHandle, Raster};
+
+/// Foraging grounds from a chlorophyll-a raster, e.g. `{"file":
+/// "chlorophyll.csv", "hotspots": 18, "min_spacing_km": 1000,
+/// "refresh_secs": 3600}`. The file is read like `sst_file`, see
+/// `Raster::load_csv`, and read again every `refresh_secs` so a pipeline can
+/// swap in newer data.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductivityConfig {
+    pub file: String,
+    /// How many of the most productive spots become attraction points
+    pub hotspots: usize,
+    /// Closest two hotspots may be, so they don't all crowd one bloom
+    pub min_spacing_km: f64,
+    pub refresh_secs: f64,
+}
+
+impl ProductivityConfig {
+    pub fn load_raster(&self, data_dir: &DataDir) -> Result<Raster, Box<dyn Error>> {
+        Raster::load_csv(data_dir.resolve(&self.file)?)
+    }
+
+    pub fn pick_hotspots(&self, raster: &Raster) -> Vec<Point<f64>> {
+        raster.hotspots(self.hotspots, Km(self.min_spacing_km).to_degrees())
+    }
+}
+
+/// Reloads the raster every `refresh_secs` and hands the new hotspots to the
+/// physics thread. A failed reload keeps the previous ones.
+pub async fn refresh_hotspots(
+    config: ProductivityConfig,
+    data_dir: DataDir,
+    physics: PhysicsHandle,
+) {
+    let period = Duration::from_secs_f64(config.refresh_secs);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        let (config, data_dir) = (config.clone(), data_dir.clone());
+        let loaded = tokio::task::spawn_blocking(move || {
+            let raster = config.load_raster(&data_dir).map_err(|e| e.to_string())?;
+            let hotspots = config.pick_hotspots(&raster);
+            Ok::<_, String>((raster, hotspots))
+        })
+        .await
+        .unwrap();
+        let (raster, hotspots) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!(
+                    "failed to reload the chlorophyll raster, keeping the old hotspots: {}",
+                    e
+                );
+                continue;
+            }
+        };
+        let refreshed = physics.commands.send(Box::new(move |simulation, _| {
+            simulation.hotspots = hotspots;
+            simulation.environment = Arc::new(Environment {
+                chlorophyll: Some(Arc::new(raster)),
+                ..(*simulation.environment).clone()
+            });
+        }));
+        if refreshed.is_err() {
+            return;
+        }
+    }
+}
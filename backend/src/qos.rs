@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::time::Duration;
+
+/// How often a client on automatic QoS is pinged and its level reconsidered
+pub const QOS_INTERVAL: Duration = Duration::from_secs(1);
+/// Round trip above which a client counts as struggling
+const SLOW_RTT_MS: f64 = 250.0;
+/// Round trip below which a client counts as healthy
+const FAST_RTT_MS: f64 = 100.0;
+/// Frames a client may miss per interval before it counts as struggling
+const MAX_DROPPED: u64 = 2;
+/// Healthy intervals in a row before moving up a level
+const UPGRADE_AFTER: u32 = 5;
+/// Weight of the newest round trip in the running average
+const RTT_SMOOTHING: f64 = 0.3;
+
+/// How much a client gets, from everything down to a coarse overview. Each
+/// level sends fewer frames than the one before.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QosLevel {
+    /// Every frame, in the format the client asked for
+    #[default]
+    Full,
+    /// Every other frame
+    HalfRate,
+    /// Every other frame as `{"quantized": ...}`, see `serialize_quantized`
+    Quantized,
+    /// Every fourth frame as `{"aggregated": ...}`, see `serialize_aggregated`
+    Aggregated,
+}
+
+impl QosLevel {
+    /// Only every `frame_stride`th frame goes out.
+    pub fn frame_stride(self) -> u64 {
+        match self {
+            QosLevel::Full => 1,
+            QosLevel::HalfRate | QosLevel::Quantized => 2,
+            QosLevel::Aggregated => 4,
+        }
+    }
+
+    fn down(self) -> Self {
+        match self {
+            QosLevel::Full => QosLevel::HalfRate,
+            QosLevel::HalfRate => QosLevel::Quantized,
+            QosLevel::Quantized | QosLevel::Aggregated => QosLevel::Aggregated,
+        }
+    }
+
+    fn up(self) -> Self {
+        match self {
+            QosLevel::Full | QosLevel::HalfRate => QosLevel::Full,
+            QosLevel::Quantized => QosLevel::HalfRate,
+            QosLevel::Aggregated => QosLevel::Quantized,
+        }
+    }
+}
+
+/// Sent to the client whenever its level changes, as `{"qos": ...}`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct QosReport {
+    pub level: QosLevel,
+    pub rtt_ms: f64,
+    /// Frames missed during the last interval
+    pub dropped: u64,
+}
+
+/// Picks the QoS level of one connection from its round trips and missed frames.
+#[derive(Debug, Default)]
+pub struct QosController {
+    level: QosLevel,
+    rtt_ms: Option<f64>,
+    dropped: u64,
+    healthy_intervals: u32,
+    /// `Simulation::frame` of the last frame seen, sent or not
+    last_frame: Option<u64>,
+}
+
+impl QosController {
+    pub fn level(&self) -> QosLevel {
+        self.level
+    }
+
+    pub fn record_rtt(&mut self, rtt_ms: f64) {
+        self.rtt_ms = Some(match self.rtt_ms {
+            Some(average) => average + (rtt_ms - average) * RTT_SMOOTHING,
+            None => rtt_ms,
+        });
+    }
+
+    /// Notes a frame the connection picked up, counting the ones published
+    /// since the previous but never seen because the client was behind.
+    pub fn record_frame(&mut self, frame: u64) {
+        if let Some(last) = self.last_frame {
+            self.dropped += frame.saturating_sub(last + 1);
+        }
+        self.last_frame = Some(frame);
+    }
+
+    /// Moves one level down if the client struggled during the interval, or
+    /// one up after `UPGRADE_AFTER` healthy ones. Returns a report on change.
+    pub fn evaluate(&mut self) -> Option<QosReport> {
+        let rtt_ms = self.rtt_ms.unwrap_or(0.0);
+        let dropped = std::mem::take(&mut self.dropped);
+        let previous = self.level;
+
+        if rtt_ms > SLOW_RTT_MS || dropped > MAX_DROPPED {
+            self.level = self.level.down();
+            self.healthy_intervals = 0;
+        } else if rtt_ms < FAST_RTT_MS && dropped == 0 {
+            self.healthy_intervals += 1;
+            if self.healthy_intervals >= UPGRADE_AFTER {
+                self.level = self.level.up();
+                self.healthy_intervals = 0;
+            }
+        } else {
+            self.healthy_intervals = 0;
+        }
+
+        (self.level != previous).then_some(QosReport {
+            level: self.level,
+            rtt_ms,
+            dropped,
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use geo::{BoundingRect, Closest, ClosestPoint, Contains, Distance, Euclidean, Intersects};
+use geo::{Point, Polygon, Rect};
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::{DataDir, Species, load_land};
+
+/// How strictly a species is kept inside its range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeMode {
+    /// Sharks outside are steered back with `SimulationConfig::range_strength`
+    #[default]
+    Soft,
+    /// The outside counts as land: sharks avoid it like a coast and never
+    /// swim out once inside
+    Hard,
+}
+
+/// Where a species may go, e.g.
+/// `{"species": "whale_shark", "file": "ranges/whale_shark.shp", "mode": "hard"}`.
+/// The file is a polygon shapefile or GeoJSON found through the data directory.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpeciesRangeConfig {
+    pub species: Species,
+    pub file: String,
+    #[serde(default)]
+    pub mode: RangeMode,
+}
+
+/// The loaded range of one species.
+#[derive(Debug, Clone)]
+pub struct SpeciesRange {
+    pub species: Species,
+    pub mode: RangeMode,
+    polygons: Vec<Polygon<f64>>,
+    bounds: Vec<Rect<f64>>,
+}
+
+impl SpeciesRange {
+    pub fn load(config: &SpeciesRangeConfig, data_dir: &DataDir) -> Result<Self, Box<dyn Error>> {
+        let polygons = load_land(data_dir.resolve(&config.file)?)?;
+        Ok(Self::new(config.species, config.mode, polygons))
+    }
+
+    pub fn new(species: Species, mode: RangeMode, polygons: Vec<Polygon<f64>>) -> Self {
+        let bounds = polygons
+            .iter()
+            .map(|polygon| {
+                polygon
+                    .bounding_rect()
+                    .unwrap_or(Rect::new((0., 0.), (0., 0.)))
+            })
+            .collect();
+        Self {
+            species,
+            mode,
+            polygons,
+            bounds,
+        }
+    }
+
+    pub fn contains(&self, point: Point<f64>) -> bool {
+        self.polygons
+            .iter()
+            .zip(&self.bounds)
+            .any(|(polygon, bounds)| bounds.intersects(&point) && polygon.contains(&point))
+    }
+
+    /// Steering force back into the range: unit length towards the closest
+    /// edge from outside, and from inside away from edges closer than
+    /// `margin`, like `calculate_land_avoidance` does for coasts. 0 leaves
+    /// the inside alone.
+    pub fn steer_inside(&self, point: Point<f64>, margin: f64) -> Point<f64> {
+        let inside = self.contains(point);
+        let mut closest: Option<(Point<f64>, f64)> = None;
+        for polygon in &self.polygons {
+            let edge = match polygon.exterior().closest_point(&point) {
+                Closest::Intersection(p) | Closest::SinglePoint(p) => p,
+                Closest::Indeterminate => continue,
+            };
+            let dist = Euclidean.distance(point, edge);
+            if closest.is_none_or(|(_, best)| dist < best) {
+                closest = Some((edge, dist));
+            }
+        }
+        let Some((edge, dist)) = closest else {
+            return Point::new(0.0, 0.0);
+        };
+        if dist < f64::EPSILON || (inside && dist >= margin) {
+            return Point::new(0.0, 0.0);
+        }
+
+        let (dx, dy) = (edge.x() - point.x(), edge.y() - point.y());
+        if inside {
+            // away from the edge, harder the closer it is
+            let strength = (margin - dist) / margin;
+            Point::new(-dx / dist * strength, -dy / dist * strength)
+        } else {
+            Point::new(dx / dist, dy / dist)
+        }
+    }
+}
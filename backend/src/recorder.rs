@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Shark, Simulation};
+
+/// Starts every recording file, followed by the version of the frame layout
+pub const RECORDING_MAGIC: &[u8; 6] = b"SHREC\0";
+pub const RECORDING_VERSION: u16 = 2;
+/// Extension of recording files
+pub const RECORDING_EXTENSION: &str = "shrec";
+
+/// Settings for `Recorder`, e.g. `{"dir": "recordings", "max_file_mb": 64, "keep_files": 20}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RecorderConfig {
+    /// Where the files go, created when missing
+    pub dir: PathBuf,
+    /// A new file is started once the current one reaches this size
+    pub max_file_mb: f64,
+    /// Oldest files of the session are deleted beyond this many, 0 keeps them all
+    pub keep_files: usize,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("recordings"),
+            max_file_mb: 64.0,
+            keep_files: 0,
+        }
+    }
+}
+
+/// The state of one tick as written to disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub tick: u64,
+    /// See `tick::server_time_ms`
+    pub server_time_ms: f64,
+    pub sharks: Vec<Shark>,
+}
+
+impl RecordedFrame {
+    pub fn new(simulation: &Simulation) -> Self {
+        Self {
+            tick: simulation.tick,
+            server_time_ms: simulation.server_time_ms,
+            sharks: simulation.sharks.clone(),
+        }
+    }
+}
+
+/// File `part` of the session started at `session`, e.g.
+/// `recordings/1760000000-003.shrec`. Parts of a session sort in order.
+pub fn recording_part_path(dir: &Path, session: u64, part: u32) -> PathBuf {
+    dir.join(format!("{}-{:03}.{}", session, part, RECORDING_EXTENSION))
+}
+
+/// Appends every published frame to disk on a thread of its own, so slow
+/// disks never hold up the physics. A file is the magic and version followed
+/// by frames, each a little-endian `u32` length and a bincode `RecordedFrame`.
+#[derive(Clone)]
+pub struct Recorder {
+    frames: Sender<Arc<Simulation>>,
+}
+
+impl Recorder {
+    pub fn spawn(config: RecorderConfig) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        println!(
+            "recording to {}",
+            recording_part_path(&config.dir, session, 0).display()
+        );
+        let (frames, received) = channel();
+        std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || {
+                if let Err(e) = write_frames(&config, session, received) {
+                    eprintln!("recording stopped: {}", e);
+                }
+            })?;
+        Ok(Self { frames })
+    }
+
+    /// Queues a frame, dropped quietly once the recorder has stopped.
+    pub fn record(&self, frame: Arc<Simulation>) {
+        let _ = self.frames.send(frame);
+    }
+}
+
+/// Runs until every `Recorder` is dropped or writing fails.
+fn write_frames(
+    config: &RecorderConfig,
+    session: u64,
+    frames: Receiver<Arc<Simulation>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let max_bytes = (config.max_file_mb * 1024.0 * 1024.0) as u64;
+    let mut part = 0;
+    let mut file = start_part(&config.dir, session, part)?;
+    let mut written = 0;
+
+    for simulation in frames {
+        let frame = bincode::serialize(&RecordedFrame::new(&simulation))?;
+        if written > 0 && written + frame.len() as u64 > max_bytes {
+            file.flush()?;
+            part += 1;
+            file = start_part(&config.dir, session, part)?;
+            written = 0;
+            if config.keep_files > 0 && part as usize >= config.keep_files {
+                let oldest = part - config.keep_files as u32;
+                let _ = std::fs::remove_file(recording_part_path(&config.dir, session, oldest));
+            }
+        }
+        file.write_all(&(frame.len() as u32).to_le_bytes())?;
+        file.write_all(&frame)?;
+        written += 4 + frame.len() as u64;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+fn start_part(dir: &Path, session: u64, part: u32) -> std::io::Result<BufWriter<File>> {
+    let mut file = BufWriter::new(File::create(recording_part_path(dir, session, part))?);
+    file.write_all(RECORDING_MAGIC)?;
+    file.write_all(&RECORDING_VERSION.to_le_bytes())?;
+    Ok(file)
+}
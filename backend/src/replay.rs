@@ -0,0 +1,278 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+
+use tokio::sync::mpsc;
+
+use crate::recorder::{RECORDING_MAGIC, RECORDING_VERSION, recording_part_path};
+use crate::tick::{Scheduler, server_time_ms};
+use crate::{
+    Command, FrameHistory, PhysicsHandle, RecordedFrame, SharedConfig, SharedHistory, Simulation,
+    SimulationConfig, SnapshotPublisher, snapshot_channel,
+};
+
+/// `--replay` settings, e.g. `--replay recordings/1760000000-000.shrec --replay-speed 4`
+#[derive(Debug, Clone, clap::Args)]
+#[command(next_help_heading = "Replay")]
+pub struct ReplayOptions {
+    /// Plays this recording back instead of simulating, the first file of it
+    /// with the parts after it following on
+    #[arg(long = "replay", value_name = "FILE")]
+    pub path: Option<PathBuf>,
+    /// Recorded frames per tick to start with, negative plays backwards
+    #[arg(long = "replay-speed", default_value_t = 1.0, requires = "path")]
+    pub speed: f64,
+}
+
+/// Where a replay stands, sent in frames so clients can draw a seek bar.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Playback {
+    /// Recorded frames per tick: 1 plays at the recorded pace, 4 four times
+    /// as fast, negative backwards. `pause` and `resume` work as usual.
+    pub speed: f64,
+    pub first_tick: u64,
+    pub last_tick: u64,
+    /// Set by the `replay_seek` admin command, taken up on the next tick
+    #[serde(skip)]
+    pub seek_to: Option<u64>,
+}
+
+#[derive(Debug)]
+struct FrameLocation {
+    part: usize,
+    /// Start of the encoded frame, after its length
+    offset: u64,
+    len: u32,
+    tick: u64,
+}
+
+/// The frames of a recording across all its parts, read from disk as needed.
+#[derive(Debug)]
+pub struct Recording {
+    parts: Vec<File>,
+    frames: Vec<FrameLocation>,
+}
+
+impl Recording {
+    /// Opens `path` and the parts of the same session after it, e.g.
+    /// `1760000000-001.shrec` after `1760000000-000.shrec`, indexing every
+    /// complete frame.
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut recording = Self {
+            parts: Vec::new(),
+            frames: Vec::new(),
+        };
+        for part in part_paths(path) {
+            recording
+                .index_part(&part)
+                .map_err(|e| format!("{}: {}", part.display(), e))?;
+        }
+        if recording.frames.is_empty() {
+            return Err(format!("no frames in {}", path.display()).into());
+        }
+        Ok(recording)
+    }
+
+    fn index_part(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let mut header = [0; RECORDING_MAGIC.len() + 2];
+        file.read_exact(&mut header)?;
+        if &header[..RECORDING_MAGIC.len()] != RECORDING_MAGIC {
+            return Err("not a recording".into());
+        }
+        let version = u16::from_le_bytes([header[6], header[7]]);
+        if version != RECORDING_VERSION {
+            return Err(format!(
+                "recorded with version {}, this server reads {}",
+                version, RECORDING_VERSION
+            )
+            .into());
+        }
+
+        let part = self.parts.len();
+        let mut offset = header.len() as u64;
+        // the length, then the tick that bincode puts first
+        let mut prefix = [0; 12];
+        while offset + prefix.len() as u64 <= size {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut prefix)?;
+            let len = u32::from_le_bytes(prefix[..4].try_into().unwrap());
+            let tick = u64::from_le_bytes(prefix[4..].try_into().unwrap());
+            // a frame cut off when the recorder stopped ends the part
+            if offset + 4 + len as u64 > size {
+                break;
+            }
+            self.frames.push(FrameLocation {
+                part,
+                offset: offset + 4,
+                len,
+                tick,
+            });
+            offset += 4 + len as u64;
+        }
+        self.parts.push(file);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Index of the first frame at or after `tick`, the last frame past the end.
+    pub fn position_of(&self, tick: u64) -> usize {
+        self.frames
+            .partition_point(|frame| frame.tick < tick)
+            .min(self.frames.len() - 1)
+    }
+
+    pub fn read(&mut self, index: usize) -> Result<RecordedFrame, Box<dyn Error>> {
+        let location = &self.frames[index];
+        let file = &mut self.parts[location.part];
+        file.seek(SeekFrom::Start(location.offset))?;
+        let mut bytes = vec![0; location.len as usize];
+        file.read_exact(&mut bytes)?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
+    fn playback(&self, speed: f64) -> Playback {
+        Playback {
+            speed,
+            first_tick: self.frames[0].tick,
+            last_tick: self.frames[self.frames.len() - 1].tick,
+            seek_to: None,
+        }
+    }
+}
+
+/// `first` and the parts of its session that follow it on disk.
+fn part_paths(first: &Path) -> Vec<PathBuf> {
+    let dir = first.parent().unwrap_or(Path::new(""));
+    let session_and_part = first
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.split_once('-'))
+        .and_then(|(session, part)| Some((session.parse().ok()?, part.parse::<u32>().ok()?)));
+    let following: Vec<PathBuf> = match session_and_part {
+        Some((session, part)) => (part + 1..)
+            .map(|part| recording_part_path(dir, session, part))
+            .take_while(|path| path.exists())
+            .collect(),
+        None => Vec::new(),
+    };
+    std::iter::once(first.to_path_buf())
+        .chain(following)
+        .collect()
+}
+
+/// Plays `recording` back in place of the physics thread: the same handle,
+/// so the WebSocket and HTTP clients see frames just like from a live run.
+/// `simulation` supplies everything but the sharks, such as land and goals.
+pub fn spawn_replay_thread(
+    mut simulation: Simulation,
+    config: SimulationConfig,
+    mut recording: Recording,
+    speed: f64,
+    history: FrameHistory,
+) -> Result<(PhysicsHandle, JoinHandle<()>), Box<dyn Error>> {
+    let first = recording.read(0)?;
+    simulation.tick = first.tick;
+    simulation.adopt_sharks(first.sharks);
+    simulation.playback = Some(recording.playback(speed));
+    println!(
+        "replaying {} frames, ticks {} to {}",
+        recording.len(),
+        recording.frames[0].tick,
+        recording.frames[recording.len() - 1].tick
+    );
+
+    let (command_tx, command_rx) = mpsc::unbounded_channel();
+    let (snapshot_tx, snapshot_rx) = snapshot_channel(Arc::new(simulation.clone()));
+    let history = Arc::new(Mutex::new(history));
+    let config = Arc::new(RwLock::new(config));
+    let thread_history = history.clone();
+    let thread_config = config.clone();
+
+    let thread = std::thread::Builder::new()
+        .name("replay".to_string())
+        .spawn(move || {
+            replay_loop(
+                simulation,
+                recording,
+                thread_config,
+                command_rx,
+                snapshot_tx,
+                thread_history,
+            )
+        })?;
+
+    let handle = PhysicsHandle {
+        commands: command_tx,
+        snapshots: snapshot_rx,
+        history,
+        config,
+    };
+    Ok((handle, thread))
+}
+
+fn replay_loop(
+    mut simulation: Simulation,
+    mut recording: Recording,
+    shared_config: SharedConfig,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    snapshots: SnapshotPublisher,
+    history: SharedHistory,
+) {
+    let mut scheduler = Scheduler::new();
+    let last = (recording.len() - 1) as f64;
+    let mut position = 0.0;
+    let mut shown = 0;
+    loop {
+        {
+            let mut config = shared_config.write().unwrap();
+            while let Ok(command) = commands.try_recv() {
+                command(&mut simulation, &mut config);
+            }
+        }
+
+        let paused = simulation.paused;
+        let playback = simulation
+            .playback
+            .as_mut()
+            .expect("a replay always has playback");
+        if let Some(tick) = playback.seek_to.take() {
+            position = recording.position_of(tick) as f64;
+        } else if !paused {
+            position = (position + playback.speed).clamp(0.0, last);
+        }
+        if !scheduler.broadcast_due() {
+            scheduler.wait_for_next_tick();
+            continue;
+        }
+        let index = position as usize;
+        if index != shown {
+            match recording.read(index) {
+                Ok(frame) => {
+                    simulation.tick = frame.tick;
+                    simulation.adopt_sharks(frame.sharks);
+                    shown = index;
+                }
+                Err(e) => eprintln!("replay: failed to read frame {}: {}", index, e),
+            }
+        }
+
+        simulation.server_time_ms = server_time_ms();
+        simulation.frame += 1;
+        let snapshot = Arc::new(simulation.clone());
+        history.lock().unwrap().push(snapshot.clone());
+        // every receiver is gone only once the server has shut down
+        if snapshots.publish(snapshot).is_err() {
+            return;
+        }
+        scheduler.wait_for_next_tick();
+    }
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{Shark, SpatialGrid};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchoolStats {
+    /// Groups of two or more sharks
+    pub schools: usize,
+    /// Sharks not in any school
+    pub loners: usize,
+    /// School sizes, largest first
+    pub sizes: Vec<usize>,
+}
+
+/// Hands out school IDs that stay attached to a group from tick to tick.
+#[derive(Debug, Clone, Default)]
+pub struct SchoolTracker {
+    next_id: u64,
+}
+
+impl SchoolTracker {
+    /// Groups sharks into connected components of the proximity graph and stores
+    /// each shark's `school_id`. Two sharks link within `join_radius`; sharks that were
+    /// already schoolmates stay linked up to `leave_radius`, so schools don't flicker
+    /// apart when members hover around the threshold. A school keeps the ID most of its
+    /// members had last tick; larger schools get first pick when groups split.
+    pub fn update(
+        &mut self,
+        sharks: &mut [Shark],
+        join_radius: f64,
+        leave_radius: f64,
+    ) -> SchoolStats {
+        let n = sharks.len();
+        let mut parent: Vec<usize> = (0..n).collect();
+
+        // only pairs within the larger radius can link
+        let reach = join_radius.max(leave_radius);
+        let grid = SpatialGrid::new(reach, sharks.iter().map(|shark| shark.position));
+        for i in 0..n {
+            for (j, dist) in grid.within(sharks[i].position, reach) {
+                if j <= i {
+                    continue;
+                }
+                let were_schoolmates =
+                    sharks[i].school_id.is_some() && sharks[i].school_id == sharks[j].school_id;
+                if dist < join_radius || (were_schoolmates && dist < leave_radius) {
+                    union(&mut parent, i, j);
+                }
+            }
+        }
+
+        let mut components: HashMap<usize, Vec<usize>> = HashMap::new();
+        for i in 0..n {
+            let root = find(&mut parent, i);
+            components.entry(root).or_default().push(i);
+        }
+        let mut components: Vec<Vec<usize>> = components.into_values().collect();
+        // biggest first, ties broken by lowest member so the order is deterministic
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+
+        let mut stats = SchoolStats::default();
+        let mut claimed = Vec::new();
+        let mut new_ids = vec![None; n];
+        for members in &components {
+            if members.len() < 2 {
+                stats.loners += 1;
+                continue;
+            }
+
+            let mut votes: HashMap<u64, usize> = HashMap::new();
+            for &member in members {
+                if let Some(id) = sharks[member].school_id {
+                    *votes.entry(id).or_default() += 1;
+                }
+            }
+            let inherited = votes
+                .into_iter()
+                .filter(|(id, _)| !claimed.contains(id))
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+                .map(|(id, _)| id);
+            let id = inherited.unwrap_or_else(|| {
+                self.next_id += 1;
+                self.next_id
+            });
+            claimed.push(id);
+
+            for &member in members {
+                new_ids[member] = Some(id);
+            }
+            stats.schools += 1;
+            stats.sizes.push(members.len());
+        }
+
+        for (shark, id) in sharks.iter_mut().zip(new_ids) {
+            shark.school_id = id;
+        }
+        stats
+    }
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let root_a = find(parent, a);
+    let root_b = find(parent, b);
+    if root_a != root_b {
+        parent[root_b] = root_a;
+    }
+}
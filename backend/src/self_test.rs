@@ -0,0 +1,182 @@
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use crate::tick::tps;
+use crate::{
+    Args, Config, DataDir, ListenAddr, Recording, ViewStore, WorldPreset, build_simulation,
+    load_land,
+};
+
+/// Ticks `--self-test` runs
+pub const SELF_TEST_TICKS: u64 = 100;
+/// Share of shark positions over the run allowed on land. Land avoidance is
+/// a steering force, so sharks cut a corner of the coast now and then; with
+/// it broken they'd be on land about as often as the map is.
+pub const MAX_ON_LAND_SHARE: f64 = 0.05;
+
+/// Counts the failed checks, printing a line for each check.
+#[derive(Default)]
+struct Checks {
+    failed: usize,
+}
+
+impl Checks {
+    fn check<T, E: Display>(&mut self, name: &str, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                println!("ok    {}", name);
+                Some(value)
+            }
+            Err(e) => {
+                println!("FAIL  {}: {}", name, e);
+                self.failed += 1;
+                None
+            }
+        }
+    }
+}
+
+/// `--self-test`: loads the config and every data file it names, runs
+/// `SELF_TEST_TICKS` ticks without serving anything and checks the sharks
+/// start in the water and mostly stay there, keep numbers that are numbers,
+/// and the ticks keep up with `--tps`. True when every check passed.
+pub fn run_self_test(args: &Args) -> bool {
+    let mut checks = Checks::default();
+    let data_dir = DataDir::from_env();
+    let Some(mut config) = checks.check("config", Config::load(&args.config, &data_dir)) else {
+        return false;
+    };
+    if !args.bind.is_empty() {
+        config.listeners = args.bind.clone();
+    }
+    for listener in &config.listeners {
+        checks.check(
+            &format!("listener {}", listener),
+            listener.parse::<ListenAddr>(),
+        );
+    }
+    if let Some(listener) = &config.http_listener {
+        checks.check(
+            &format!("http listener {}", listener),
+            listener.parse::<SocketAddr>(),
+        );
+    }
+
+    // the world falls back to the embedded coastline, which is no good for a demo
+    if let WorldPreset::NaturalEarth = config.world {
+        let land = data_dir
+            .resolve(&args.shapefile)
+            .map_err(|e| e.to_string())
+            .and_then(|path| load_land(path).map_err(|e| e.to_string()));
+        checks.check(&format!("land {}", args.shapefile), land);
+    }
+    checks.check(
+        &format!("saved views {}", config.views_file),
+        ViewStore::open(&config.views_file),
+    );
+    if let Some(path) = &args.replay.path {
+        checks.check(
+            &format!("recording {}", path.display()),
+            Recording::open(path),
+        );
+    }
+    let Some((mut simulation, simulation_config)) = checks.check(
+        "world and data files",
+        build_simulation(args, &config, &data_dir),
+    ) else {
+        return false;
+    };
+    // a check leaves no files behind
+    if let Some(contacts) = &mut simulation.contacts {
+        contacts.stop_exporting();
+    }
+
+    let spawned_on_land = simulation
+        .sharks
+        .iter()
+        .filter(|shark| !simulation.in_water(shark.position))
+        .count();
+    checks.check(
+        "sharks spawn in water",
+        match spawned_on_land {
+            0 => Ok(()),
+            _ => Err(format!("{} on land", spawned_on_land)),
+        },
+    );
+
+    let dt = 1.0 / tps() as f64;
+    let mut tick_times = Vec::with_capacity(SELF_TEST_TICKS as usize);
+    let mut not_a_number = None;
+    let (mut positions, mut on_land) = (0, 0);
+    for _ in 0..SELF_TEST_TICKS {
+        let started = Instant::now();
+        simulation.advance(dt, 1, &simulation_config);
+        tick_times.push(started.elapsed());
+
+        positions += simulation.sharks.len();
+        for shark in &simulation.sharks {
+            let numbers = [
+                shark.position.x(),
+                shark.position.y(),
+                shark.rotation_rad,
+                shark.speed,
+                shark.energy,
+                shark.depth,
+                shark.stress,
+            ];
+            if not_a_number.is_none() && numbers.iter().any(|n| !n.is_finite()) {
+                not_a_number = Some((simulation.tick, shark.id));
+            }
+            if !simulation.in_water(shark.position) {
+                on_land += 1;
+            }
+        }
+    }
+
+    checks.check(
+        "no NaN",
+        match not_a_number {
+            Some((tick, id)) => Err(format!("shark {} at tick {}", id, tick)),
+            None => Ok(()),
+        },
+    );
+    let on_land_share = on_land as f64 / positions.max(1) as f64;
+    checks.check(
+        &format!("{:.1}% of shark positions on land", on_land_share * 100.0),
+        if on_land_share > MAX_ON_LAND_SHARE {
+            Err(format!("more than {}%", MAX_ON_LAND_SHARE * 100.0))
+        } else {
+            Ok(())
+        },
+    );
+    // the second half taking much longer means something piles up tick on tick
+    let (first, second) = tick_times.split_at(tick_times.len() / 2);
+    let mean = |times: &[Duration]| times.iter().sum::<Duration>() / times.len() as u32;
+    let achievable = 1.0 / mean(&tick_times).as_secs_f64();
+    checks.check(
+        &format!("{:.0} ticks per second achievable", achievable),
+        if achievable < tps() as f64 {
+            Err(format!("below the {} of --tps", tps()))
+        } else if mean(second) > mean(first) * 2 {
+            Err(format!(
+                "ticks slowed from {:?} to {:?}",
+                mean(first),
+                mean(second)
+            ))
+        } else {
+            Ok(())
+        },
+    );
+
+    println!(
+        "{} with {} sharks: {}",
+        args.config,
+        simulation.sharks.len(),
+        match checks.failed {
+            0 => "all checks passed".to_string(),
+            failed => format!("{} checks failed", failed),
+        }
+    );
+    checks.failed == 0
+}
@@ -0,0 +1,607 @@
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures_util::SinkExt;
+use futures_util::{Sink, Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpSocket, UnixListener};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{Semaphore, broadcast, mpsc};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::Result;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::{accept_hdr_async, tungstenite};
+
+use crate::buoys::{BUOY_READING_INTERVAL, BuoyReadings};
+use crate::history::MAX_HISTORY_SECONDS;
+use crate::qos::QOS_INTERVAL;
+use crate::tick::server_time_ms;
+use crate::{
+    Admin, AdminCommand, AdminReply, AdminRequest, Event, EventCursor, EventGap, FieldMask, Frame,
+    PhysicsHandle, QosController, QosLevel, Shutdown, Simulation, Snapshots, serialize_aggregated,
+    serialize_batch, serialize_delta, serialize_events, serialize_history, serialize_quantized,
+    serialize_snapshot,
+};
+
+/// Pending connections the kernel queues before `accept`
+pub const ACCEPT_BACKLOG: u32 = 1024;
+/// Open WebSocket connections, further clients get a 503 during the handshake
+pub const MAX_CONNECTIONS: usize = 500;
+/// Ticks between full frames for clients receiving deltas
+pub const DELTA_KEYFRAME_TICKS: u64 = 50;
+/// Most frames a client may ask to get batched into one message
+pub const MAX_BATCH_FRAMES: usize = 600;
+
+#[derive(Debug, Clone)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = std::net::AddrParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("unix:") {
+            Some(path) => Ok(ListenAddr::Unix(PathBuf::from(path))),
+            None => Ok(ListenAddr::Tcp(s.parse()?)),
+        }
+    }
+}
+
+impl Display for ListenAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{}", addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Binds `listen_addr` and serves every accepted client the frames of
+/// `spawn_frame_publisher`. All listeners draw from the same pool of
+/// connection slots. Stops accepting on shutdown.
+pub async fn serve(
+    listen_addr: ListenAddr,
+    physics: PhysicsHandle,
+    frames: broadcast::WeakSender<Arc<Frame>>,
+    admin: Admin,
+    connection_slots: Arc<Semaphore>,
+    mut shutdown: Shutdown,
+) -> std::io::Result<()> {
+    println!("listening on {}", listen_addr);
+    match listen_addr {
+        ListenAddr::Tcp(addr) => {
+            let socket = if addr.is_ipv4() {
+                TcpSocket::new_v4()?
+            } else {
+                TcpSocket::new_v6()?
+            };
+            socket.set_reuseaddr(true)?;
+            socket.bind(addr)?;
+            let server = socket.listen(ACCEPT_BACKLOG)?;
+            loop {
+                let (stream, peer) = tokio::select! {
+                    accepted = server.accept() => accepted?,
+                    _ = shutdown.wait() => return Ok(()),
+                };
+                dispatch(
+                    stream,
+                    peer.to_string(),
+                    &physics,
+                    &frames,
+                    &admin,
+                    &connection_slots,
+                    &shutdown,
+                );
+            }
+        }
+        ListenAddr::Unix(path) => {
+            // a socket file left behind by a previous run would make bind fail
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            let server = UnixListener::bind(&path)?;
+            loop {
+                let (stream, _) = tokio::select! {
+                    accepted = server.accept() => accepted?,
+                    _ = shutdown.wait() => return std::fs::remove_file(&path),
+                };
+                let peer = format!("unix:{}", path.display());
+                dispatch(
+                    stream,
+                    peer,
+                    &physics,
+                    &frames,
+                    &admin,
+                    &connection_slots,
+                    &shutdown,
+                );
+            }
+        }
+    }
+}
+
+fn dispatch<S>(
+    stream: S,
+    peer: String,
+    physics: &PhysicsHandle,
+    frames: &broadcast::WeakSender<Arc<Frame>>,
+    admin: &Admin,
+    connection_slots: &Arc<Semaphore>,
+    shutdown: &Shutdown,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    match connection_slots.clone().try_acquire_owned() {
+        Ok(permit) => {
+            let physics = physics.clone();
+            let frames = frames.clone();
+            let admin = admin.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                let result =
+                    handle_connection(stream, peer.clone(), physics, frames, admin, shutdown).await;
+                match result {
+                    Ok(reason) => println!("{} disconnected: {}", peer, reason),
+                    Err(e) => println!("{} disconnected: {}", peer, e),
+                }
+                drop(permit);
+            });
+        }
+        Err(_) => {
+            tokio::spawn(reject_overloaded(stream, peer));
+        }
+    }
+}
+
+/// Answers the handshake with a 503 so clients back off instead of hanging.
+async fn reject_overloaded<S: AsyncRead + AsyncWrite + Unpin>(stream: S, peer: String) {
+    println!(
+        "Rejecting {}: {} connections already open",
+        peer, MAX_CONNECTIONS
+    );
+    #[allow(clippy::result_large_err)] // the error type is fixed by tungstenite's Callback
+    let _ = accept_hdr_async(stream, |_: &Request, _: Response| {
+        let mut response = ErrorResponse::new(Some("server overloaded, retry later".into()));
+        *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        Err(response)
+    })
+    .await;
+}
+
+/// Reads `history_seconds=N` from a connect query string, 0 when absent,
+/// invalid or above `MAX_HISTORY_SECONDS`.
+fn history_seconds_from_query(query: &str) -> u64 {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("history_seconds="))
+        .and_then(|seconds| seconds.parse().ok())
+        .filter(|&seconds| seconds <= MAX_HISTORY_SECONDS)
+        .unwrap_or(0)
+}
+
+/// Whether a connect query string asks for the history as decimated tracks
+/// with `history=tracks` instead of whole frames, see `TrackDecimation`.
+fn history_tracks_from_query(query: &str) -> bool {
+    query.split('&').any(|pair| pair == "history=tracks")
+}
+
+/// Spacing in km of the points added along great circles with
+/// `great_circle_km=N`, for `history=tracks`, see `Track::follow_great_circles`.
+fn great_circle_from_query(query: &str) -> Option<f64> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("great_circle_km="))
+        .and_then(|km| km.parse().ok())
+}
+
+/// Whether a connect query string asks for deltas with `delta=1` or `delta=true`.
+fn delta_from_query(query: &str) -> bool {
+    query
+        .split('&')
+        .any(|pair| pair == "delta=1" || pair == "delta=true")
+}
+
+/// How a client wants its frames encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameFormat {
+    /// `serialize_snapshot`, the default
+    Json,
+    /// `format=packed`, see `pack_snapshot`
+    Packed,
+    /// `format=columnar`, see `serialize_columnar`
+    Columnar,
+    /// `format=columnar_packed`, see `pack_columnar`
+    ColumnarPacked,
+}
+
+/// Reads `format=...` from a connect query string, JSON when absent or
+/// unknown. Field masks and deltas only apply to JSON frames.
+fn format_from_query(query: &str) -> FrameFormat {
+    match query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("format="))
+    {
+        Some("packed") => FrameFormat::Packed,
+        Some("columnar") => FrameFormat::Columnar,
+        Some("columnar_packed") => FrameFormat::ColumnarPacked,
+        Some(_) | None => FrameFormat::Json,
+    }
+}
+
+/// Reads `batch=N` from a connect query string, capped at `MAX_BATCH_FRAMES`.
+/// 0 when absent or invalid, meaning no batching.
+fn batch_from_query(query: &str) -> usize {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("batch="))
+        .and_then(|frames| frames.parse().ok())
+        .map_or(0, |frames: usize| frames.min(MAX_BATCH_FRAMES))
+}
+
+/// Whether a connect query string asks for automatic quality of service with
+/// `qos=auto`, see `QosLevel`. Clients of a format other than JSON keep
+/// their format, only their rate drops.
+fn qos_from_query(query: &str) -> bool {
+    query.split('&').any(|pair| pair == "qos=auto")
+}
+
+/// What the reading half of a connection hands to the writing half.
+enum ClientEvent {
+    /// Answer to an admin command
+    Reply(String),
+    /// Round trip of a ping, in ms
+    Rtt(f64),
+}
+
+/// Runs the commands a client sends, e.g. `{"cmd": "add_goal", "lon": -40,
+/// "lat": 30, "session": "a1b2"}`, and hands each reply to the connection to send back, along
+/// with the round trip of every pong. Only the commands of
+/// `AdminCommand::for_clients` are taken, the rest are refused. Ends when the
+/// client closes its side.
+async fn read_commands<R>(mut read: R, admin: Admin, events: mpsc::UnboundedSender<ClientEvent>)
+where
+    R: Stream<Item = Result<Message>> + Unpin,
+{
+    while let Some(Ok(message)) = read.next().await {
+        let event = match message {
+            Message::Text(text) => {
+                let reply = match AdminRequest::from_json(&text) {
+                    Ok(request) if request.command.for_clients() => admin.execute(request).await,
+                    Ok(AdminRequest {
+                        command: AdminCommand::RemoveGoal { .. },
+                        ..
+                    }) => AdminReply::failure(
+                        "remove_goal only goes through POST /admin, remove_my_goal removes your own"
+                            .to_string(),
+                    ),
+                    Ok(_) => AdminReply::failure("only POST /admin takes that command".to_string()),
+                    Err(e) => AdminReply::failure(format!("unreadable command: {}", e)),
+                };
+                ClientEvent::Reply(serde_json::to_string(&reply).unwrap())
+            }
+            // our pings carry the server time they were sent at
+            Message::Pong(payload) => match <[u8; 8]>::try_from(&payload[..]) {
+                Ok(sent) => ClientEvent::Rtt(server_time_ms() - f64::from_le_bytes(sent)),
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+/// Feed of the `/buoys` path: sensor readings every `BUOY_READING_INTERVAL`
+/// instead of frames.
+async fn send_buoy_readings<W>(
+    mut write: W,
+    snapshots: Snapshots,
+    mut shutdown: Shutdown,
+) -> Result<&'static str>
+where
+    W: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let mut interval = tokio::time::interval(BUOY_READING_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait() => return say_goodbye(&mut write).await,
+        }
+        let snapshot = snapshots.latest();
+        let readings = BuoyReadings {
+            tick: snapshot.tick,
+            buoys: &snapshot.buoys,
+        };
+        let readings_json = serde_json::to_string(&readings).unwrap();
+        write.send(Message::Text(readings_json.into())).await?;
+    }
+}
+
+/// Sends the events that go with `frame`, right after it, along with those
+/// of the frames the client skipped since the last one it got and the gaps
+/// left by frames it lost.
+async fn send_events<W>(
+    write: &mut W,
+    frame: &Frame,
+    skipped: &mut Vec<Event>,
+    missed: &mut Vec<EventGap>,
+) -> Result<()>
+where
+    W: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    if skipped.is_empty() && missed.is_empty() {
+        if let Some(events) = &frame.events {
+            write.send(Message::Text(events.clone())).await?;
+        }
+        return Ok(());
+    }
+    let snapshot = &frame.snapshot;
+    skipped.extend(snapshot.events.iter().cloned());
+    let events_json = serialize_events(snapshot.frame, snapshot.tick, skipped, missed).unwrap();
+    write.send(Message::Text(events_json.into())).await?;
+    skipped.clear();
+    missed.clear();
+    Ok(())
+}
+
+/// Closes the connection with a close frame telling the client why.
+async fn say_goodbye<W>(write: &mut W) -> Result<&'static str>
+where
+    W: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let reason = "server shutting down";
+    write
+        .send(Message::Close(Some(CloseFrame {
+            code: CloseCode::Away,
+            reason: reason.into(),
+        })))
+        .await?;
+    Ok(reason)
+}
+
+/// Streams frames to one client until either side goes away, returning why.
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin + Send + 'static>(
+    stream: S,
+    peer: String,
+    physics: PhysicsHandle,
+    frames: broadcast::WeakSender<Arc<Frame>>,
+    admin: Admin,
+    mut shutdown: Shutdown,
+) -> Result<&'static str> {
+    let mut field_mask = FieldMask::all();
+    let mut history_seconds = 0;
+    let mut history_tracks = false;
+    let mut great_circle_km = None;
+    let mut delta = false;
+    let mut format = FrameFormat::Json;
+    let mut auto_qos = false;
+    let mut batch_frames = 0;
+    let mut buoy_feed = false;
+    #[allow(clippy::result_large_err)] // the error type is fixed by tungstenite's Callback
+    let ws_stream = accept_hdr_async(stream, |request: &Request, response: Response| {
+        buoy_feed = request.uri().path() == "/buoys";
+        if let Some(query) = request.uri().query() {
+            field_mask = FieldMask::from_query(query);
+            history_seconds = history_seconds_from_query(query);
+            history_tracks = history_tracks_from_query(query);
+            great_circle_km = great_circle_from_query(query);
+            delta = delta_from_query(query);
+            format = format_from_query(query);
+            auto_qos = qos_from_query(query);
+            batch_frames = batch_from_query(query);
+        }
+        Ok(response)
+    })
+    .await?;
+    println!("New WebSocket connection: {}", peer);
+    if buoy_feed {
+        return send_buoy_readings(ws_stream, physics.snapshots, shutdown).await;
+    }
+
+    let Some(mut frames) = frames.upgrade().map(|frames| frames.subscribe()) else {
+        return Ok("simulation stopped");
+    };
+    let (mut write, read) = ws_stream.split();
+    let (event_tx, mut events) = mpsc::unbounded_channel();
+    tokio::spawn(read_commands(read, admin, event_tx));
+
+    // frames up to this one go out as history, later ones live
+    let mut history_until = None;
+    if history_seconds > 0 {
+        let latest = physics.snapshots.latest();
+        let history_json = if history_tracks {
+            let tracks = physics.history.lock().unwrap().tracks(
+                Some(history_seconds),
+                latest.tick,
+                great_circle_km,
+            );
+            serde_json::json!({ "tracks": tracks }).to_string()
+        } else {
+            let past = physics
+                .history
+                .lock()
+                .unwrap()
+                .window(Some(history_seconds), latest.tick);
+            serialize_history(&past, &field_mask).unwrap()
+        };
+        write.send(Message::Text(history_json.into())).await?;
+        history_until = Some(latest.frame);
+    }
+
+    #[cfg(feature = "chaos")]
+    let slow_client_delay = physics.snapshots.latest().chaos.slow_client_delay();
+    #[cfg(feature = "chaos")]
+    if let Some(delay) = slow_client_delay {
+        println!("chaos: {} is a slow client, {:?} per frame", peer, delay);
+    }
+
+    // with deltas, the frame this client last got and the tick of its last full frame
+    let mut last_sent: Option<Arc<Simulation>> = None;
+    let mut keyframe_tick = 0;
+    let mut qos = auto_qos.then(QosController::default);
+    let mut qos_interval = tokio::time::interval(QOS_INTERVAL);
+    let mut frames_seen: u64 = 0;
+    let mut batched = Vec::with_capacity(batch_frames);
+    // the last frame batched and the frames lost since, for the next batch
+    let mut last_batched_frame: Option<u64> = None;
+    let mut batch_missed = None;
+    // events of frames not sent to this client, they go with the next one
+    let mut skipped_events = Vec::new();
+    // events lost with frames this client lagged out of, see `EventGap`
+    let mut event_cursor = EventCursor::default();
+    let mut missed_events = Vec::new();
+    loop {
+        let mut frame = tokio::select! {
+            // one frame per physics tick; the publisher only goes away when the physics thread stops
+            received = frames.recv() => match received {
+                Ok(frame) => frame,
+                // missed frames show up as a gap in `Simulation::frame`, their
+                // events in the `missed` of the next events message
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Ok("simulation stopped"),
+            },
+            event = events.recv() => match event {
+                Some(ClientEvent::Reply(reply)) => {
+                    write.send(Message::Text(reply.into())).await?;
+                    continue;
+                }
+                Some(ClientEvent::Rtt(rtt_ms)) => {
+                    if let Some(qos) = &mut qos {
+                        qos.record_rtt(rtt_ms);
+                    }
+                    continue;
+                }
+                // the reader only stops once the client has closed its side
+                None => return Ok("closed by client"),
+            },
+            _ = qos_interval.tick(), if qos.is_some() => {
+                let qos = qos.as_mut().unwrap();
+                if let Some(report) = qos.evaluate() {
+                    println!("{} now at QoS {:?}", peer, report.level);
+                    let report_json = serde_json::json!({ "qos": report }).to_string();
+                    write.send(Message::Text(report_json.into())).await?;
+                }
+                let sent_at = server_time_ms().to_le_bytes();
+                write.send(Message::Ping(sent_at.to_vec().into())).await?;
+                continue;
+            }
+            _ = shutdown.wait() => return say_goodbye(&mut write).await,
+        };
+        if history_until.is_some_and(|until| frame.snapshot.frame <= until) {
+            continue;
+        }
+        event_cursor.take_in(&frame.snapshot, &mut missed_events);
+        // batches skip no frames and ignore the other options. Frames lost to
+        // lagging end the batch early and the next one names them.
+        if batch_frames > 0 {
+            let snapshot = &frame.snapshot;
+            let lost_after = last_batched_frame.filter(|&last| snapshot.frame > last + 1);
+            if let Some(last) = lost_after {
+                if !batched.is_empty() {
+                    let batch_json = serialize_batch(&batched, batch_missed.take()).unwrap();
+                    write.send(Message::Text(batch_json.into())).await?;
+                    // their events go out after the next full batch
+                    for earlier in batched.drain(..) {
+                        skipped_events.extend(earlier.events.iter().cloned());
+                    }
+                }
+                batch_missed = Some([last + 1, snapshot.frame - 1]);
+            }
+            last_batched_frame = Some(snapshot.frame);
+            batched.push(snapshot.clone());
+            if batched.len() == batch_frames {
+                let batch_json = serialize_batch(&batched, batch_missed.take()).unwrap();
+                write.send(Message::Text(batch_json.into())).await?;
+                for earlier in &batched[..batched.len() - 1] {
+                    skipped_events.extend(earlier.events.iter().cloned());
+                }
+                send_events(&mut write, &frame, &mut skipped_events, &mut missed_events).await?;
+                batched.clear();
+            }
+            continue;
+        }
+        // a client that fell behind skips straight to the newest frame
+        loop {
+            match frames.try_recv() {
+                Ok(newer) => {
+                    skipped_events.extend(frame.snapshot.events.iter().cloned());
+                    event_cursor.take_in(&newer.snapshot, &mut missed_events);
+                    frame = newer;
+                }
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        let snapshot = frame.snapshot.clone();
+        let level = match &mut qos {
+            Some(qos) => {
+                qos.record_frame(snapshot.frame);
+                qos.level()
+            }
+            None => QosLevel::Full,
+        };
+        frames_seen += 1;
+        if !frames_seen.is_multiple_of(level.frame_stride()) {
+            skipped_events.extend(snapshot.events.iter().cloned());
+            continue;
+        }
+        let message = if format == FrameFormat::Packed {
+            Message::Binary(frame.packed.clone())
+        } else if format == FrameFormat::ColumnarPacked {
+            Message::Binary(frame.columnar_packed())
+        } else if format == FrameFormat::Columnar {
+            Message::Text(frame.columnar())
+        } else if level >= QosLevel::Quantized {
+            // a delta client has to start over from a full frame afterwards
+            last_sent = None;
+            let simulation_json = if level == QosLevel::Aggregated {
+                serialize_aggregated(&snapshot).unwrap()
+            } else {
+                serialize_quantized(&snapshot).unwrap()
+            };
+            Message::Text(simulation_json.into())
+        } else {
+            let simulation_json = match &last_sent {
+                Some(previous)
+                    if (keyframe_tick..keyframe_tick + DELTA_KEYFRAME_TICKS)
+                        .contains(&snapshot.tick) =>
+                {
+                    serialize_delta(previous, &snapshot, &field_mask)
+                        .unwrap()
+                        .into()
+                }
+                _ => {
+                    keyframe_tick = snapshot.tick;
+                    if field_mask.is_all() {
+                        frame.json.clone()
+                    } else {
+                        serialize_snapshot(&snapshot, &field_mask).unwrap().into()
+                    }
+                }
+            };
+            if delta {
+                last_sent = Some(snapshot);
+            }
+
+            // dbg!(&simulation_json);
+
+            Message::Text(simulation_json)
+        };
+
+        #[cfg(feature = "chaos")]
+        if let Some(delay) = slow_client_delay {
+            tokio::time::sleep(delay).await;
+        }
+        write.send(message).await?;
+        send_events(&mut write, &frame, &mut skipped_events, &mut missed_events).await?;
+    }
+}
@@ -1,9 +1,80 @@
 use geo::Point;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Clone, Copy)]
+use crate::Species;
+use crate::summary::PointSchema;
+
+/// Most energy a shark can hold, new sharks start half full
+pub const MAX_ENERGY: f64 = 1.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema)]
 pub struct Shark {
+    /// Unique for the whole run, kept from spawn to removal
+    pub id: u64,
+    #[serde(default)]
+    pub species: Species,
+    /// Longitude/latitude in degrees
+    #[schema(value_type = PointSchema)]
     pub position: Point<f64>,
     pub rotation_rad: f64,
+    /// Degrees per second, see `units::KmPerHour` for a physical reading
     pub speed: f64,
+    /// Heading change over the last step in rad/s
+    pub angular_velocity: f64,
+    /// Heading sent to clients, smoothed when `Simulation::heading_smoothing_secs` is set
+    pub reported_rotation_rad: f64,
+    /// Knows about the goals, only meaningful with leadership enabled
+    pub informed: bool,
+    /// Group this shark currently swims with, `None` when alone
+    pub school_id: Option<u64>,
+    /// Up to `MAX_ENERGY`, gained by eating prey, see `PreyField`
+    #[serde(default)]
+    pub energy: f64,
+    /// Metres below the surface, see `SpeciesParams::max_depth_m`
+    #[serde(default)]
+    pub depth: f64,
+    /// Raised by clients disturbing the water nearby and wearing off by itself,
+    /// see `SimulationConfig::flight_stress`
+    #[serde(default)]
+    pub stress: f64,
+    /// Where the last disturbance that reached the shark was, fled from while stressed
+    #[serde(skip)]
+    pub disturbed_from: Option<Point<f64>>,
+    /// How far through its dive cycle the shark is, 0 to 1
+    #[serde(skip)]
+    pub dive_phase: f64,
+    /// Offset of the wander target on its circle, relative to the heading
+    #[serde(skip)]
+    pub wander_angle: f64,
+}
+
+impl Shark {
+    /// A shark swimming straight at `rotation_rad`, not yet part of anything.
+    pub fn new(
+        id: u64,
+        species: Species,
+        position: Point<f64>,
+        rotation_rad: f64,
+        speed: f64,
+    ) -> Self {
+        Self {
+            id,
+            species,
+            position,
+            rotation_rad,
+            speed,
+            angular_velocity: 0.0,
+            reported_rotation_rad: rotation_rad,
+            informed: false,
+            school_id: None,
+            energy: MAX_ENERGY / 2.0,
+            depth: 0.0,
+            stress: 0.0,
+            disturbed_from: None,
+            // spread over the cycle so sharks don't all dive together
+            dive_phase: (id as f64 * 0.618_033_988_749_895).fract(),
+            wander_angle: 0.0,
+        }
+    }
 }
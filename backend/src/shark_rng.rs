@@ -0,0 +1,42 @@
+use rand::RngCore;
+
+/// Counter-based random stream for one shark in one tick. The stream is a pure
+/// function of (seed, shark, tick), so stochastic behaviour is reproducible no
+/// matter in which order or on which thread sharks are stepped.
+pub struct SharkRng {
+    state: u64,
+}
+
+impl SharkRng {
+    pub fn new(seed: u64, shark: u64, tick: u64) -> Self {
+        let mut state = splitmix64(seed);
+        state = splitmix64(state ^ shark);
+        state = splitmix64(state ^ tick);
+        Self { state }
+    }
+}
+
+impl RngCore for SharkRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        splitmix64(self.state)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// SplitMix64 finalizer, a cheap bijective 64-bit mix.
+fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+/// How long connections get to say goodbye before the process exits anyway
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Tells the listeners and connections that the server is going down. Cheap
+/// to clone, every task waits on its own copy.
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// Fires once the process gets ctrl-c.
+    pub fn on_ctrl_c() -> Self {
+        let (trigger, shutdown) = watch::channel(false);
+        tokio::spawn(async move {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                eprintln!(
+                    "can't listen for ctrl-c, shut down won't be graceful: {}",
+                    e
+                );
+                std::future::pending::<()>().await;
+            }
+            println!("shutting down");
+            let _ = trigger.send(true);
+        });
+        Self(shutdown)
+    }
+
+    /// Resolves once the shutdown has begun.
+    pub async fn wait(&mut self) {
+        let _ = self.0.wait_for(|stopping| *stopping).await;
+    }
+}
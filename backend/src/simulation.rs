@@ -1,99 +1,391 @@
-use crate::{Shark, random_point_in_water};
-use geo::algorithm::closest_point::ClosestPoint; // trait
+use crate::boundary::{BoundaryFlow, BoundaryStats, exit_edge, spawn_on_edge};
+use crate::land_field::{EXACT_BAND, LandField};
+use crate::land_tiers::{NEAR_COAST, closest_coast, coarse_land};
+use crate::shark::MAX_ENERGY;
+use crate::{
+    AttractionPoint, Boundary, Buoy, CategoryAffinity, ContactTracker, CurrentField, Environment,
+    Event, EventKind, EventLog, FrameStats, HotEvent, HotEvents, Km, KmPerHour, Leadership,
+    Playback, PreyField, RangeMode, Raster, SchoolStats, SchoolTracker, Shark, SharkRng, SimClock,
+    SimulationConfig, SimulationScratch, SimulationState, Species, SpeciesRange, StateHash,
+    SteeringScheme, UserGoals, WeightKernel, random_point_in_water,
+};
 use geo::algorithm::contains::Contains; // trait
 use geo::algorithm::euclidean_distance::EuclideanDistance; // trait
-use geo::{BoundingRect, Centroid, Closest, Intersects, Rect};
+use geo::{BoundingRect, Centroid, Distance, Euclidean, Intersects, Rect};
 use geo::{Point, Polygon};
 use rand::Rng;
 use serde::Serialize;
 use std::f64::EPSILON;
 use std::f64::consts::PI;
+use std::sync::Arc;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Simulation {
     pub sharks: Vec<Shark>,
+    /// Id the next shark to appear gets
+    #[serde(skip)]
+    next_shark_id: u64,
+    /// Number of steps taken so far
+    pub tick: u64,
+    /// When this frame was published, see `tick::server_time_ms`
+    pub server_time_ms: f64,
+    /// Frames published so far, unlike `tick` one per broadcast
+    #[serde(skip)]
+    pub frame: u64,
+    /// Seed of the per-shark random streams, see `SharkRng`
+    #[serde(skip)]
+    pub seed: u64,
+    #[serde(skip)]
+    land: Arc<Vec<Polygon<f64>>>,
+    /// `land` simplified, for avoidance away from the coast, see `closest_coast`
+    #[serde(skip)]
+    land_coarse: Arc<Vec<Polygon<f64>>>,
+    /// Distance to the coast on a grid, for avoidance out at sea, see `LandField`
+    #[serde(skip)]
+    land_field: Arc<LandField>,
     land_bounds: Vec<Rect<f64>>,
     // 1. ADDED: Vector of points the sharks are interested in
-    pub goals: Vec<Point<f64>>,
+    pub goals: Vec<AttractionPoint>,
+    /// How much each category of goal draws each species, see `AttractionPoint::pull`
+    #[serde(skip)]
+    pub category_affinity: Arc<CategoryAffinity>,
+    /// Most productive waters, sought like `goals` and replaced on every
+    /// refresh, see `ProductivityConfig`
+    pub hotspots: Vec<Point<f64>>,
+    /// Goals added by clients, sought just like `goals`
+    pub user_goals: UserGoals,
+    /// Time constant for smoothing the reported heading, `None` reports the raw heading
+    #[serde(skip)]
+    pub heading_smoothing_secs: Option<f64>,
+    /// Set by the `pause` admin command, frames keep going out but nothing moves
+    pub paused: bool,
+    /// Mirrors a primary process instead of stepping, see `standby`
+    #[serde(skip)]
+    pub replica: bool,
+    /// What happens to sharks reaching the map edges
+    #[serde(skip)]
+    pub boundary: Boundary,
+    /// How the flocking behaviours add up to one force
+    #[serde(skip)]
+    pub steering: SteeringScheme,
+    /// Informed-leader dynamics, `None` lets every shark seek goals
+    #[serde(skip)]
+    pub leadership: Option<Leadership>,
+    #[serde(skip)]
+    boundary_flow: BoundaryFlow,
+    /// Entries and exits through the edges, only with an open boundary
+    pub boundary_stats: Option<BoundaryStats>,
+    /// Injected faults, see `Chaos`
+    #[cfg(feature = "chaos")]
+    #[serde(skip)]
+    pub chaos: crate::Chaos,
+    /// Most recent periodic state hash, for spotting behaviour changes between runs
+    pub state_hash: Option<StateHash>,
+    pub school_stats: SchoolStats,
+    /// Tick timing and what the physics loop did about overruns
+    pub frame_stats: FrameStats,
+    #[serde(skip)]
+    school_tracker: SchoolTracker,
+    /// Fixed sensors, read out through the `/buoys` feed
+    #[serde(skip)]
+    pub buoys: Vec<Buoy>,
+    /// Ocean conditions, shared since they don't change between frames
+    #[serde(skip)]
+    pub environment: Arc<Environment>,
+    /// Pairwise contact durations, `None` when not tracked. Snapshots get
+    /// an empty copy, see `ContactTracker`.
+    #[serde(skip)]
+    pub contacts: Option<ContactTracker>,
+    /// Fish schools hunted in place of the goals, `None` without prey
+    pub prey: Option<PreyField>,
+    /// Random spots drawing sharks for a while, `None` when off
+    pub hot_events: Option<HotEvents>,
+    /// Sharks that ran out of energy since the start, see `SimulationConfig::mortality`
+    pub starved: u64,
+    /// Simulated time of day, see `SimulationConfig::clock_speed`
+    pub clock: SimClock,
+    /// Position and speed of a replay, `None` in a live run, see `spawn_replay_thread`
+    pub playback: Option<Playback>,
+    /// Events since the last broadcast
+    #[serde(skip)]
+    pub event_log: EventLog,
+    /// Events that go out with this frame, see `serialize_events`
+    #[serde(skip)]
+    pub events: Arc<Vec<Event>>,
+    /// Where each species may go, at most one per species, see `SpeciesRange`
+    #[serde(skip)]
+    pub ranges: Arc<Vec<SpeciesRange>>,
+    #[serde(skip)]
+    scratch: SimulationScratch,
 }
 
 impl Simulation {
+    /// Scatters `amount_of_sharks` sharks over the water around
+    /// `land_shape_file`. Fails when there's no water to be found.
     pub fn new<R: Rng>(
         amount_of_sharks: usize,
         rng: &mut R,
-        land_shape_file: &[Polygon<f64>],
+        land_shape_file: Arc<Vec<Polygon<f64>>>,
         // 2. ADDED: Goals parameter
-        goals: Vec<Point<f64>>,
-    ) -> Self {
+        goals: Vec<AttractionPoint>,
+    ) -> Result<Self, String> {
         let mut sharks = Vec::<Shark>::with_capacity(amount_of_sharks);
         for _ in 0..amount_of_sharks {
-            let rand_point = random_point_in_water(rng, land_shape_file);
+            let rand_point = random_point_in_water(rng, &land_shape_file)?;
             let random_orientation: f64 = rng.random_range(0.0..(2.0 * PI));
             let random_speed: f64 = rng.random_range(0.5..1.5);
-            let shark = Shark {
-                position: rand_point,
-                rotation_rad: random_orientation,
-                speed: random_speed,
-            };
-            sharks.push(shark);
+            sharks.push(Shark::new(
+                sharks.len() as u64,
+                Species::random(rng),
+                rand_point,
+                random_orientation,
+                random_speed,
+            ));
         }
 
         let land_bounds = land_shape_file
             .iter()
             .filter_map(|poly| poly.bounding_rect())
             .collect();
+        let land_coarse = Arc::new(coarse_land(&land_shape_file));
+        let land_field = Arc::new(LandField::new(&land_shape_file));
 
-        Self {
+        Ok(Self {
             sharks,
+            next_shark_id: amount_of_sharks as u64,
+            tick: 0,
+            server_time_ms: 0.0,
+            frame: 0,
+            seed: 0,
+            land: land_shape_file,
+            land_coarse,
+            land_field,
             land_bounds,
             // 3. Initialized the new field
             goals,
-        }
+            category_affinity: Arc::default(),
+            hotspots: Vec::new(),
+            user_goals: UserGoals::default(),
+            heading_smoothing_secs: None,
+            paused: false,
+            replica: false,
+            boundary: Boundary::default(),
+            steering: SteeringScheme::default(),
+            leadership: None,
+            boundary_flow: BoundaryFlow::new(amount_of_sharks),
+            boundary_stats: None,
+            #[cfg(feature = "chaos")]
+            chaos: crate::Chaos::default(),
+            state_hash: None,
+            school_stats: SchoolStats::default(),
+            frame_stats: FrameStats::default(),
+            school_tracker: SchoolTracker::default(),
+            buoys: Vec::new(),
+            environment: Arc::default(),
+            contacts: None,
+            prey: None,
+            hot_events: None,
+            starved: 0,
+            clock: SimClock::default(),
+            playback: None,
+            event_log: EventLog::default(),
+            events: Arc::default(),
+            ranges: Arc::default(),
+            scratch: SimulationScratch::default(),
+        })
     }
 }
 
 impl Simulation {
-    #[allow(clippy::too_many_arguments)] // Allowing many arguments for the simulation parameters
-    pub fn step(
-        &mut self,
-        dt: f64,
-        perception_radius: f64,
-        separation_distance: f64,
-        cohesion_strength: f64,
-        separation_strength: f64,
-        alignment_strength: f64,
-        land_shape_file: &[Polygon<f64>],
-        map_bounds: (f64, f64, f64, f64),
-        land_avoid_radius: f64,
-        land_avoid_strength: f64,
-        border_margin: f64,
-        border_strength: f64,
-        // 4. ADDED: Goal-seeking parameters
-        goal_seeking_radius: f64,
-        goal_seeking_strength: f64,
-    ) {
+    /// One tick of `dt` seconds: the sharks move in `substeps` equal steps,
+    /// then schools, contacts, buoys, prey and hot events catch up. The
+    /// physics loop and the self-test both go through here.
+    pub fn advance(&mut self, dt: f64, substeps: u32, config: &SimulationConfig) {
+        for _ in 0..substeps {
+            self.step(dt / substeps as f64, config);
+        }
+        self.update_schools(config.school_join_radius, config.school_leave_radius);
+        self.update_contacts(dt);
+        self.update_buoys();
+        self.update_prey(dt);
+        self.update_hot_events(dt);
+    }
+
+    pub fn step(&mut self, dt: f64, config: &SimulationConfig) {
+        let SimulationConfig {
+            cohesion_strength,
+            separation_distance,
+            separation_strength,
+            alignment_strength,
+            land_avoid_radius,
+            land_avoid_strength,
+            map_bounds,
+            border_margin,
+            border_strength,
+            goal_seeking_radius,
+            goal_seeking_strength,
+            temperature_strength,
+            polar_latitude,
+            polar_strength,
+            range_strength,
+            thermocline_depth_m,
+            thermocline_avoidance,
+            current_drift_scale,
+            clock_speed,
+            twilight_hours,
+            twilight_perception_factor,
+            twilight_hunting_factor,
+            energy_use_per_km,
+            goal_feeding_radius,
+            goal_energy_per_sec,
+            low_energy,
+            mortality,
+            max_neighbors,
+            field_of_view_rad,
+            flocking_kernel,
+            wander_strength,
+            wander_jitter,
+            max_turn_rate,
+            stress_decay_secs,
+            flight_stress,
+            flight_strength,
+            parallel_from_sharks,
+            ..
+        } = *config;
+        let land_shape_file = self.land.clone();
+
+        // the simulation itself works in degrees, see `units`
+        let separation_distance = separation_distance.to_degrees();
+        let land_avoid_radius = land_avoid_radius.to_degrees();
+        let border_margin = border_margin.to_degrees();
+        let goal_seeking_radius = goal_seeking_radius.to_degrees();
+        let goal_feeding_radius = goal_feeding_radius.to_degrees();
+
         let (min_x, min_y, max_x, max_y) = map_bounds;
-        let max_turn_rate = PI * dt;
+        let max_turn = max_turn_rate.0 * dt;
+        let cos_half_fov = (field_of_view_rad / 2.0).min(PI).cos();
+        let heading_alpha = match self.heading_smoothing_secs {
+            Some(secs) if secs > 0.0 => 1.0 - (-dt / secs).exp(),
+            _ => 1.0,
+        };
+        let stress_decay = if stress_decay_secs > 0.0 {
+            (-dt / stress_decay_secs).exp()
+        } else {
+            0.0
+        };
 
-        let old_sharks: Vec<Shark> = self.sharks.clone();
-        let mut new_sharks = Vec::with_capacity(self.sharks.len());
+        self.tick += 1;
+        self.clock.advance(dt, clock_speed);
+        // hot events are goals with prey as well
+        let user_goals = self.user_goals.positions().map(AttractionPoint::at).chain(
+            self.hot_events
+                .iter()
+                .flat_map(|hot| &hot.active)
+                .map(HotEvent::attraction_point),
+        );
+        let goals: Vec<AttractionPoint> = match &self.prey {
+            // the goals and hotspots only decide where prey appears then
+            Some(_) => user_goals.collect(),
+            None => self
+                .goals
+                .iter()
+                .cloned()
+                .chain(self.hotspots.iter().copied().map(AttractionPoint::at))
+                .chain(user_goals)
+                .collect(),
+        };
+        let month = self.clock.month();
+        let category_affinity = self.category_affinity.clone();
+        let prey: Vec<Point<f64>> = self.prey.iter().flat_map(PreyField::positions).collect();
+        let hunt_radius = self.prey.as_ref().map_or(0.0, PreyField::hunt_radius);
 
-        for i in 0..old_sharks.len() {
-            let shark = &old_sharks[i];
+        // each shark only reads the old state and has its own random stream,
+        // so they can be stepped in any order and on any thread alike
+        let step_shark = |state: &SimulationState, i: usize| {
+            let shark = &state.sharks[i];
+            let mut rng = SharkRng::new(self.seed, shark.id, self.tick);
+            let heading = (shark.rotation_rad.cos(), shark.rotation_rad.sin());
+            let species = shark.species.params();
+            // crepuscular: sharks are at their sharpest around dawn and dusk
+            let twilight = self.clock.twilight(shark.position.x(), twilight_hours);
+            let perception_radius = species.perception_radius.to_degrees()
+                * (1.0 + (twilight_perception_factor - 1.0) * twilight);
+            let (min_speed, max_speed) = (
+                species.speed_limits.0.to_degrees_per_sec(),
+                species.speed_limits.1.to_degrees_per_sec(),
+            );
+            // 0 when fed, 1 with no energy left
+            let hunger = if shark.energy < low_energy {
+                (1.0 - shark.energy / low_energy).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            // a starving shark saves its strength, down to its slowest
+            let max_speed = max_speed - (max_speed - min_speed) * hunger;
 
-            let mut nearby_sharks = Vec::new();
-            for (j, other) in old_sharks.iter().enumerate() {
-                if i != j && shark.position.euclidean_distance(&other.position) < perception_radius
-                {
-                    nearby_sharks.push(other);
+            let mut nearby = Vec::new();
+            for (j, dist) in state.grid.within(shark.position, perception_radius) {
+                let other = &state.sharks[j];
+                if i == j {
+                    continue;
+                }
+                if dist > 0.0 {
+                    // cosine of the angle between the heading and the direction to the neighbor
+                    let cos_angle = ((other.position.x() - shark.position.x()) * heading.0
+                        + (other.position.y() - shark.position.y()) * heading.1)
+                        / dist;
+                    if cos_angle < cos_half_fov {
+                        continue;
+                    }
                 }
+                nearby.push((dist, other));
+            }
+            if let Some(k) = max_neighbors
+                && nearby.len() > k
+            {
+                nearby.select_nth_unstable_by(k, |a, b| a.0.total_cmp(&b.0));
+                nearby.truncate(k);
             }
 
-            let cohesion = calculate_cohesion(shark, &nearby_sharks);
-            let separation = calculate_separation(shark, &nearby_sharks, separation_distance);
-            let alignment = calculate_alignment(shark, &nearby_sharks);
+            let cohesion = calculate_cohesion(shark, &nearby, flocking_kernel, perception_radius);
+            let separation =
+                calculate_separation(shark, &nearby, flocking_kernel, separation_distance);
+            let alignment = calculate_alignment(shark, &nearby, flocking_kernel, perception_radius);
             // 5. ADDED: Goal-seeking force calculation
-            let goal_seeking = calculate_goal_seeking(shark, &self.goals, goal_seeking_radius);
+            // a school of fish in reach beats any goal
+            let hunting =
+                calculate_goal_seeking(shark, prey.iter().map(|p| (*p, 1.0)), hunt_radius);
+            let goal_seeking = if hunting != Point::new(0.0, 0.0) {
+                hunting
+            } else {
+                let pulls = goals.iter().map(|goal| {
+                    (
+                        goal.position,
+                        goal.pull(shark.species, month, &category_affinity),
+                    )
+                });
+                calculate_goal_seeking(shark, pulls, goal_seeking_radius)
+            };
+            let temperature = match &self.environment.sst {
+                Some(sst) => {
+                    calculate_temperature_seeking(shark, sst, species.preferred_temperature)
+                }
+                None => Point::new(0.0, 0.0),
+            };
+            let sst_known = self
+                .environment
+                .sst
+                .as_ref()
+                .is_some_and(|sst| sst.sample(shark.position).is_some());
+            let polar_avoidance = if sst_known {
+                Point::new(0.0, 0.0)
+            } else {
+                calculate_polar_avoidance(shark, polar_latitude, (min_y, max_y))
+            };
+            let wander_angle = wrap_angle(
+                shark.wander_angle + rng.random_range(-1.0..=1.0) * wander_jitter.0 * dt,
+            );
+            let wander = calculate_wander(shark, wander_angle);
 
             let look_ahead_dist = shark.speed * 20.0 * dt; // Look ahead based on speed
             let future_pos = Point::new(
@@ -104,16 +396,79 @@ impl Simulation {
             let land_avoidance = calculate_land_avoidance(
                 shark,
                 &future_pos,
-                land_shape_file,
+                &land_shape_file,
+                &self.land_coarse,
+                &self.land_field,
                 &self.land_bounds,
                 land_avoid_radius,
             );
-            let border_avoidance =
-                calculate_border_avoidance(shark, &future_pos, map_bounds, border_margin);
+            // a coast on the equator side would otherwise pin sharks against
+            // the edge of the map, which the polar force is too weak to undo
+            let land_avoidance = if shark.position.y().abs() > polar_latitude
+                && land_avoidance.y() * shark.position.y() > 0.0
+            {
+                Point::new(land_avoidance.x(), 0.0)
+            } else {
+                land_avoidance
+            };
+            // a hard range edge is one more coast, a soft one a pull back
+            let range = self
+                .ranges
+                .iter()
+                .find(|range| range.species == shark.species);
+            let mut range_steering = Point::new(0.0, 0.0);
+            let land_avoidance = match range {
+                Some(range) if range.mode == RangeMode::Hard => {
+                    let back = range.steer_inside(future_pos, land_avoid_radius);
+                    Point::new(land_avoidance.x() + back.x(), land_avoidance.y() + back.y())
+                }
+                Some(range) => {
+                    range_steering = range.steer_inside(future_pos, 0.0);
+                    land_avoidance
+                }
+                None => land_avoidance,
+            };
+            let border_avoidance = match self.boundary {
+                Boundary::Walls => {
+                    calculate_border_avoidance(shark, &future_pos, map_bounds, border_margin)
+                }
+                // nothing to steer away from, sharks just leave
+                Boundary::Open { .. } => Point::new(0.0, 0.0),
+            };
 
             let mut total_force = Point::new(0.0, 0.0);
+            let (alignment_factor, goal_factor) = match &self.leadership {
+                Some(leadership) => leadership.factors(shark),
+                None => (1.0, 1.0),
+            };
+            let goal_factor = goal_factor
+                * species.goal_affinity
+                * (1.0 + HUNGRY_GOAL_BOOST * hunger)
+                * (1.0 + (twilight_hunting_factor - 1.0) * twilight);
+            // a stressed shark forgets about food and gets away from the disturbance
+            let fleeing_from = shark
+                .disturbed_from
+                .filter(|_| shark.stress >= flight_stress);
+            let (goal_seeking, goal_strength) = match fleeing_from {
+                Some(from) => (calculate_flight(shark, from), flight_strength),
+                None => (goal_seeking, goal_seeking_strength * goal_factor),
+            };
 
-            if land_avoidance.x().powi(2) + land_avoidance.y().powi(2) > EPSILON
+            if let SteeringScheme::Priority { .. } = self.steering {
+                // highest priority first
+                total_force = self.steering.combine(&[
+                    (land_avoidance, land_avoid_strength),
+                    (border_avoidance, border_strength),
+                    (polar_avoidance, polar_strength),
+                    (range_steering, range_strength),
+                    (temperature, temperature_strength),
+                    (goal_seeking, goal_strength),
+                    (separation, separation_strength),
+                    (alignment, alignment_strength * alignment_factor),
+                    (cohesion, cohesion_strength),
+                    (wander, wander_strength),
+                ]);
+            } else if land_avoidance.x().powi(2) + land_avoidance.y().powi(2) > EPSILON
                 || border_avoidance.x().powi(2) + border_avoidance.y().powi(2) > EPSILON
             {
                 total_force = Point::new(
@@ -124,25 +479,23 @@ impl Simulation {
                     total_force.x() + border_avoidance.x() * border_strength,
                     total_force.y() + border_avoidance.y() * border_strength,
                 );
-            } else {
-                // Flocking forces
-                total_force = Point::new(
-                    total_force.x() + cohesion.x() * cohesion_strength,
-                    total_force.y() + cohesion.y() * cohesion_strength,
-                );
                 total_force = Point::new(
-                    total_force.x() + separation.x() * separation_strength,
-                    total_force.y() + separation.y() * separation_strength,
-                );
-                total_force = Point::new(
-                    total_force.x() + alignment.x() * alignment_strength,
-                    total_force.y() + alignment.y() * alignment_strength,
-                );
-                // 6. ADDED: Goal-seeking force integration
-                total_force = Point::new(
-                    total_force.x() + goal_seeking.x() * goal_seeking_strength,
-                    total_force.y() + goal_seeking.y() * goal_seeking_strength,
+                    total_force.x() + polar_avoidance.x() * polar_strength,
+                    total_force.y() + polar_avoidance.y() * polar_strength,
                 );
+            } else {
+                // Flocking forces
+                total_force = self.steering.combine(&[
+                    (cohesion, cohesion_strength),
+                    (separation, separation_strength),
+                    (alignment, alignment_strength * alignment_factor),
+                    // 6. ADDED: Goal-seeking force integration
+                    (goal_seeking, goal_strength),
+                    (temperature, temperature_strength),
+                    (polar_avoidance, polar_strength),
+                    (range_steering, range_strength),
+                    (wander, wander_strength),
+                ]);
             }
 
             let mut velocity = Point::new(
@@ -156,7 +509,7 @@ impl Simulation {
             );
 
             let new_speed = (velocity.x().powi(2) + velocity.y().powi(2)).sqrt();
-            let new_speed_clamped = new_speed.min(2.0).max(0.5); // Your speed limits
+            let new_speed_clamped = new_speed.min(max_speed).max(min_speed);
 
             if new_speed > EPSILON {
                 velocity = Point::new(
@@ -166,63 +519,479 @@ impl Simulation {
             }
 
             let desired_angle = velocity.y().atan2(velocity.x());
-            let mut angle_diff = desired_angle - shark.rotation_rad;
-
-            while angle_diff <= -PI {
-                angle_diff += 2.0 * PI;
-            }
-            while angle_diff > PI {
-                angle_diff -= 2.0 * PI;
-            }
+            let angle_diff = wrap_angle(desired_angle - shark.rotation_rad);
 
-            let turn = angle_diff.max(-max_turn_rate).min(max_turn_rate);
+            let turn = angle_diff.max(-max_turn).min(max_turn);
             let new_angle = shark.rotation_rad + turn;
 
+            let reported_diff = wrap_angle(new_angle - shark.reported_rotation_rad);
+            let reported_angle = shark.reported_rotation_rad + reported_diff * heading_alpha;
+
+            // the current carries the shark on top of its own swimming
+            let drift = match &self.environment.currents {
+                Some(currents) => current_drift(currents, shark.position, current_drift_scale),
+                None => Point::new(0.0, 0.0),
+            };
             let mut new_position = Point::new(
-                shark.position.x() + velocity.x() * dt,
-                shark.position.y() + velocity.y() * dt,
+                shark.position.x() + (velocity.x() + drift.x()) * dt,
+                shark.position.y() + (velocity.y() + drift.y()) * dt,
             );
 
             new_position = Point::new(
                 new_position.x().max(min_x + EPSILON).min(max_x - EPSILON),
                 new_position.y().max(min_y + EPSILON).min(max_y - EPSILON),
             );
+            // once inside a hard range a shark stays, its avoidance turns it around
+            if let Some(range) = range
+                && range.mode == RangeMode::Hard
+                && range.contains(shark.position)
+                && !range.contains(new_position)
+            {
+                new_position = shark.position;
+            }
+
+            // nothing to eat out of season, for the wrong species or on the run
+            let feeding = fleeing_from.is_none()
+                && goals.iter().any(|goal| {
+                    goal.pull(shark.species, month, &category_affinity) > 0.0
+                        && Euclidean.distance(shark.position, goal.position) < goal_feeding_radius
+                });
+            let mut energy =
+                shark.energy - energy_use_per_km * Km::from_degrees(new_speed_clamped * dt).0;
+            if feeding {
+                energy += goal_energy_per_sec * dt;
+            }
+
+            // depth plays no part in steering, the sharks still move on the map
+            let dive_phase = (shark.dive_phase + dt / species.dive_period_secs).fract();
+            let depth = dive_depth(
+                shark.depth,
+                dive_phase,
+                species.max_depth_m,
+                (thermocline_depth_m, thermocline_avoidance),
+                dt / species.dive_period_secs,
+            );
 
-            new_sharks.push(Shark {
+            Shark {
                 position: new_position,
+                depth,
+                dive_phase,
+                energy: energy.clamp(0.0, MAX_ENERGY),
+                stress: shark.stress * stress_decay,
                 rotation_rad: new_angle,
                 speed: new_speed_clamped,
+                angular_velocity: turn / dt,
+                reported_rotation_rad: reported_angle,
+                wander_angle,
+                ..*shark
+            }
+        };
+        let parallel = self.sharks.len() >= parallel_from_sharks;
+        self.scratch.step_sharks(
+            &mut self.sharks,
+            Species::max_perception_radius().to_degrees(),
+            parallel,
+            step_shark,
+        );
+        if mortality && energy_use_per_km > 0.0 {
+            let (tick, event_log) = (self.tick, &mut self.event_log);
+            let before = self.sharks.len();
+            self.sharks.retain(|shark| {
+                let alive = shark.energy > 0.0;
+                if !alive {
+                    event_log.emit(tick, EventKind::Starved { shark_id: shark.id });
+                }
+                alive
+            });
+            self.starved += (before - self.sharks.len()) as u64;
+        }
+        if let Boundary::Open { inflow_per_sec } = self.boundary {
+            self.apply_open_boundary(inflow_per_sec, dt, map_bounds, &land_shape_file);
+        }
+    }
+
+    /// Removes sharks that reached an edge and lets new ones in, see `Boundary::Open`.
+    fn apply_open_boundary(
+        &mut self,
+        inflow_per_sec: f64,
+        dt: f64,
+        map_bounds: (f64, f64, f64, f64),
+        land_shape_file: &[Polygon<f64>],
+    ) {
+        let stats = self
+            .boundary_stats
+            .get_or_insert_with(BoundaryStats::default);
+        let (tick, event_log) = (self.tick, &mut self.event_log);
+        let before = self.sharks.len();
+        self.sharks
+            .retain(|shark| match exit_edge(shark.position, map_bounds) {
+                Some(edge) => {
+                    stats.exits.add(edge);
+                    let shark_id = shark.id;
+                    event_log.emit(tick, EventKind::LeftMap { shark_id, edge });
+                    false
+                }
+                None => true,
             });
+        let exited = before - self.sharks.len();
+
+        let inflow = self
+            .boundary_flow
+            .take_inflow(inflow_per_sec, dt, self.sharks.len());
+        // a stream apart from the per-shark ones
+        let mut rng = SharkRng::new(self.seed, u64::MAX, self.tick);
+        let mut entered = 0;
+        for _ in 0..inflow {
+            let Some((edge, position, heading)) =
+                spawn_on_edge(&mut rng, map_bounds, land_shape_file)
+            else {
+                break;
+            };
+            let speed = rng.random_range(0.5..1.5);
+            let species = Species::random(&mut rng);
+            self.sharks.push(Shark::new(
+                self.next_shark_id,
+                species,
+                position,
+                heading,
+                speed,
+            ));
+            let shark_id = self.next_shark_id;
+            event_log.emit(tick, EventKind::Arrived { shark_id, edge });
+            self.next_shark_id += 1;
+            stats.entries.add(edge);
+            entered += 1;
+        }
+        stats.record_tick(entered, exited, dt);
+    }
+}
+
+impl Simulation {
+    fn take_shark_id(&mut self) -> u64 {
+        let id = self.next_shark_id;
+        self.next_shark_id += 1;
+        id
+    }
+
+    /// Takes over sharks from elsewhere, e.g. a primary being mirrored, with
+    /// their ids. New sharks are numbered after the highest of them.
+    pub fn adopt_sharks(&mut self, sharks: Vec<Shark>) {
+        let after_highest = sharks.iter().map(|shark| shark.id + 1).max().unwrap_or(0);
+        self.next_shark_id = self.next_shark_id.max(after_highest);
+        self.sharks = sharks;
+    }
+
+    /// Startles the sharks within `disturbance_radius` of `position`, e.g.
+    /// where a client just added a goal, see `SimulationConfig::flight_stress`.
+    pub fn disturb(&mut self, position: Point<f64>, config: &SimulationConfig) {
+        let radius = config.disturbance_radius.to_degrees();
+        for shark in &mut self.sharks {
+            if Euclidean.distance(shark.position, position) < radius {
+                shark.stress += config.disturbance_stress;
+                shark.disturbed_from = Some(position);
+            }
+        }
+    }
+
+    /// The land the sharks steer around, as loaded and repaired.
+    pub fn land(&self) -> &[Polygon<f64>] {
+        &self.land
+    }
+
+    /// `land` simplified to within `land_tiers::COARSE_TOLERANCE`, plenty for drawing it.
+    pub fn coarse_land(&self) -> &[Polygon<f64>] {
+        &self.land_coarse
+    }
+
+    pub fn in_water(&self, point: Point<f64>) -> bool {
+        !self.land.iter().any(|poly| poly.contains(&point))
+    }
+
+    /// Adds `count` sharks at `position`, heading off in random directions.
+    pub fn spawn_sharks<R: Rng>(&mut self, position: Point<f64>, count: usize, rng: &mut R) {
+        for _ in 0..count {
+            let orientation = rng.random_range(0.0..(2.0 * PI));
+            let speed = rng.random_range(0.5..1.5);
+            let id = self.take_shark_id();
+            self.sharks.push(Shark::new(
+                id,
+                Species::random(rng),
+                position,
+                orientation,
+                speed,
+            ));
         }
+    }
 
-        self.sharks = new_sharks;
+    /// Moves the simulation onto the land and sharks of `fresh`, keeping the
+    /// goals and settings. `fresh` comes from `Simulation::new`, which is slow
+    /// enough to build away from the physics thread. The tick keeps counting.
+    pub fn replace_world<R: Rng>(&mut self, fresh: Simulation, rng: &mut R) {
+        let amount_of_sharks = fresh.sharks.len();
+        self.sharks = fresh.sharks;
+        for shark in &mut self.sharks {
+            shark.id += self.next_shark_id;
+        }
+        self.next_shark_id += amount_of_sharks as u64;
+        self.land = fresh.land;
+        self.land_coarse = fresh.land_coarse;
+        self.land_field = fresh.land_field;
+        self.land_bounds = fresh.land_bounds;
+        self.boundary_flow = fresh.boundary_flow;
+        self.school_tracker = fresh.school_tracker;
+        if let Some(leadership) = &self.leadership {
+            leadership.assign_informed(rng, &mut self.sharks);
+        }
+        if let Some(contacts) = &mut self.contacts {
+            contacts.clear();
+        }
+    }
+
+    /// Recomputes which sharks school together, see `SchoolTracker::update`.
+    pub fn update_schools(&mut self, join_radius: Km, leave_radius: Km) {
+        self.school_stats = self.school_tracker.update(
+            &mut self.sharks,
+            join_radius.to_degrees(),
+            leave_radius.to_degrees(),
+        );
+    }
+
+    pub fn update_buoys(&mut self) {
+        for buoy in &mut self.buoys {
+            for shark_id in buoy.update(&self.sharks, &self.environment) {
+                let buoy = buoy.name.clone();
+                self.event_log
+                    .emit(self.tick, EventKind::PassBy { buoy, shark_id });
+            }
+        }
+    }
+
+    /// Moves the fish schools and lets the sharks feed, see `PreyField`.
+    pub fn update_prey(&mut self, dt: f64) {
+        let Some(prey) = &mut self.prey else {
+            return;
+        };
+        let sites: Vec<Point<f64>> = self
+            .goals
+            .iter()
+            .map(|goal| goal.position)
+            .chain(self.hotspots.iter().copied())
+            .collect();
+        // a stream apart from the per-shark ones and the boundary's
+        let mut rng = SharkRng::new(self.seed, u64::MAX - 1, self.tick);
+        let in_water = |point: Point<f64>| {
+            !self
+                .land
+                .iter()
+                .zip(&self.land_bounds)
+                .any(|(poly, bounds)| bounds.contains(&point) && poly.contains(&point))
+        };
+        prey.update(&mut self.sharks, &sites, in_water, dt, &mut rng);
+    }
+
+    /// Ends and starts hot events, see `HotEvents`.
+    pub fn update_hot_events(&mut self, dt: f64) {
+        let Some(hot_events) = &mut self.hot_events else {
+            return;
+        };
+        let sites: Vec<Point<f64>> = self
+            .goals
+            .iter()
+            .map(|goal| goal.position)
+            .chain(self.hotspots.iter().copied())
+            .collect();
+        // a stream apart from the per-shark ones, the boundary's and the prey's
+        let mut rng = SharkRng::new(self.seed, u64::MAX - 2, self.tick);
+        let in_water = |point: Point<f64>| {
+            !self
+                .land
+                .iter()
+                .zip(&self.land_bounds)
+                .any(|(poly, bounds)| bounds.contains(&point) && poly.contains(&point))
+        };
+        hot_events.update(
+            &sites,
+            in_water,
+            dt,
+            self.tick,
+            &mut self.event_log,
+            &mut rng,
+        );
+    }
+
+    /// Accumulates contact time between nearby sharks, see `ContactTracker`.
+    pub fn update_contacts(&mut self, dt: f64) {
+        if let Some(contacts) = &mut self.contacts {
+            contacts.update(&self.sharks, dt);
+        }
+    }
+}
+
+/// Wraps an angle difference into (-PI, PI].
+pub fn wrap_angle(mut angle: f64) -> f64 {
+    while angle <= -PI {
+        angle += 2.0 * PI;
+    }
+    while angle > PI {
+        angle -= 2.0 * PI;
+    }
+    angle
+}
+
+/// Drift velocity in degrees per second from the current at `position`.
+fn current_drift(currents: &CurrentField, position: Point<f64>, scale: f64) -> Point<f64> {
+    let Some((u, v)) = currents.sample(position) else {
+        return Point::new(0.0, 0.0);
+    };
+    // m/s to km/h
+    let to_degrees_per_sec = |mps: f64| KmPerHour(mps * 3.6 * scale).to_degrees_per_sec();
+    Point::new(to_degrees_per_sec(u), to_degrees_per_sec(v))
+}
+
+/// Depth after one step of a dive cycle: down towards `max_depth` and back up
+/// once per cycle, following `phase`. The part of a dive below the
+/// thermocline is shortened by `avoidance`. Moves at most the depth range
+/// times `cycle_fraction` times `DIVE_SPEED_FACTOR`.
+fn dive_depth(
+    depth: f64,
+    phase: f64,
+    max_depth: f64,
+    (thermocline, avoidance): (f64, f64),
+    cycle_fraction: f64,
+) -> f64 {
+    let mut target = max_depth * 0.5 * (1.0 - (2.0 * PI * phase).cos());
+    if target > thermocline {
+        target = thermocline + (target - thermocline) * (1.0 - avoidance.clamp(0.0, 1.0));
+    }
+    let max_change = max_depth * cycle_fraction * DIVE_SPEED_FACTOR;
+    (depth + (target - depth).clamp(-max_change, max_change)).max(0.0)
+}
+
+/// Vertical speed limit in depth ranges per cycle. The oscillation needs a
+/// bit over 3 at its steepest, so sharks keep up without jumping to depth.
+const DIVE_SPEED_FACTOR: f64 = 4.0;
+
+/// How much harder a shark with no energy left seeks goals and prey than a fed one
+const HUNGRY_GOAL_BOOST: f64 = 2.0;
+
+/// °C outside the preferred band at which the temperature force reaches full strength
+const TEMPERATURE_RAMP: f64 = 2.0;
+
+/// Steering force up the temperature gradient for a shark in water colder than
+/// its preferred band, down it in warmer water. Grows with how far outside the
+/// band the shark is, up to unit length.
+fn calculate_temperature_seeking(
+    shark: &Shark,
+    sst: &Raster,
+    (coldest, warmest): (f64, f64),
+) -> Point<f64> {
+    let (Some(temperature), Some((dx, dy))) =
+        (sst.sample(shark.position), sst.gradient(shark.position))
+    else {
+        return Point::new(0.0, 0.0);
+    };
+    let outside = if temperature < coldest {
+        coldest - temperature
+    } else if temperature > warmest {
+        warmest - temperature
+    } else {
+        return Point::new(0.0, 0.0);
+    };
+    let norm = (dx * dx + dy * dy).sqrt();
+    if norm < f64::EPSILON {
+        return Point::new(0.0, 0.0);
+    }
+    let urgency = (outside / TEMPERATURE_RAMP).clamp(-1.0, 1.0);
+    Point::new(dx / norm * urgency, dy / norm * urgency)
+}
+
+/// Turns a shark beyond `polar_latitude` that still swims poleward back
+/// towards the equator, harder the closer it is to the edge of the map, up to
+/// unit length. The force is sideways to the heading: one straight against it
+/// only slows a shark down to its minimum speed and leaves it sliding along
+/// the edge.
+fn calculate_polar_avoidance(
+    shark: &Shark,
+    polar_latitude: f64,
+    (min_lat, max_lat): (f64, f64),
+) -> Point<f64> {
+    let lat = shark.position.y();
+    let (urgency, toward_equator) = if lat > polar_latitude {
+        ((lat - polar_latitude) / (max_lat - polar_latitude), -1.0)
+    } else if lat < -polar_latitude {
+        ((-polar_latitude - lat) / (-polar_latitude - min_lat), 1.0)
+    } else {
+        return Point::new(0.0, 0.0);
+    };
+    let (cos, sin) = (shark.rotation_rad.cos(), shark.rotation_rad.sin());
+    if sin * toward_equator > 0.0 {
+        return Point::new(0.0, 0.0);
+    }
+    // whichever turn gets there sooner, always the same one when heading
+    // straight at the pole
+    let side = if cos * toward_equator > 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    let urgency = urgency.clamp(0.0, 1.0) * side;
+    Point::new(sin * urgency, -cos * urgency)
+}
+
+/// Distance of the wander circle ahead of the shark and its radius. Only their
+/// ratio matters since the force is normalized; a larger circle wanders harder.
+const WANDER_DISTANCE: f64 = 2.0;
+const WANDER_RADIUS: f64 = 1.0;
+
+/// Unit steering force towards the wander target: a point on a circle projected
+/// ahead of the shark, at `wander_angle` from the heading.
+fn calculate_wander(shark: &Shark, wander_angle: f64) -> Point<f64> {
+    let target_angle = shark.rotation_rad + wander_angle;
+    let target = Point::new(
+        WANDER_DISTANCE * shark.rotation_rad.cos() + WANDER_RADIUS * target_angle.cos(),
+        WANDER_DISTANCE * shark.rotation_rad.sin() + WANDER_RADIUS * target_angle.sin(),
+    );
+    let norm = (target.x().powi(2) + target.y().powi(2)).sqrt();
+    Point::new(target.x() / norm, target.y() / norm)
+}
+
+/// Unit steering force straight away from `from`.
+fn calculate_flight(shark: &Shark, from: Point<f64>) -> Point<f64> {
+    let away = Point::new(shark.position.x() - from.x(), shark.position.y() - from.y());
+    let norm = away.x().hypot(away.y());
+    if norm > f64::EPSILON {
+        Point::new(away.x() / norm, away.y() / norm)
+    } else {
+        // right on top of it, anywhere is away
+        Point::new(shark.rotation_rad.cos(), shark.rotation_rad.sin())
     }
 }
 
 // 7. NEW HELPER FUNCTION FOR GOAL SEEKING
 
 /// Calculates a steering force towards the closest goal point within the radius.
+/// `goals` pairs each point with how hard it pulls, scaling the force; those
+/// pulling 0 are passed over.
 fn calculate_goal_seeking(
     shark: &Shark,
-    goals: &[Point<f64>],
+    goals: impl IntoIterator<Item = (Point<f64>, f64)>,
     goal_seeking_radius: f64,
 ) -> Point<f64> {
-    if goals.is_empty() {
-        return Point::new(0.0, 0.0);
-    }
-
-    let mut closest_goal: Option<(Point<f64>, f64)> = None;
+    let mut closest_goal: Option<(Point<f64>, f64, f64)> = None;
 
     // Find the closest goal within the seeking radius
-    for goal in goals {
-        let dist = shark.position.euclidean_distance(goal);
+    for (goal, pull) in goals {
+        if pull <= 0.0 {
+            continue;
+        }
+        let dist = shark.position.euclidean_distance(&goal);
         if dist < goal_seeking_radius {
             match closest_goal {
-                Some((_, current_dist)) if dist < current_dist => {
-                    closest_goal = Some((*goal, dist));
+                Some((_, current_dist, _)) if dist < current_dist => {
+                    closest_goal = Some((goal, dist, pull));
                 }
                 None => {
-                    closest_goal = Some((*goal, dist));
+                    closest_goal = Some((goal, dist, pull));
                 }
                 _ => {}
             }
@@ -230,7 +999,7 @@ fn calculate_goal_seeking(
     }
 
     match closest_goal {
-        Some((goal_point, _)) => {
+        Some((goal_point, _, pull)) => {
             // Steer towards the goal
             let steer_vector = Point::new(
                 goal_point.x() - shark.position.x(),
@@ -239,7 +1008,10 @@ fn calculate_goal_seeking(
             // Normalize the vector to get a unit direction force
             let norm = steer_vector.euclidean_distance(&Point::new(0.0, 0.0));
             if norm > EPSILON {
-                Point::new(steer_vector.x() / norm, steer_vector.y() / norm)
+                Point::new(
+                    steer_vector.x() / norm * pull,
+                    steer_vector.y() / norm * pull,
+                )
             } else {
                 Point::new(0.0, 0.0)
             }
@@ -250,60 +1022,76 @@ fn calculate_goal_seeking(
 
 // --- EXISTING HELPER FUNCTIONS (KEEP THEM AS THEY ARE) ---
 
-fn calculate_cohesion(shark: &Shark, nearby: &[&Shark]) -> Point<f64> {
-    // ... (unchanged)
-    if nearby.is_empty() {
-        return Point::new(0.0, 0.0);
-    }
+/// `nearby` holds each neighbor with its distance to `shark`.
+fn calculate_cohesion(
+    shark: &Shark,
+    nearby: &[(f64, &Shark)],
+    kernel: WeightKernel,
+    perception_radius: f64,
+) -> Point<f64> {
     let mut avg_pos = Point::new(0.0, 0.0);
-    for neighbor in nearby {
+    let mut total_weight = 0.0;
+    for (dist, neighbor) in nearby {
+        let weight = kernel.weight(*dist, perception_radius);
         avg_pos = Point::new(
-            avg_pos.x() + neighbor.position.x(),
-            avg_pos.y() + neighbor.position.y(),
+            avg_pos.x() + neighbor.position.x() * weight,
+            avg_pos.y() + neighbor.position.y() * weight,
         );
+        total_weight += weight;
     }
-    avg_pos = Point::new(
-        avg_pos.x() / nearby.len() as f64,
-        avg_pos.y() / nearby.len() as f64,
-    );
+    if total_weight <= 0.0 {
+        return Point::new(0.0, 0.0);
+    }
+    avg_pos = Point::new(avg_pos.x() / total_weight, avg_pos.y() / total_weight);
     Point::new(
         avg_pos.x() - shark.position.x(),
         avg_pos.y() - shark.position.y(),
     )
 }
 
-fn calculate_separation(shark: &Shark, nearby: &[&Shark], separation_distance: f64) -> Point<f64> {
-    // ... (unchanged)
+fn calculate_separation(
+    shark: &Shark,
+    nearby: &[(f64, &Shark)],
+    kernel: WeightKernel,
+    separation_distance: f64,
+) -> Point<f64> {
     let mut steer = Point::new(0.0, 0.0);
-    for neighbor in nearby {
-        let dist = shark.position.euclidean_distance(&neighbor.position);
+    for &(dist, neighbor) in nearby {
         if dist > 0.0 && dist < separation_distance {
             let diff = Point::new(
                 shark.position.x() - neighbor.position.x(),
                 shark.position.y() - neighbor.position.y(),
             );
-            steer = Point::new(steer.x() + diff.x() / dist, steer.y() + diff.y() / dist);
+            let weight = kernel.weight(dist, separation_distance);
+            steer = Point::new(
+                steer.x() + diff.x() / dist * weight,
+                steer.y() + diff.y() / dist * weight,
+            );
         }
     }
     steer
 }
 
-fn calculate_alignment(shark: &Shark, nearby: &[&Shark]) -> Point<f64> {
-    // ... (unchanged)
-    if nearby.is_empty() {
-        return Point::new(0.0, 0.0);
-    }
+fn calculate_alignment(
+    shark: &Shark,
+    nearby: &[(f64, &Shark)],
+    kernel: WeightKernel,
+    perception_radius: f64,
+) -> Point<f64> {
     let mut avg_vel = Point::new(0.0, 0.0);
-    for neighbor in nearby {
+    let mut total_weight = 0.0;
+    for (dist, neighbor) in nearby {
+        let weight = kernel.weight(*dist, perception_radius);
         avg_vel = Point::new(
-            avg_vel.x() + neighbor.rotation_rad.cos(),
-            avg_vel.y() + neighbor.rotation_rad.sin(),
+            avg_vel.x() + neighbor.rotation_rad.cos() * weight,
+            avg_vel.y() + neighbor.rotation_rad.sin() * weight,
         );
+        total_weight += weight;
     }
-    avg_vel = Point::new(
-        avg_vel.x() / nearby.len() as f64,
-        avg_vel.y() / nearby.len() as f64,
-    );
+    if total_weight <= 0.0 {
+        return Point::new(0.0, 0.0);
+    }
+    avg_vel = Point::new(avg_vel.x() / total_weight, avg_vel.y() / total_weight);
 
     let shark_vel = Point::new(shark.rotation_rad.cos(), shark.rotation_rad.sin());
     Point::new(avg_vel.x() - shark_vel.x(), avg_vel.y() - shark_vel.y())
@@ -313,10 +1101,27 @@ fn calculate_land_avoidance(
     _shark: &Shark,
     future_pos: &Point<f64>,
     land_shape_file: &[Polygon<f64>],
+    land_coarse: &[Polygon<f64>],
+    land_field: &LandField,
     land_bounds: &[Rect<f64>],
     land_avoid_radius: f64,
 ) -> Point<f64> {
-    // ... (unchanged)
+    // away from the coast the field knows the way out to sea, only the
+    // nearest coast counts then
+    if let Some((distance, gradient)) = land_field.sample(*future_pos)
+        && distance.abs() >= EXACT_BAND
+    {
+        let norm = gradient.x().hypot(gradient.y());
+        if distance.abs() >= land_avoid_radius || norm <= f64::EPSILON {
+            return Point::new(0.0, 0.0);
+        }
+        let strength = (land_avoid_radius - distance.abs()) / land_avoid_radius;
+        return Point::new(
+            gradient.x() / norm * strength,
+            gradient.y() / norm * strength,
+        );
+    }
+
     let mut total_avoidance_force = Point::new(0.0, 0.0);
 
     for (i, poly) in land_shape_file.iter().enumerate() {
@@ -340,21 +1145,17 @@ fn calculate_land_avoidance(
             continue;
         }
 
-        let closest = poly.closest_point(future_pos);
-        let cp = match closest {
-            Closest::Indeterminate => continue,
-            Closest::Intersection(p) => p,
-            Closest::SinglePoint(p) => p,
+        let Some((cp, dist)) = closest_coast(poly, &land_coarse[i], future_pos) else {
+            continue;
         };
-
-        let dist = future_pos.euclidean_distance(&cp);
         if dist >= land_avoid_radius {
             continue;
         }
 
         let dir_vec = Point::new(cp.x() - future_pos.x(), cp.y() - future_pos.y());
 
-        let is_inside = poly.contains(future_pos);
+        // that far from the coarse coast it can only be out at sea
+        let is_inside = dist < NEAR_COAST && poly.contains(future_pos);
 
         let dir = if is_inside {
             dir_vec
@@ -389,7 +1190,10 @@ fn calculate_border_avoidance(
 ) -> Point<f64> {
     // ... (unchanged)
     let (min_x, min_y, max_x, max_y) = map_bounds;
-    let mut desired_velocity = Point::new(0.0, 0.0);
+    let current_velocity = Point::new(shark.rotation_rad.cos(), shark.rotation_rad.sin());
+    // keeps swimming along an edge rather than being held facing the wall,
+    // which pins a shark heading straight at it
+    let mut desired_velocity = current_velocity;
     let mut changed = false;
 
     if future_pos.x() < min_x + border_margin {
@@ -409,7 +1213,6 @@ fn calculate_border_avoidance(
     }
 
     if changed {
-        let current_velocity = Point::new(shark.rotation_rad.cos(), shark.rotation_rad.sin());
         return Point::new(
             desired_velocity.x() - current_velocity.x(),
             desired_velocity.y() - current_velocity.y(),
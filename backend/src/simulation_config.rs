@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use crate::{Km, RadPerSec, WeightKernel};
+
+/// Tuning of the steering behaviours, passed to `Simulation::step`. Strengths
+/// are unitless gains, everything else carries its unit, see `units`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// Default radius of the `/neighbors` graph. Sharks themselves see as far
+    /// as their species does, see `Species`.
+    pub perception_radius: Km,
+    pub cohesion_strength: f64,
+    pub separation_distance: Km,
+    pub separation_strength: f64,
+    pub alignment_strength: f64,
+    pub land_avoid_radius: Km,
+    pub land_avoid_strength: f64,
+    /// `(min_lon, min_lat, max_lon, max_lat)` in degrees
+    pub map_bounds: (f64, f64, f64, f64),
+    pub border_margin: Km,
+    pub border_strength: f64,
+    pub goal_seeking_radius: Km,
+    pub goal_seeking_strength: f64,
+    /// Pull back into the species' preferred temperature band, only with a
+    /// sea surface temperature grid loaded, see `Environment`
+    pub temperature_strength: f64,
+    /// Latitude in degrees, north or south, beyond which sharks are pushed back
+    /// towards the equator, harder the closer they get to the edge of the map.
+    /// Where a sea surface temperature grid has data the temperature decides instead.
+    pub polar_latitude: f64,
+    pub polar_strength: f64,
+    /// Pull back into a species' soft range from outside, see `SpeciesRangeConfig`
+    pub range_strength: f64,
+    /// Brings current speeds onto the simulation's time scale, see `units`.
+    /// 0 lets the sharks swim through currents untouched.
+    pub current_drift_scale: f64,
+    /// Metres below which the water turns cold, sharks mostly stay above it
+    pub thermocline_depth_m: f64,
+    /// How much of a dive below the thermocline is cut short, 0 ignores it
+    /// and 1 keeps sharks above it
+    pub thermocline_avoidance: f64,
+    /// Simulated seconds per real second of the clock behind dawn and dusk,
+    /// e.g. 1440 for a day every minute, see `SimClock`
+    pub clock_speed: f64,
+    /// Hours either side of dawn and dusk over which sharks are most active
+    pub twilight_hours: f64,
+    /// How much farther sharks see at dawn and dusk, 1 for no change
+    pub twilight_perception_factor: f64,
+    /// How much harder sharks go after goals and prey at dawn and dusk
+    pub twilight_hunting_factor: f64,
+    /// Energy a shark uses per km swum, so faster sharks tire sooner, see
+    /// `Shark::energy`. 0 turns metabolism off.
+    pub energy_use_per_km: f64,
+    /// Sharks this close to a goal feed, regaining `goal_energy_per_sec`
+    pub goal_feeding_radius: Km,
+    pub goal_energy_per_sec: f64,
+    /// Below this much energy sharks slow down and seek goals and prey harder
+    pub low_energy: f64,
+    /// Removes sharks that run out of energy, otherwise they carry on at their slowest
+    pub mortality: bool,
+    /// Only the k nearest neighbors within the perception radius are considered
+    pub max_neighbors: Option<usize>,
+    /// Full angle of the perception cone around the heading, 2*PI sees all around
+    pub field_of_view_rad: f64,
+    /// Distance weighting applied to cohesion, separation and alignment
+    pub flocking_kernel: WeightKernel,
+    /// Reynolds wander: idle steering towards a target drifting on a circle ahead
+    pub wander_strength: f64,
+    /// Random drift of the wander target
+    pub wander_jitter: RadPerSec,
+    pub max_turn_rate: RadPerSec,
+    /// Sharks closer than this join a school, schoolmates leave beyond `school_leave_radius`
+    pub school_join_radius: Km,
+    pub school_leave_radius: Km,
+    /// Sharks this close to where a client adds a goal get `disturbance_stress`
+    /// more stress
+    pub disturbance_radius: Km,
+    pub disturbance_stress: f64,
+    /// Seconds for stress to wear off to about a third
+    pub stress_decay_secs: f64,
+    /// Stress at which sharks stop feeding and flee the last disturbance, so a
+    /// goal now and then draws them but a flurry of them scatters the school
+    pub flight_stress: f64,
+    pub flight_strength: f64,
+    /// From this many sharks on they are stepped on all cores, below it the
+    /// threads cost more than they save. The result is the same either way.
+    pub parallel_from_sharks: usize,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            perception_radius: Km(445.),
+            cohesion_strength: 0.1,
+            separation_distance: Km(223.),
+            separation_strength: 0.1,
+            alignment_strength: 0.05,
+            land_avoid_radius: Km(1113.),
+            land_avoid_strength: 100.,
+            map_bounds: (-180., -85., 180.0, 85.0),
+            border_margin: Km(56.),
+            border_strength: 6.0,
+            goal_seeking_radius: Km(1113.),
+            goal_seeking_strength: 0.3,
+            temperature_strength: 0.3,
+            polar_latitude: 60.0,
+            polar_strength: 0.5,
+            range_strength: 1.0,
+            // roughly how much faster than real sharks the simulated ones swim
+            current_drift_scale: 50_000.0,
+            thermocline_depth_m: 150.0,
+            thermocline_avoidance: 0.7,
+            clock_speed: 1440.0,
+            twilight_hours: 1.5,
+            twilight_perception_factor: 1.3,
+            twilight_hunting_factor: 1.5,
+            energy_use_per_km: 0.0,
+            goal_feeding_radius: Km(100.),
+            goal_energy_per_sec: 0.1,
+            low_energy: 0.25,
+            mortality: true,
+            max_neighbors: Some(7),
+            field_of_view_rad: 1.5 * PI, // blind spot behind the tail
+            flocking_kernel: WeightKernel::Smooth,
+            wander_strength: 0.3,
+            wander_jitter: RadPerSec(2.0),
+            max_turn_rate: RadPerSec(PI),
+            school_join_radius: Km(223.),
+            school_leave_radius: Km(334.),
+            disturbance_radius: Km(500.),
+            disturbance_stress: 0.4,
+            stress_decay_secs: 20.0,
+            flight_stress: 1.0,
+            flight_strength: 1.0,
+            parallel_from_sharks: 1000,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Reads a `.toml` or `.json` file. Missing keys keep their defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(toml::from_str(&text)?),
+            _ => Ok(serde_json::from_str(&text)?),
+        }
+    }
+
+    /// A copy with the keys in `overrides` replaced. Fails on unknown keys and
+    /// values that don't fit, leaving `self` as it was.
+    pub fn with_overrides(&self, overrides: Map<String, Value>) -> Result<Self, Box<dyn Error>> {
+        let mut value = serde_json::to_value(self)?;
+        let fields = value
+            .as_object_mut()
+            .expect("config serializes to an object");
+        for (key, override_value) in overrides {
+            if !fields.contains_key(&key) {
+                return Err(format!("unknown parameter {}", key).into());
+            }
+            fields.insert(key, override_value);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn cohesion(mut self, strength: f64) -> Self {
+        self.cohesion_strength = strength;
+        self
+    }
+
+    pub fn separation(mut self, distance: Km, strength: f64) -> Self {
+        self.separation_distance = distance;
+        self.separation_strength = strength;
+        self
+    }
+
+    pub fn alignment(mut self, strength: f64) -> Self {
+        self.alignment_strength = strength;
+        self
+    }
+
+    pub fn land_avoidance(mut self, radius: Km, strength: f64) -> Self {
+        self.land_avoid_radius = radius;
+        self.land_avoid_strength = strength;
+        self
+    }
+
+    pub fn border(mut self, margin: Km, strength: f64) -> Self {
+        self.border_margin = margin;
+        self.border_strength = strength;
+        self
+    }
+
+    pub fn goal_seeking(mut self, radius: Km, strength: f64) -> Self {
+        self.goal_seeking_radius = radius;
+        self.goal_seeking_strength = strength;
+        self
+    }
+
+    pub fn perception(
+        mut self,
+        radius: Km,
+        max_neighbors: Option<usize>,
+        field_of_view_rad: f64,
+    ) -> Self {
+        self.perception_radius = radius;
+        self.max_neighbors = max_neighbors;
+        self.field_of_view_rad = field_of_view_rad;
+        self
+    }
+}
@@ -0,0 +1,59 @@
+use rayon::prelude::*;
+
+use crate::{Shark, SpatialGrid};
+
+/// What a step reads of the sharks: every one of them as they were when the
+/// step began, and a grid over their positions. It borrows them for the
+/// whole step and the step writes elsewhere, see `SimulationScratch`, so a
+/// shark can't see another one's next position however the work is split.
+pub struct SimulationState<'a> {
+    pub sharks: &'a [Shark],
+    pub grid: &'a SpatialGrid,
+}
+
+/// The buffers of `Simulation::step`, kept from tick to tick so a step
+/// allocates nothing for the sharks once they've grown to fit them all.
+/// A copy starts out empty, snapshots have no use for them.
+#[derive(Debug, Default)]
+pub struct SimulationScratch {
+    /// Where the next tick's sharks are written, the last tick's once swapped
+    next_sharks: Vec<Shark>,
+    grid: SpatialGrid,
+}
+
+impl Clone for SimulationScratch {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl SimulationScratch {
+    /// Replaces every shark with what `step_shark(state, i)` makes of shark
+    /// `i`. The sharks keep their order, and come out the same on one thread
+    /// or, with `parallel`, on rayon's, as long as `step_shark` only goes by
+    /// `state` and its arguments. `cell_size` is that of `state.grid`.
+    pub fn step_sharks(
+        &mut self,
+        sharks: &mut Vec<Shark>,
+        cell_size: f64,
+        parallel: bool,
+        step_shark: impl Fn(&SimulationState, usize) -> Shark + Sync,
+    ) {
+        self.grid
+            .rebuild(cell_size, sharks.iter().map(|shark| shark.position));
+        let state = SimulationState {
+            sharks,
+            grid: &self.grid,
+        };
+        let count = state.sharks.len();
+        self.next_sharks.clear();
+        if parallel {
+            self.next_sharks
+                .par_extend((0..count).into_par_iter().map(|i| step_shark(&state, i)));
+        } else {
+            self.next_sharks
+                .extend((0..count).map(|i| step_shark(&state, i)));
+        }
+        std::mem::swap(sharks, &mut self.next_sharks);
+    }
+}
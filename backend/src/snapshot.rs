@@ -0,0 +1,484 @@
+use crate::{Shark, Simulation};
+use geo::Point;
+use serde::Serialize;
+use serde::ser::{SerializeMap, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+/// Which shark fields a client wants in its frames.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldMask {
+    pub position: bool,
+    pub rotation_rad: bool,
+    pub speed: bool,
+    pub angular_velocity: bool,
+    pub reported_rotation_rad: bool,
+    pub school_id: bool,
+    pub informed: bool,
+    pub species: bool,
+    pub energy: bool,
+    pub depth: bool,
+    pub stress: bool,
+}
+
+impl FieldMask {
+    pub fn all() -> Self {
+        Self {
+            position: true,
+            rotation_rad: true,
+            speed: true,
+            angular_velocity: true,
+            reported_rotation_rad: true,
+            school_id: true,
+            informed: true,
+            species: true,
+            energy: true,
+            depth: true,
+            stress: true,
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.position
+            && self.rotation_rad
+            && self.speed
+            && self.angular_velocity
+            && self.reported_rotation_rad
+            && self.school_id
+            && self.informed
+            && self.species
+            && self.energy
+            && self.depth
+            && self.stress
+    }
+
+    /// Parses the `fields` parameter of a connect query string,
+    /// e.g. `fields=position,rotation_rad`. Without one every field is sent,
+    /// unknown fields are ignored.
+    pub fn from_query(query: &str) -> Self {
+        let Some(fields) = query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("fields="))
+        else {
+            return Self::all();
+        };
+
+        let mut mask = Self {
+            position: false,
+            rotation_rad: false,
+            speed: false,
+            angular_velocity: false,
+            reported_rotation_rad: false,
+            school_id: false,
+            informed: false,
+            species: false,
+            energy: false,
+            depth: false,
+            stress: false,
+        };
+        for field in fields.split(',') {
+            match field {
+                "position" => mask.position = true,
+                "rotation_rad" => mask.rotation_rad = true,
+                "speed" => mask.speed = true,
+                "angular_velocity" => mask.angular_velocity = true,
+                "reported_rotation_rad" => mask.reported_rotation_rad = true,
+                "school_id" => mask.school_id = true,
+                "informed" => mask.informed = true,
+                "species" => mask.species = true,
+                "energy" => mask.energy = true,
+                "depth" => mask.depth = true,
+                "stress" => mask.stress = true,
+                _ => {}
+            }
+        }
+        mask
+    }
+}
+
+struct SparseShark<'a> {
+    shark: &'a Shark,
+    mask: &'a FieldMask,
+}
+
+impl Serialize for SparseShark<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        // always sent, clients need it to tell sharks apart
+        map.serialize_entry("id", &self.shark.id)?;
+        if self.mask.position {
+            map.serialize_entry("position", &self.shark.position)?;
+        }
+        if self.mask.rotation_rad {
+            map.serialize_entry("rotation_rad", &self.shark.rotation_rad)?;
+        }
+        if self.mask.speed {
+            map.serialize_entry("speed", &self.shark.speed)?;
+        }
+        if self.mask.angular_velocity {
+            map.serialize_entry("angular_velocity", &self.shark.angular_velocity)?;
+        }
+        if self.mask.reported_rotation_rad {
+            map.serialize_entry("reported_rotation_rad", &self.shark.reported_rotation_rad)?;
+        }
+        if self.mask.school_id {
+            map.serialize_entry("school_id", &self.shark.school_id)?;
+        }
+        if self.mask.informed {
+            map.serialize_entry("informed", &self.shark.informed)?;
+        }
+        if self.mask.species {
+            map.serialize_entry("species", &self.shark.species)?;
+        }
+        if self.mask.energy {
+            map.serialize_entry("energy", &self.shark.energy)?;
+        }
+        if self.mask.depth {
+            map.serialize_entry("depth", &self.shark.depth)?;
+        }
+        if self.mask.stress {
+            map.serialize_entry("stress", &self.shark.stress)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct SparseFrame<'a> {
+    sharks: Vec<SparseShark<'a>>,
+}
+
+/// Serializes the simulation for one client. A full mask produces the regular
+/// frame; anything narrower produces `{"sharks": [...]}` with only the masked fields.
+pub fn serialize_snapshot(simulation: &Simulation, mask: &FieldMask) -> serde_json::Result<String> {
+    if mask.is_all() {
+        return serde_json::to_string(simulation);
+    }
+
+    let frame = SparseFrame {
+        sharks: simulation
+            .sharks
+            .iter()
+            .map(|shark| SparseShark { shark, mask })
+            .collect(),
+    };
+    serde_json::to_string(&frame)
+}
+
+/// Where a shark that was already on the map has got to.
+#[derive(Serialize)]
+struct SharkMove {
+    id: u64,
+    position: Point<f64>,
+    reported_rotation_rad: f64,
+}
+
+#[derive(Serialize)]
+struct Delta<'a> {
+    tick: u64,
+    server_time_ms: f64,
+    /// Sharks that weren't in the previous frame, with the masked fields
+    added: Vec<SparseShark<'a>>,
+    moved: Vec<SharkMove>,
+    removed: Vec<u64>,
+}
+
+#[derive(Serialize)]
+struct DeltaFrame<'a> {
+    delta: Delta<'a>,
+}
+
+/// Serializes what changed between two frames as `{"delta": {...}}`. Only the
+/// sharks are covered, goals and stats wait for the next full frame.
+pub fn serialize_delta(
+    previous: &Simulation,
+    current: &Simulation,
+    mask: &FieldMask,
+) -> serde_json::Result<String> {
+    let before: HashMap<u64, &Shark> = previous
+        .sharks
+        .iter()
+        .map(|shark| (shark.id, shark))
+        .collect();
+    let mut added = Vec::new();
+    let mut moved = Vec::new();
+    for shark in &current.sharks {
+        match before.get(&shark.id) {
+            None => added.push(SparseShark { shark, mask }),
+            Some(old)
+                if old.position != shark.position
+                    || old.reported_rotation_rad != shark.reported_rotation_rad =>
+            {
+                moved.push(SharkMove {
+                    id: shark.id,
+                    position: shark.position,
+                    reported_rotation_rad: shark.reported_rotation_rad,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    let current_ids: HashSet<u64> = current.sharks.iter().map(|shark| shark.id).collect();
+    let removed = previous
+        .sharks
+        .iter()
+        .map(|shark| shark.id)
+        .filter(|id| !current_ids.contains(id))
+        .collect();
+
+    serde_json::to_string(&DeltaFrame {
+        delta: Delta {
+            tick: current.tick,
+            server_time_ms: current.server_time_ms,
+            added,
+            moved,
+            removed,
+        },
+    })
+}
+
+/// Bytes per shark in a packed frame
+pub const PACKED_SHARK_BYTES: usize = 16;
+
+/// Encodes the sharks as a compact binary frame for clients that don't want
+/// JSON. All little-endian: `tick: u64`, `server_time_ms: f64`, `count: u32`,
+/// then per shark `id: u32`, `lon: f32`, `lat: f32`, `reported_rotation_rad: f32`.
+pub fn pack_snapshot(simulation: &Simulation) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(20 + simulation.sharks.len() * PACKED_SHARK_BYTES);
+    bytes.extend_from_slice(&simulation.tick.to_le_bytes());
+    bytes.extend_from_slice(&simulation.server_time_ms.to_le_bytes());
+    bytes.extend_from_slice(&(simulation.sharks.len() as u32).to_le_bytes());
+    for shark in &simulation.sharks {
+        bytes.extend_from_slice(&(shark.id as u32).to_le_bytes());
+        bytes.extend_from_slice(&(shark.position.x() as f32).to_le_bytes());
+        bytes.extend_from_slice(&(shark.position.y() as f32).to_le_bytes());
+        bytes.extend_from_slice(&(shark.reported_rotation_rad as f32).to_le_bytes());
+    }
+    bytes
+}
+
+#[derive(Serialize)]
+struct Columnar {
+    tick: u64,
+    server_time_ms: f64,
+    ids: Vec<u64>,
+    /// Longitudes
+    xs: Vec<f64>,
+    /// Latitudes
+    ys: Vec<f64>,
+    /// `reported_rotation_rad`
+    headings: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct ColumnarFrame {
+    columnar: Columnar,
+}
+
+/// Serializes the sharks as `{"columnar": {...}}`, one array per field that
+/// line up by index. Smaller than a regular frame after compression and
+/// quicker for browsers to turn into typed arrays.
+pub fn serialize_columnar(simulation: &Simulation) -> serde_json::Result<String> {
+    let sharks = &simulation.sharks;
+    serde_json::to_string(&ColumnarFrame {
+        columnar: Columnar {
+            tick: simulation.tick,
+            server_time_ms: simulation.server_time_ms,
+            ids: sharks.iter().map(|shark| shark.id).collect(),
+            xs: sharks.iter().map(|shark| shark.position.x()).collect(),
+            ys: sharks.iter().map(|shark| shark.position.y()).collect(),
+            headings: sharks
+                .iter()
+                .map(|shark| shark.reported_rotation_rad)
+                .collect(),
+        },
+    })
+}
+
+/// `pack_snapshot` laid out by column: the same header, then every `id: u32`,
+/// every `lon: f32`, every `lat: f32` and every `reported_rotation_rad: f32`.
+pub fn pack_columnar(simulation: &Simulation) -> Vec<u8> {
+    let sharks = &simulation.sharks;
+    let mut bytes = Vec::with_capacity(20 + sharks.len() * PACKED_SHARK_BYTES);
+    bytes.extend_from_slice(&simulation.tick.to_le_bytes());
+    bytes.extend_from_slice(&simulation.server_time_ms.to_le_bytes());
+    bytes.extend_from_slice(&(sharks.len() as u32).to_le_bytes());
+    for shark in sharks {
+        bytes.extend_from_slice(&(shark.id as u32).to_le_bytes());
+    }
+    for shark in sharks {
+        bytes.extend_from_slice(&(shark.position.x() as f32).to_le_bytes());
+    }
+    for shark in sharks {
+        bytes.extend_from_slice(&(shark.position.y() as f32).to_le_bytes());
+    }
+    for shark in sharks {
+        bytes.extend_from_slice(&(shark.reported_rotation_rad as f32).to_le_bytes());
+    }
+    bytes
+}
+
+/// Steps per degree of positions and per radian of rotations in quantized frames
+pub const QUANTIZE_SCALE: f64 = 100.0;
+/// Size of the grid cells sharks are lumped into in aggregated frames
+pub const AGGREGATE_CELL_DEG: f64 = 5.0;
+
+fn quantize(value: f64) -> f64 {
+    (value * QUANTIZE_SCALE).round() / QUANTIZE_SCALE
+}
+
+#[derive(Serialize)]
+struct Quantized {
+    tick: u64,
+    server_time_ms: f64,
+    /// `[id, lon, lat, reported_rotation_rad]` per shark
+    sharks: Vec<(u64, f64, f64, f64)>,
+}
+
+#[derive(Serialize)]
+struct QuantizedFrame {
+    quantized: Quantized,
+}
+
+/// Serializes the sharks as `{"quantized": {...}}`, each a bare array with
+/// values rounded to `1 / QUANTIZE_SCALE`, for clients on a reduced QoS level.
+pub fn serialize_quantized(simulation: &Simulation) -> serde_json::Result<String> {
+    serde_json::to_string(&QuantizedFrame {
+        quantized: Quantized {
+            tick: simulation.tick,
+            server_time_ms: simulation.server_time_ms,
+            sharks: simulation
+                .sharks
+                .iter()
+                .map(|shark| {
+                    (
+                        shark.id,
+                        quantize(shark.position.x()),
+                        quantize(shark.position.y()),
+                        quantize(shark.reported_rotation_rad),
+                    )
+                })
+                .collect(),
+        },
+    })
+}
+
+#[derive(Serialize)]
+struct Cell {
+    /// Mean position of the sharks in the cell
+    position: Point<f64>,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct Aggregated {
+    tick: u64,
+    server_time_ms: f64,
+    cell_deg: f64,
+    cells: Vec<Cell>,
+}
+
+#[derive(Serialize)]
+struct AggregatedFrame {
+    aggregated: Aggregated,
+}
+
+/// Serializes shark counts per `AGGREGATE_CELL_DEG` grid cell as
+/// `{"aggregated": {...}}`, the coarsest view a struggling client gets.
+pub fn serialize_aggregated(simulation: &Simulation) -> serde_json::Result<String> {
+    let mut cells: BTreeMap<(i64, i64), (f64, f64, usize)> = BTreeMap::new();
+    for shark in &simulation.sharks {
+        let key = (
+            (shark.position.x() / AGGREGATE_CELL_DEG).floor() as i64,
+            (shark.position.y() / AGGREGATE_CELL_DEG).floor() as i64,
+        );
+        let cell = cells.entry(key).or_default();
+        cell.0 += shark.position.x();
+        cell.1 += shark.position.y();
+        cell.2 += 1;
+    }
+    serde_json::to_string(&AggregatedFrame {
+        aggregated: Aggregated {
+            tick: simulation.tick,
+            server_time_ms: simulation.server_time_ms,
+            cell_deg: AGGREGATE_CELL_DEG,
+            cells: cells
+                .into_values()
+                .map(|(x, y, count)| Cell {
+                    position: Point::new(x / count as f64, y / count as f64),
+                    count,
+                })
+                .collect(),
+        },
+    })
+}
+
+/// The sharks of one tick in a batch, column by column.
+#[derive(Default, Serialize)]
+struct SharkColumns {
+    id: Vec<u64>,
+    lon: Vec<f64>,
+    lat: Vec<f64>,
+    reported_rotation_rad: Vec<f64>,
+}
+
+#[derive(Default, Serialize)]
+struct Batch {
+    tick: Vec<u64>,
+    server_time_ms: Vec<f64>,
+    sharks: Vec<SharkColumns>,
+    /// First and last `Simulation::frame` lost right before this batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missed_frames: Option<[u64; 2]>,
+}
+
+#[derive(Serialize)]
+struct BatchFrame {
+    batch: Batch,
+}
+
+/// Serializes consecutive frames as one `{"batch": {...}}` message for
+/// consumers that archive rather than render. Every array has one entry per
+/// frame, the shark columns of a frame line up by index. `missed_frames` are
+/// the frames the client lost right before these.
+pub fn serialize_batch(
+    frames: &[Arc<Simulation>],
+    missed_frames: Option<[u64; 2]>,
+) -> serde_json::Result<String> {
+    let mut batch = Batch {
+        missed_frames,
+        ..Batch::default()
+    };
+    for frame in frames {
+        batch.tick.push(frame.tick);
+        batch.server_time_ms.push(frame.server_time_ms);
+        let mut columns = SharkColumns::default();
+        for shark in &frame.sharks {
+            columns.id.push(shark.id);
+            columns.lon.push(shark.position.x());
+            columns.lat.push(shark.position.y());
+            columns
+                .reported_rotation_rad
+                .push(shark.reported_rotation_rad);
+        }
+        batch.sharks.push(columns);
+    }
+    serde_json::to_string(&BatchFrame { batch })
+}
+
+/// Bundles past frames into a single `{"history": [...]}` message, oldest first,
+/// each serialized like a live frame.
+pub fn serialize_history(
+    frames: &[Arc<Simulation>],
+    mask: &FieldMask,
+) -> serde_json::Result<String> {
+    let mut json = String::from("{\"history\":[");
+    for (i, frame) in frames.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&serialize_snapshot(frame, mask)?);
+    }
+    json.push_str("]}");
+    Ok(json)
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use tokio::sync::watch;
+
+use crate::Simulation;
+
+/// One published frame. It never changes once published, so every read of
+/// it, over WebSocket or HTTP, sees the whole of one tick and nothing of the
+/// next, however long the reader holds on to it.
+pub type Snapshot = Arc<Simulation>;
+
+/// The physics thread's end of `snapshot_channel`.
+pub struct SnapshotPublisher {
+    latest: Arc<ArcSwap<Simulation>>,
+    frames: watch::Sender<u64>,
+}
+
+/// The readers' end of `snapshot_channel`, cheap to clone. Taking the latest
+/// snapshot never waits on the physics thread or on other readers, and a
+/// reader holding one only keeps that frame alive. New snapshots come at
+/// `tick::broadcast_rate()`; a reader that falls behind gets the latest one
+/// and skips those in between, nothing queues up.
+#[derive(Clone)]
+pub struct Snapshots {
+    latest: Arc<ArcSwap<Simulation>>,
+    frames: watch::Receiver<u64>,
+}
+
+/// Starts publishing with `first` as the latest snapshot.
+pub fn snapshot_channel(first: Snapshot) -> (SnapshotPublisher, Snapshots) {
+    let (frames_tx, frames_rx) = watch::channel(first.frame);
+    let latest = Arc::new(ArcSwap::new(first));
+    let publisher = SnapshotPublisher {
+        latest: latest.clone(),
+        frames: frames_tx,
+    };
+    let snapshots = Snapshots {
+        latest,
+        frames: frames_rx,
+    };
+    (publisher, snapshots)
+}
+
+impl SnapshotPublisher {
+    /// Makes `snapshot` the latest and wakes the readers waiting for it. Fails
+    /// once every reader is gone, which only happens when the server has shut
+    /// down.
+    pub fn publish(&self, snapshot: Snapshot) -> Result<(), watch::error::SendError<u64>> {
+        let frame = snapshot.frame;
+        // stored first, so a reader woken up finds at least this frame
+        self.latest.store(snapshot);
+        self.frames.send(frame)
+    }
+}
+
+impl Snapshots {
+    pub fn latest(&self) -> Snapshot {
+        self.latest.load_full()
+    }
+
+    /// Waits for a snapshot newer than the last one this reader waited for
+    /// and returns the latest. `None` once the physics thread has stopped.
+    pub async fn changed(&mut self) -> Option<Snapshot> {
+        self.frames.changed().await.ok()?;
+        self.frames.borrow_and_update();
+        Some(self.latest())
+    }
+
+    /// Makes the next `changed` return right away, with the current snapshot.
+    pub fn mark_changed(&mut self) {
+        self.frames.mark_changed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Shark, Species};
+    use geo::Point;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    const PUBLISHED: u64 = 2_000;
+    const READERS: usize = 4;
+
+    /// Tick, frame and sharks all derived from `n`, so a reader can tell a
+    /// snapshot mixed from two publishes apart from a whole one.
+    fn snapshot(n: u64) -> Snapshot {
+        let mut simulation =
+            Simulation::new(0, &mut rand::rng(), Arc::default(), Vec::new()).unwrap();
+        simulation.tick = n;
+        simulation.frame = n;
+        simulation.sharks = (0..n % 50)
+            .map(|i| Shark::new(n, Species::Tiger, Point::new(i as f64, n as f64), 0.0, 1.0))
+            .collect();
+        Arc::new(simulation)
+    }
+
+    fn assert_whole(snapshot: &Simulation) {
+        let n = snapshot.frame;
+        assert_eq!(snapshot.tick, n);
+        assert_eq!(snapshot.sharks.len() as u64, n % 50);
+        for (i, shark) in snapshot.sharks.iter().enumerate() {
+            assert_eq!(shark.id, n);
+            assert_eq!(shark.position, Point::new(i as f64, n as f64));
+        }
+    }
+
+    #[test]
+    fn readers_only_see_whole_snapshots_in_order() {
+        let (publisher, snapshots) = snapshot_channel(snapshot(0));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..READERS)
+            .map(|reader| {
+                let mut snapshots = snapshots.clone();
+                let done = done.clone();
+                thread::spawn(move || {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .build()
+                        .unwrap();
+                    let mut last = 0;
+                    let mut seen = 0;
+                    // at least one read, even if the publisher is done already
+                    loop {
+                        let finished = done.load(Ordering::Acquire);
+                        // half the readers poll, the other half wait for changes
+                        let snapshot = if reader % 2 == 0 {
+                            snapshots.latest()
+                        } else {
+                            match runtime.block_on(snapshots.changed()) {
+                                Some(snapshot) => snapshot,
+                                None => break,
+                            }
+                        };
+                        assert_whole(&snapshot);
+                        assert!(
+                            snapshot.frame >= last,
+                            "went back from {} to {}",
+                            last,
+                            snapshot.frame
+                        );
+                        last = snapshot.frame;
+                        seen += 1;
+                        if finished {
+                            break;
+                        }
+                    }
+                    seen
+                })
+            })
+            .collect();
+        drop(snapshots);
+
+        for n in 1..=PUBLISHED {
+            publisher.publish(snapshot(n)).unwrap();
+        }
+        done.store(true, Ordering::Release);
+        // wakes the readers waiting on `changed`
+        drop(publisher);
+
+        for reader in readers {
+            assert!(reader.join().unwrap() > 0);
+        }
+    }
+}
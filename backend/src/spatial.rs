@@ -0,0 +1,74 @@
+use geo::{Distance, Euclidean, Point};
+use std::collections::HashMap;
+
+/// Uniform grid over points for radius queries, rebuilt whenever the points
+/// move. With the cell size near the usual query radius a query only looks at
+/// the 3x3 cells around the center instead of every point.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialGrid {
+    cell_size: f64,
+    cells: HashMap<Cell, Vec<(usize, Point<f64>)>>,
+}
+
+type Cell = (i64, i64);
+
+impl SpatialGrid {
+    /// Indexes `points`, each under its position in the iterator.
+    pub fn new(cell_size: f64, points: impl IntoIterator<Item = Point<f64>>) -> Self {
+        let mut grid = Self {
+            cell_size: cell_size.max(f64::MIN_POSITIVE),
+            cells: HashMap::new(),
+        };
+        for (index, point) in points.into_iter().enumerate() {
+            let cell = grid.cell_of(point);
+            grid.cells.entry(cell).or_default().push((index, point));
+        }
+        grid
+    }
+
+    /// Indexes `points` like `new`, reusing the cells still in use, so a grid
+    /// rebuilt every tick stops allocating once the points settle.
+    pub fn rebuild(&mut self, cell_size: f64, points: impl IntoIterator<Item = Point<f64>>) {
+        self.cell_size = cell_size.max(f64::MIN_POSITIVE);
+        // cells left empty last time stay empty for good otherwise
+        self.cells.retain(|_, cell| !cell.is_empty());
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        for (index, point) in points.into_iter().enumerate() {
+            let cell = self.cell_of(point);
+            self.cells.entry(cell).or_default().push((index, point));
+        }
+    }
+
+    /// Indices of the points closer than `radius` to `center`, with their distance,
+    /// none unless `radius` is above 0. The order is fixed for a given grid and query.
+    pub fn within(
+        &self,
+        center: Point<f64>,
+        radius: f64,
+    ) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let (cx, cy) = self.cell_of(center);
+        // an empty range of cells for a negative or NaN radius
+        let reach = if radius > 0.0 {
+            (radius / self.cell_size).ceil() as i64
+        } else {
+            -1
+        };
+        (cx - reach..=cx + reach)
+            .flat_map(move |x| (cy - reach..=cy + reach).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter_map(move |(index, point)| {
+                let dist = Euclidean.distance(center, *point);
+                (dist < radius).then_some((*index, dist))
+            })
+    }
+
+    fn cell_of(&self, point: Point<f64>) -> Cell {
+        (
+            (point.x() / self.cell_size).floor() as i64,
+            (point.y() / self.cell_size).floor() as i64,
+        )
+    }
+}
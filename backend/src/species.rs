@@ -0,0 +1,134 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{Km, KmPerHour};
+
+/// Kind of shark, deciding how fast it swims, how far it sees, how keen it
+/// is on the goals and which waters it likes. Speeds are on the simulation's time scale, see `units`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Species {
+    #[default]
+    GreatWhite,
+    Tiger,
+    Hammerhead,
+    WhaleShark,
+}
+
+/// Behaviour of one species, used by `Simulation::step` in place of global values.
+#[derive(Debug, Clone, Copy)]
+pub struct SpeciesParams {
+    /// Slowest and fastest it swims
+    pub speed_limits: (KmPerHour, KmPerHour),
+    pub perception_radius: Km,
+    /// Multiplies the goal seeking strength, 1 for no preference
+    pub goal_affinity: f64,
+    /// Sea surface temperatures in °C it is comfortable in, see `Environment`
+    pub preferred_temperature: (f64, f64),
+    /// Deepest it dives in metres
+    pub max_depth_m: f64,
+    /// Seconds from the surface down and back up, on the simulation's time scale
+    pub dive_period_secs: f64,
+    /// Multiplies `PreyConfig::bite_per_sec`, how much it eats where prey is plentiful
+    pub max_intake: f64,
+    /// Divides `PreyConfig::half_saturation`, how well it feeds where prey is thin
+    pub attack_rate: f64,
+}
+
+impl Species {
+    pub const ALL: [Species; 4] = [
+        Species::GreatWhite,
+        Species::Tiger,
+        Species::Hammerhead,
+        Species::WhaleShark,
+    ];
+
+    pub fn params(self) -> SpeciesParams {
+        match self {
+            Species::GreatWhite => SpeciesParams {
+                speed_limits: (KmPerHour(200_000.), KmPerHour(800_000.)),
+                perception_radius: Km(445.),
+                goal_affinity: 1.0,
+                preferred_temperature: (12.0, 24.0),
+                max_depth_m: 250.0,
+                dive_period_secs: 30.0,
+                max_intake: 1.0,
+                attack_rate: 1.0,
+            },
+            // sticks to its patch more than the others
+            Species::Tiger => SpeciesParams {
+                speed_limits: (KmPerHour(150_000.), KmPerHour(600_000.)),
+                perception_radius: Km(334.),
+                goal_affinity: 0.7,
+                preferred_temperature: (22.0, 30.0),
+                max_depth_m: 150.0,
+                dive_period_secs: 25.0,
+                // eats whatever it comes across
+                max_intake: 1.0,
+                attack_rate: 1.5,
+            },
+            // wide-set eyes, migrates in schools
+            Species::Hammerhead => SpeciesParams {
+                speed_limits: (KmPerHour(180_000.), KmPerHour(700_000.)),
+                perception_radius: Km(556.),
+                goal_affinity: 1.3,
+                preferred_temperature: (20.0, 28.0),
+                max_depth_m: 300.0,
+                dive_period_secs: 40.0,
+                max_intake: 0.8,
+                attack_rate: 1.0,
+            },
+            // a slow filter feeder drifting after plankton
+            Species::WhaleShark => SpeciesParams {
+                speed_limits: (KmPerHour(50_000.), KmPerHour(250_000.)),
+                perception_radius: Km(223.),
+                goal_affinity: 0.5,
+                preferred_temperature: (21.0, 30.0),
+                // rare but very deep dives
+                max_depth_m: 600.0,
+                dive_period_secs: 60.0,
+                // takes in a lot, but only from dense patches
+                max_intake: 2.0,
+                attack_rate: 0.4,
+            },
+        }
+    }
+
+    /// Farthest any species sees, the cell size neighbor lookups need.
+    pub fn max_perception_radius() -> Km {
+        let radius = Self::ALL
+            .iter()
+            .map(|species| species.params().perception_radius.0)
+            .fold(0.0, f64::max);
+        Km(radius)
+    }
+
+    /// As in JSON frames, e.g. `"great_white"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Species::GreatWhite => "great_white",
+            Species::Tiger => "tiger",
+            Species::Hammerhead => "hammerhead",
+            Species::WhaleShark => "whale_shark",
+        }
+    }
+
+    /// Every species equally likely.
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::ALL[rng.random_range(0..Self::ALL.len())]
+    }
+}
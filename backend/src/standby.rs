@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use geo::Point;
+use serde::Deserialize;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::tick::broadcast_rate;
+use crate::{AttractionPoint, HotEvents, PhysicsHandle, PreyField, Shark, SimClock};
+
+/// Shortest the primary may go quiet before the standby takes over
+const MIN_TAKEOVER_AFTER: Duration = Duration::from_secs(1);
+/// Frames the primary may miss in a row before the standby takes over
+const MISSED_FRAMES: u32 = 3;
+
+/// How long the primary may go quiet before the standby takes over: three
+/// frames at the broadcast rate, at least `MIN_TAKEOVER_AFTER`. Assumes the
+/// standby runs with the primary's `--broadcast-hz` and `--tps`.
+pub fn takeover_after() -> Duration {
+    let frame_interval = Duration::from_secs(1) / broadcast_rate() as u32;
+    MIN_TAKEOVER_AFTER.max(frame_interval * MISSED_FRAMES)
+}
+
+/// The parts of a full frame a standby needs to continue the run.
+#[derive(Debug, Deserialize)]
+struct ReplicatedState {
+    sharks: Vec<Shark>,
+    tick: u64,
+    goals: Vec<AttractionPoint>,
+    hotspots: Vec<Point<f64>>,
+    prey: Option<PreyField>,
+    hot_events: Option<HotEvents>,
+    clock: SimClock,
+}
+
+/// Mirrors the frames of the primary at `url` into this process's simulation,
+/// which doesn't step on its own meanwhile. Once the primary disconnects or
+/// sends nothing for `takeover_after()`, the simulation resumes from the last
+/// mirrored frame and this process carries on broadcasting by itself.
+pub async fn follow_primary(url: String, physics: PhysicsHandle) {
+    match connect_async(&url).await {
+        Ok((mut stream, _)) => {
+            println!("standby: following primary at {}", url);
+            let takeover_after = takeover_after();
+            while let Ok(Some(Ok(message))) =
+                tokio::time::timeout(takeover_after, stream.next()).await
+            {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                // the events following a frame, see `serialize_events`
+                if text.starts_with("{\"events\"") {
+                    continue;
+                }
+                let state: ReplicatedState = match serde_json::from_str(&text) {
+                    Ok(state) => state,
+                    Err(e) => {
+                        eprintln!("standby: ignoring unreadable frame: {}", e);
+                        continue;
+                    }
+                };
+                let adopted = physics.commands.send(Box::new(move |simulation, _| {
+                    simulation.adopt_sharks(state.sharks);
+                    simulation.tick = state.tick;
+                    simulation.goals = state.goals;
+                    simulation.hotspots = state.hotspots;
+                    simulation.clock = state.clock;
+                    if let (Some(prey), Some(mirrored)) = (&mut simulation.prey, state.prey) {
+                        prey.adopt_schools(mirrored.schools);
+                    }
+                    if let (Some(hot_events), Some(mirrored)) =
+                        (&mut simulation.hot_events, state.hot_events)
+                    {
+                        hot_events.adopt_events(mirrored.active);
+                    }
+                }));
+                if adopted.is_err() {
+                    return;
+                }
+            }
+            println!("standby: lost primary, taking over");
+        }
+        Err(e) => eprintln!(
+            "standby: can't reach primary at {}, taking over: {}",
+            url, e
+        ),
+    }
+    let _ = physics
+        .commands
+        .send(Box::new(|simulation, _| simulation.replica = false));
+}
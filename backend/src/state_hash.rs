@@ -0,0 +1,47 @@
+use serde::Serialize;
+
+use crate::Shark;
+
+/// Positions are rounded to 1e-6 degrees (~0.1 m) before hashing so the hash
+/// only changes when behaviour does, not on float noise in the last bits.
+const POSITION_QUANTUM: f64 = 1e-6;
+const ANGLE_QUANTUM: f64 = 1e-6;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StateHash {
+    pub tick: u64,
+    /// Hex string because JavaScript numbers can't hold a full u64
+    pub hash: String,
+}
+
+impl StateHash {
+    pub fn new(tick: u64, sharks: &[Shark]) -> Self {
+        Self {
+            tick,
+            hash: format!("{:016x}", state_hash(sharks)),
+        }
+    }
+}
+
+/// FNV-1a over the quantized shark state. Unlike `DefaultHasher` the result is
+/// stable across Rust versions and platforms, so runs with a fixed seed can be compared.
+pub fn state_hash(sharks: &[Shark]) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut feed = |value: i64| {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+
+    for shark in sharks {
+        feed((shark.position.x() / POSITION_QUANTUM).round() as i64);
+        feed((shark.position.y() / POSITION_QUANTUM).round() as i64);
+        feed((shark.rotation_rad / ANGLE_QUANTUM).round() as i64);
+        feed((shark.speed / POSITION_QUANTUM).round() as i64);
+    }
+    hash
+}
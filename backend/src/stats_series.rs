@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use utoipa::ToSchema;
+
+use crate::summary::goal_visitors;
+use crate::{KmPerHour, Simulation};
+
+/// Resolutions kept, finest first: an hour at 1s, a day at 1min and a week
+/// at 10min. Samples are expected once per second.
+const LEVELS: [(Resolution, usize); 3] = [
+    (Resolution::Second, 3600),
+    (Resolution::Minute, 1440),
+    (Resolution::TenMinutes, 1008),
+];
+
+/// Time between two points of a series.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+pub enum Resolution {
+    #[default]
+    #[serde(rename = "1s")]
+    Second,
+    #[serde(rename = "1min")]
+    Minute,
+    #[serde(rename = "10min")]
+    TenMinutes,
+}
+
+impl Resolution {
+    /// Samples that make up one point
+    fn samples(self) -> usize {
+        match self {
+            Resolution::Second => 1,
+            Resolution::Minute => 60,
+            Resolution::TenMinutes => 600,
+        }
+    }
+}
+
+/// The aggregate stats at one point in time, or their mean over a bucket.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct StatsSample {
+    /// Start of the bucket on the server clock, see `tick::server_time_ms`
+    pub server_time_ms: f64,
+    pub sharks: f64,
+    pub avg_speed_kmh: f64,
+    /// Goals with at least one shark visiting
+    pub active_hotspots: f64,
+    /// Sharks visiting each goal, in the order of `goals` in frames
+    pub goal_visitors: Vec<f64>,
+}
+
+impl StatsSample {
+    pub fn new(simulation: &Simulation) -> Self {
+        let visitors = goal_visitors(simulation);
+        let speeds: f64 = simulation.sharks.iter().map(|shark| shark.speed).sum();
+        let avg_speed = speeds / simulation.sharks.len().max(1) as f64;
+        Self {
+            server_time_ms: simulation.server_time_ms,
+            sharks: simulation.sharks.len() as f64,
+            avg_speed_kmh: KmPerHour::from_degrees_per_sec(avg_speed).0,
+            active_hotspots: visitors.iter().filter(|&&count| count > 0).count() as f64,
+            goal_visitors: visitors.into_iter().map(|count| count as f64).collect(),
+        }
+    }
+
+    /// Mean of `samples`, stamped with the first one's time.
+    fn mean(samples: &[StatsSample]) -> Self {
+        let n = samples.len().max(1) as f64;
+        let goals = samples
+            .iter()
+            .map(|s| s.goal_visitors.len())
+            .max()
+            .unwrap_or(0);
+        let mut goal_visitors = vec![0.0; goals];
+        for sample in samples {
+            for (total, count) in goal_visitors.iter_mut().zip(&sample.goal_visitors) {
+                *total += count / n;
+            }
+        }
+        Self {
+            server_time_ms: samples.first().map_or(0.0, |s| s.server_time_ms),
+            sharks: samples.iter().map(|s| s.sharks).sum::<f64>() / n,
+            avg_speed_kmh: samples.iter().map(|s| s.avg_speed_kmh).sum::<f64>() / n,
+            active_hotspots: samples.iter().map(|s| s.active_hotspots).sum::<f64>() / n,
+            goal_visitors,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Level {
+    resolution: Resolution,
+    capacity: usize,
+    points: VecDeque<StatsSample>,
+    /// Samples of the bucket not yet complete
+    pending: Vec<StatsSample>,
+}
+
+/// Rolling history of `StatsSample`s at several resolutions, each point of a
+/// coarser one the mean of the finest samples it covers. Memory stays fixed,
+/// so a week of history needs no database.
+#[derive(Debug)]
+pub struct StatsSeries {
+    levels: Vec<Level>,
+}
+
+impl Default for StatsSeries {
+    fn default() -> Self {
+        Self {
+            levels: LEVELS
+                .iter()
+                .map(|&(resolution, capacity)| Level {
+                    resolution,
+                    capacity,
+                    points: VecDeque::with_capacity(capacity),
+                    pending: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl StatsSeries {
+    /// Adds a sample, meant to be taken once per second.
+    pub fn push(&mut self, sample: StatsSample) {
+        for level in &mut self.levels {
+            level.pending.push(sample.clone());
+            if level.pending.len() < level.resolution.samples() {
+                continue;
+            }
+            let point = StatsSample::mean(&level.pending);
+            level.pending.clear();
+            if level.points.len() == level.capacity {
+                level.points.pop_front();
+            }
+            level.points.push_back(point);
+        }
+    }
+
+    /// Points at `resolution` from the last `seconds`, oldest first.
+    pub fn range(&self, resolution: Resolution, seconds: f64) -> Vec<StatsSample> {
+        let Some(level) = self.levels.iter().find(|l| l.resolution == resolution) else {
+            return Vec::new();
+        };
+        let Some(latest) = level.points.back() else {
+            return Vec::new();
+        };
+        let since = latest.server_time_ms - seconds * 1000.0;
+        level
+            .points
+            .iter()
+            .filter(|point| point.server_time_ms >= since)
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,85 @@
+use geo::Point;
+use serde::Deserialize;
+
+/// How the flocking behaviours are combined into one steering force.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SteeringScheme {
+    /// Each force times its strength, added up. Strengths are absolute, so one
+    /// large strength drowns out the others.
+    #[default]
+    Sum,
+    /// Each force capped to unit length, then blended by its strength relative
+    /// to the sum of all strengths, and scaled to `max_force`. Strengths only
+    /// matter relative to each other.
+    Weighted { max_force: f64 },
+    /// Behaviours take turns in priority order: land avoidance, border, goals,
+    /// then flocking. Each one whose weighted force is at least `satisfied_below`
+    /// gets as much of the remaining `max_force` budget as it asks for, so lower
+    /// priorities only steer with what is left over.
+    Priority {
+        max_force: f64,
+        satisfied_below: f64,
+    },
+}
+
+/// A behaviour's force paired with its strength, in priority order where that matters
+pub type Behavior = (Point<f64>, f64);
+
+impl SteeringScheme {
+    pub fn combine(&self, behaviors: &[Behavior]) -> Point<f64> {
+        match self {
+            SteeringScheme::Sum => behaviors
+                .iter()
+                .fold(Point::new(0.0, 0.0), |total, (force, strength)| {
+                    total + *force * *strength
+                }),
+            SteeringScheme::Weighted { max_force } => {
+                let total_strength: f64 = behaviors.iter().map(|(_, strength)| strength).sum();
+                if total_strength <= 0.0 {
+                    return Point::new(0.0, 0.0);
+                }
+                behaviors
+                    .iter()
+                    .fold(Point::new(0.0, 0.0), |total, (force, strength)| {
+                        total + cap_length(*force, 1.0) * (strength / total_strength)
+                    })
+                    * *max_force
+            }
+            SteeringScheme::Priority {
+                max_force,
+                satisfied_below,
+            } => {
+                let mut total = Point::new(0.0, 0.0);
+                let mut budget = *max_force;
+                for (force, strength) in behaviors {
+                    if budget <= 0.0 {
+                        break;
+                    }
+                    let wanted = *force * *strength;
+                    if length(wanted) < *satisfied_below {
+                        continue;
+                    }
+                    let granted = cap_length(wanted, budget);
+                    budget -= length(granted);
+                    total += granted;
+                }
+                total
+            }
+        }
+    }
+}
+
+/// Shortens `force` to at most `max` long, keeping its direction.
+pub fn cap_length(force: Point<f64>, max: f64) -> Point<f64> {
+    let length = length(force);
+    if length > max {
+        force * (max / length)
+    } else {
+        force
+    }
+}
+
+fn length(force: Point<f64>) -> f64 {
+    (force.x().powi(2) + force.y().powi(2)).sqrt()
+}
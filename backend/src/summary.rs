@@ -0,0 +1,101 @@
+use geo::{Distance, Euclidean, Point};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{BoundaryStats, Km, KmPerHour, Simulation};
+
+/// A shark within this distance of a goal counts as visiting it.
+pub const VISIT_RADIUS: Km = Km(300.0);
+/// Goals listed in `WorldSummary::top_goals`
+pub const TOP_GOALS: usize = 5;
+
+/// A few numbers describing the whole world, cheap to poll from a dashboard.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct WorldSummary {
+    pub tick: u64,
+    pub sharks: usize,
+    /// Goals with at least one shark visiting
+    pub active_hotspots: usize,
+    /// Most visited goals, busiest first
+    pub top_goals: Vec<GoalVisitors>,
+    pub avg_speed_kmh: f64,
+    /// Entries and exits per edge, only with an open boundary
+    pub boundary: Option<BoundaryStats>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GoalVisitors {
+    #[schema(value_type = PointSchema)]
+    pub position: Point<f64>,
+    /// Label of the goal, left out when it has none
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub visitors: usize,
+}
+
+// mirrors how `geo::Point` serializes, for the OpenAPI document
+/// A position on the map
+#[derive(ToSchema)]
+#[schema(as = Point)]
+#[allow(dead_code)] // only describes the JSON shape
+pub struct PointSchema {
+    /// Longitude in degrees
+    x: f64,
+    /// Latitude in degrees
+    y: f64,
+}
+
+/// Sharks within `VISIT_RADIUS` of each goal, in the order of `goals`.
+pub fn goal_visitors(simulation: &Simulation) -> Vec<usize> {
+    let visit_radius = VISIT_RADIUS.to_degrees();
+    simulation
+        .goals
+        .iter()
+        .map(|goal| {
+            simulation
+                .sharks
+                .iter()
+                .filter(|shark| Euclidean.distance(shark.position, goal.position) < visit_radius)
+                .count()
+        })
+        .collect()
+}
+
+impl WorldSummary {
+    pub fn new(simulation: &Simulation) -> Self {
+        let mut goals: Vec<GoalVisitors> = simulation
+            .goals
+            .iter()
+            .zip(goal_visitors(simulation))
+            .map(|(goal, visitors)| GoalVisitors {
+                position: goal.position,
+                name: goal.name.clone(),
+                visitors,
+            })
+            .collect();
+        let active_hotspots = goals.iter().filter(|goal| goal.visitors > 0).count();
+        goals.retain(|goal| goal.visitors > 0);
+        goals.sort_by_key(|goal| std::cmp::Reverse(goal.visitors));
+        goals.truncate(TOP_GOALS);
+
+        let avg_speed = if simulation.sharks.is_empty() {
+            0.0
+        } else {
+            simulation
+                .sharks
+                .iter()
+                .map(|shark| shark.speed)
+                .sum::<f64>()
+                / simulation.sharks.len() as f64
+        };
+
+        Self {
+            tick: simulation.tick,
+            sharks: simulation.sharks.len(),
+            active_hotspots,
+            top_goals: goals,
+            avg_speed_kmh: KmPerHour::from_degrees_per_sec(avg_speed).0,
+            boundary: simulation.boundary_stats,
+        }
+    }
+}
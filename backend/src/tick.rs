@@ -6,7 +6,128 @@
 //     time::Instant,
 // };
 
-pub const TPS: u64 = 10;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+/// Ticks per second unless `--tps` says otherwise
+pub const DEFAULT_TPS: u64 = 10;
+
+static TPS: AtomicU64 = AtomicU64::new(DEFAULT_TPS);
+/// 0 to broadcast every tick
+static BROADCAST_RATE: AtomicU64 = AtomicU64::new(0);
+
+/// Physics ticks per second.
+pub fn tps() -> u64 {
+    TPS.load(Ordering::Relaxed)
+}
+
+/// Frames sent to clients per second, at most one per tick.
+pub fn broadcast_rate() -> u64 {
+    match BROADCAST_RATE.load(Ordering::Relaxed) {
+        0 => tps(),
+        rate => rate.min(tps()),
+    }
+}
+
+/// Sets the rates, once on startup before the physics runs. Without a
+/// `broadcast_rate` every tick is broadcast.
+pub fn set_rates(tps: u64, broadcast_rate: Option<u64>) {
+    TPS.store(tps, Ordering::Relaxed);
+    BROADCAST_RATE.store(broadcast_rate.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Ticks run back to back to make up for slow ones before the scheduler
+/// gives up on them
+pub const MAX_CATCH_UP_TICKS: u32 = 5;
+/// How long the actual tick rate is measured over
+const MEASURE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Paces a loop at `tps` and picks the ticks whose frames go out at
+/// `broadcast_rate`, e.g. every third one simulating at 30 Hz and
+/// broadcasting at 10 Hz. Ticks are due at fixed times rather than a fixed
+/// sleep after each, so the rate holds however long they take: after a slow
+/// tick the next ones run without sleeping until they are back on schedule.
+#[derive(Debug)]
+pub struct Scheduler {
+    tick_interval: Duration,
+    next_tick: Instant,
+    /// Broadcasts per tick
+    broadcast_share: f64,
+    /// Adds up `broadcast_share` every tick, a frame goes out on reaching 1
+    broadcast_credit: f64,
+    window_started: Instant,
+    window_ticks: u32,
+    measured_tps: f64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            tick_interval: Duration::from_secs(1) / tps() as u32,
+            next_tick: Instant::now(),
+            broadcast_share: broadcast_rate() as f64 / tps() as f64,
+            // so the first tick is broadcast
+            broadcast_credit: 1.0,
+            window_started: Instant::now(),
+            window_ticks: 0,
+            measured_tps: tps() as f64,
+        }
+    }
+
+    /// Ticks actually run per second, over the last `MEASURE_WINDOW`.
+    pub fn measured_tps(&self) -> f64 {
+        self.measured_tps
+    }
+
+    /// Wall time a tick may take.
+    pub fn tick_budget(&self) -> Duration {
+        self.tick_interval
+    }
+
+    /// Whether the frame of this tick is broadcast, asked once per tick.
+    pub fn broadcast_due(&mut self) -> bool {
+        let due = self.broadcast_credit >= 1.0 - 1e-9;
+        if due {
+            self.broadcast_credit -= 1.0;
+        }
+        self.broadcast_credit += self.broadcast_share;
+        due
+    }
+
+    /// Sleeps until the next tick is due. More than `MAX_CATCH_UP_TICKS`
+    /// behind, it starts over from now instead of rushing through the missed
+    /// ones, and the simulation falls behind the wall clock.
+    pub fn wait_for_next_tick(&mut self) {
+        let now = Instant::now();
+        self.window_ticks += 1;
+        let window = now - self.window_started;
+        if window >= MEASURE_WINDOW {
+            self.measured_tps = self.window_ticks as f64 / window.as_secs_f64();
+            self.window_started = now;
+            self.window_ticks = 0;
+        }
+
+        self.next_tick += self.tick_interval;
+        if self.next_tick + self.tick_interval * MAX_CATCH_UP_TICKS < now {
+            self.next_tick = now;
+        }
+        std::thread::sleep(self.next_tick.saturating_duration_since(now));
+    }
+}
+
+lazy_static! {
+    /// Zero of the server clock, fixed the first time it is read
+    static ref SERVER_START: Instant = Instant::now();
+}
+
+/// Milliseconds on the server's monotonic clock. Frames are stamped with it, and
+/// clients line their own clock up with it through the `time_sync` command.
+pub fn server_time_ms() -> f64 {
+    SERVER_START.elapsed().as_secs_f64() * 1000.0
+}
+
 // lazy_static! {
 //     pub static ref LAST_TRIGGER: RwLock<Option<Instant>> = RwLock::new(None);
 // }
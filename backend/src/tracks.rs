@@ -0,0 +1,118 @@
+use geo::{Bearing, Distance, Haversine, InterpolatePoint, LineString, Point, SimplifyIdx, coord};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{Km, Simulation};
+
+/// How much of a track to keep, e.g. `{"min_interval_secs": 1.0, "tolerance_km": 10.0}`.
+/// Points closer in time than `min_interval_secs` are dropped first, then
+/// Douglas-Peucker removes those within `tolerance_km` of the simplified line.
+/// The first and last point always stay.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackDecimation {
+    pub min_interval_secs: f64,
+    pub tolerance_km: f64,
+}
+
+impl Default for TrackDecimation {
+    fn default() -> Self {
+        Self {
+            min_interval_secs: 1.0,
+            tolerance_km: 10.0,
+        }
+    }
+}
+
+/// Where one shark went, as `[server_time_ms, lon, lat]` points oldest first.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Track {
+    pub id: u64,
+    #[schema(value_type = Vec<[f64; 3]>)]
+    pub points: Vec<(f64, f64, f64)>,
+    /// True bearing in degrees clockwise from north the shark heads in at
+    /// each point, only after `follow_great_circles`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearings_deg: Option<Vec<f64>>,
+}
+
+impl Track {
+    /// Adds points along the great circle between consecutive ones so no
+    /// segment is longer than `step_km`, with times in between, and fills
+    /// `bearings_deg`. Straight lon/lat segments drawn by a GIS are then
+    /// close to the path actually taken, even across an ocean.
+    pub fn follow_great_circles(&mut self, step_km: f64) {
+        let step_m = step_km.max(1.0) * 1000.0;
+        let mut points = Vec::with_capacity(self.points.len());
+        let mut bearings = Vec::with_capacity(self.points.len());
+        for pair in self.points.windows(2) {
+            let ((t0, lon0, lat0), (t1, lon1, lat1)) = (pair[0], pair[1]);
+            let (from, to) = (Point::new(lon0, lat0), Point::new(lon1, lat1));
+            let pieces = (Haversine.distance(from, to) / step_m).ceil().max(1.0) as usize;
+            for piece in 0..pieces {
+                let ratio = piece as f64 / pieces as f64;
+                let at = Haversine.point_at_ratio_between(from, to, ratio);
+                points.push((t0 + (t1 - t0) * ratio, at.x(), at.y()));
+                bearings.push(Haversine.bearing(at, to));
+            }
+        }
+        if let Some(&last) = self.points.last() {
+            points.push(last);
+            // keeps heading the way it arrived
+            bearings.push(bearings.last().copied().unwrap_or(0.0));
+        }
+        self.points = points;
+        self.bearings_deg = Some(bearings);
+    }
+}
+
+impl TrackDecimation {
+    pub fn apply(&self, track: &mut Track) {
+        let Some(&last) = track.points.last() else {
+            return;
+        };
+        let min_interval_ms = self.min_interval_secs * 1000.0;
+        let mut thinned: Vec<(f64, f64, f64)> = Vec::with_capacity(track.points.len());
+        for &point in &track.points {
+            match thinned.last() {
+                Some(kept) if point.0 - kept.0 < min_interval_ms => {}
+                _ => thinned.push(point),
+            }
+        }
+        if thinned.last() != Some(&last) {
+            thinned.push(last);
+        }
+
+        let line: LineString<f64> = thinned
+            .iter()
+            .map(|&(_, lon, lat)| coord! { x: lon, y: lat })
+            .collect();
+        let kept = line.simplify_idx(Km(self.tolerance_km).to_degrees());
+        track.points = kept.into_iter().map(|i| thinned[i]).collect();
+    }
+}
+
+/// Splits consecutive frames into a track per shark, decimated.
+pub fn decimated_tracks(frames: &[Arc<Simulation>], decimation: &TrackDecimation) -> Vec<Track> {
+    let mut tracks: BTreeMap<u64, Track> = BTreeMap::new();
+    for frame in frames {
+        for shark in &frame.sharks {
+            tracks
+                .entry(shark.id)
+                .or_insert_with(|| Track {
+                    id: shark.id,
+                    points: Vec::new(),
+                    bearings_deg: None,
+                })
+                .points
+                .push((frame.server_time_ms, shark.position.x(), shark.position.y()));
+        }
+    }
+    let mut tracks: Vec<Track> = tracks.into_values().collect();
+    for track in &mut tracks {
+        decimation.apply(track);
+    }
+    tracks
+}
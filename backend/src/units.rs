@@ -0,0 +1,48 @@
+//! Physical units for simulation parameters.
+//!
+//! Internally the simulation works on a plate carrée map: positions and
+//! distances are in degrees, speeds in degrees per second and headings in
+//! radians. Steering strengths are unitless gains. Parameters are given in
+//! these newtypes instead so their values mean something, and are converted to
+//! degrees at the `Simulation` boundary.
+//!
+//! Distances use the length of one degree at the equator, so away from it a
+//! "km" spans less ground east-west than the name suggests. Simulated time runs
+//! at wall-clock speed, which is why shark speeds are far beyond anything real.
+
+use serde::{Deserialize, Serialize};
+
+/// Kilometres covered by one degree of latitude (or longitude at the equator).
+pub const KM_PER_DEGREE: f64 = 111.32;
+
+/// A distance on the map in kilometres.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Km(pub f64);
+
+impl Km {
+    pub fn from_degrees(degrees: f64) -> Self {
+        Self(degrees * KM_PER_DEGREE)
+    }
+
+    pub fn to_degrees(self) -> f64 {
+        self.0 / KM_PER_DEGREE
+    }
+}
+
+/// A speed in kilometres per hour.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct KmPerHour(pub f64);
+
+impl KmPerHour {
+    pub fn from_degrees_per_sec(degrees_per_sec: f64) -> Self {
+        Self(degrees_per_sec * KM_PER_DEGREE * 3600.0)
+    }
+
+    pub fn to_degrees_per_sec(self) -> f64 {
+        self.0 / KM_PER_DEGREE / 3600.0
+    }
+}
+
+/// An angular rate in radians per second.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct RadPerSec(pub f64);
@@ -0,0 +1,121 @@
+use geo::Point;
+use serde::{Deserialize, Serialize, Serializer};
+use std::time::{Duration, Instant};
+
+/// Limits on goals added by clients, e.g. `{"per_session": 5, "ttl_secs": 300}`
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct UserGoalLimits {
+    /// Goals one session may have at a time
+    pub per_session: usize,
+    /// Seconds until a goal disappears by itself
+    pub ttl_secs: f64,
+}
+
+impl Default for UserGoalLimits {
+    fn default() -> Self {
+        Self {
+            per_session: 5,
+            ttl_secs: 300.0,
+        }
+    }
+}
+
+/// Longest `ttl_secs` allowed, ten years, far enough off to never come and
+/// near enough for the expiry to be a valid `Instant`
+const MAX_TTL_SECS: f64 = 10.0 * 365.0 * 24.0 * 3600.0;
+
+impl UserGoalLimits {
+    /// Fails on a `ttl_secs` that isn't a number of seconds above 0 and at
+    /// most ten years, which goals couldn't expire after.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.ttl_secs > 0.0 && self.ttl_secs <= MAX_TTL_SECS) {
+            return Err(format!(
+                "user_goals.ttl_secs must be above 0 and at most {}, not {}",
+                MAX_TTL_SECS, self.ttl_secs
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A goal some client added, attracting sharks like the built-in ones until it
+/// expires or its owner removes it.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserGoal {
+    pub id: u64,
+    pub position: Point<f64>,
+    /// Session that added it, kept from other clients
+    #[serde(skip)]
+    pub owner: String,
+    #[serde(skip)]
+    pub expires_at: Instant,
+}
+
+/// Goals added by clients, with a quota per session and an expiry. Frames
+/// list them without their owners.
+#[derive(Debug, Clone, Default)]
+pub struct UserGoals {
+    goals: Vec<UserGoal>,
+    next_id: u64,
+    limits: UserGoalLimits,
+}
+
+impl Serialize for UserGoals {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.goals.serialize(serializer)
+    }
+}
+
+impl UserGoals {
+    pub fn new(limits: UserGoalLimits) -> Self {
+        Self {
+            goals: Vec::new(),
+            next_id: 0,
+            limits,
+        }
+    }
+
+    /// Adds a goal for `owner`, unless they are at their quota. Returns its id.
+    pub fn add(&mut self, owner: &str, position: Point<f64>) -> Result<u64, String> {
+        if self.owned_by(owner).count() >= self.limits.per_session {
+            return Err(format!(
+                "at most {} goals per session, remove one first",
+                self.limits.per_session
+            ));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.goals.push(UserGoal {
+            id,
+            position,
+            owner: owner.to_string(),
+            expires_at: Instant::now() + Duration::from_secs_f64(self.limits.ttl_secs),
+        });
+        Ok(id)
+    }
+
+    /// Removes goal `id` if `owner` added it.
+    pub fn remove(&mut self, owner: &str, id: u64) -> Result<(), String> {
+        let index = self
+            .goals
+            .iter()
+            .position(|goal| goal.id == id && goal.owner == owner)
+            .ok_or_else(|| format!("you have no goal {}", id))?;
+        self.goals.remove(index);
+        Ok(())
+    }
+
+    pub fn owned_by<'a>(&'a self, owner: &'a str) -> impl Iterator<Item = &'a UserGoal> {
+        self.goals.iter().filter(move |goal| goal.owner == owner)
+    }
+
+    /// Drops every goal whose time is up.
+    pub fn expire(&mut self, now: Instant) {
+        self.goals.retain(|goal| goal.expires_at > now);
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point<f64>> + '_ {
+        self.goals.iter().map(|goal| goal.position)
+    }
+}
@@ -0,0 +1,157 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+/// Characters of a view code, without ones easily mixed up when read aloud
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 6;
+
+/// Views kept at most, and at most per session
+const MAX_VIEWS: usize = 10_000;
+const MAX_VIEWS_PER_SESSION: usize = 50;
+/// Longest view name, and longest shark field name in `fields`
+const MAX_NAME_CHARS: usize = 100;
+const MAX_FIELDS: usize = 32;
+
+/// How a client was looking at the map, saved so a presenter can prepare
+/// shots ahead of time. The server only keeps it, clients apply it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SavedView {
+    pub name: String,
+    /// `[min_lon, min_lat, max_lon, max_lat]` shown on screen
+    pub bbox: Option<[f64; 4]>,
+    /// Id of the shark the camera follows
+    pub follow_shark: Option<u64>,
+    /// Shark fields shown, as in the `fields` connect parameter
+    pub fields: Option<Vec<String>>,
+    /// Frames per second the client renders
+    pub rate_hz: Option<f64>,
+}
+
+impl SavedView {
+    /// Fails on a name or field list too long to be worth keeping.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.chars().count() > MAX_NAME_CHARS {
+            return Err(format!(
+                "view names are at most {} characters",
+                MAX_NAME_CHARS
+            ));
+        }
+        let fields = self.fields.as_deref().unwrap_or_default();
+        if fields.len() > MAX_FIELDS {
+            return Err(format!("views show at most {} fields", MAX_FIELDS));
+        }
+        if fields
+            .iter()
+            .any(|field| field.chars().count() > MAX_NAME_CHARS)
+        {
+            return Err(format!(
+                "field names are at most {} characters",
+                MAX_NAME_CHARS
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredView {
+    view: SavedView,
+    /// Session that saved it, if any
+    owner: Option<String>,
+}
+
+/// Saved views by short code, written to a JSON file on every change so they
+/// survive restarts.
+#[derive(Debug)]
+pub struct ViewStore {
+    path: PathBuf,
+    views: Mutex<HashMap<String, StoredView>>,
+    /// Held while writing the file, so writes go one at a time
+    writing: Mutex<()>,
+}
+
+impl ViewStore {
+    /// Reads the views saved at `path`, starting empty when there is no file yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Box<dyn Error>> {
+        let path = path.into();
+        let views = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            path,
+            views: Mutex::new(views),
+            writing: Mutex::new(()),
+        })
+    }
+
+    /// Stores `view` under a fresh code and returns the code, unless the store
+    /// or `owner` has as many views as it may keep. Call `write` to keep it.
+    pub fn save(&self, view: SavedView, owner: Option<String>) -> Result<String, String> {
+        view.validate()?;
+        let mut views = self.views.lock().unwrap();
+        if views.len() >= MAX_VIEWS {
+            return Err(format!("no room for more than {} views", MAX_VIEWS));
+        }
+        if let Some(owner) = &owner {
+            let owned = views
+                .values()
+                .filter(|stored| stored.owner.as_ref() == Some(owner))
+                .count();
+            if owned >= MAX_VIEWS_PER_SESSION {
+                return Err(format!(
+                    "a session keeps at most {} views",
+                    MAX_VIEWS_PER_SESSION
+                ));
+            }
+        }
+        let mut rng = rand::rng();
+        let code = loop {
+            let code: String = (0..CODE_LENGTH)
+                .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+                .collect();
+            if !views.contains_key(&code) {
+                break code;
+            }
+        };
+        views.insert(code.clone(), StoredView { view, owner });
+        Ok(code)
+    }
+
+    pub fn get(&self, code: &str) -> Option<SavedView> {
+        self.views
+            .lock()
+            .unwrap()
+            .get(&code.to_ascii_uppercase())
+            .map(|stored| stored.view.clone())
+    }
+
+    /// Codes and views saved by `owner`.
+    pub fn owned_by(&self, owner: &str) -> Vec<(String, SavedView)> {
+        self.views
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, stored)| stored.owner.as_deref() == Some(owner))
+            .map(|(code, stored)| (code.clone(), stored.view.clone()))
+            .collect()
+    }
+
+    /// Writes the views as they are now to the file, blocking. The views are
+    /// only locked to serialize them, and as writes go one at a time the last
+    /// one always has the latest views. Goes through a temporary file so a
+    /// crash never leaves a half-written store.
+    pub fn write(&self) -> std::io::Result<()> {
+        let _writing = self.writing.lock().unwrap();
+        let views_json = serde_json::to_string_pretty(&*self.views.lock().unwrap())?;
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, views_json)?;
+        std::fs::rename(&tmp_path, &self.path)
+    }
+}
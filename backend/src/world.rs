@@ -0,0 +1,99 @@
+use geo::{LineString, Polygon, Rect};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::f64::consts::PI;
+use utoipa::ToSchema;
+
+use crate::{DataDir, generate_archipelago, load_embedded_land_polygons, load_land};
+
+/// Most islands an `Archipelago` may have
+pub const MAX_ISLANDS: usize = 1000;
+
+/// Which land the simulation runs on. The procedural presets need no data files,
+/// which keeps behaviour checks and benchmarks independent of the shapefile.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WorldPreset {
+    /// Natural Earth coastline, or the embedded coarse one if it can't be loaded
+    #[default]
+    NaturalEarth,
+    /// No land at all
+    EmptyOcean,
+    /// One circular island in the middle of the map
+    Island,
+    /// Two landmasses separated by an east-west channel along the equator
+    Channel,
+    /// `islands` random blobs covering `coverage` (0..1) of the map, for stress tests
+    Archipelago {
+        islands: usize,
+        coverage: f64,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl WorldPreset {
+    /// Refuses settings out of range, such as `coverage` outside (0, 1).
+    pub fn validate(&self) -> Result<(), String> {
+        if let WorldPreset::Archipelago {
+            islands, coverage, ..
+        } = self
+        {
+            if *islands > MAX_ISLANDS {
+                return Err(format!("at most {} islands", MAX_ISLANDS));
+            }
+            if !(*coverage > 0.0 && *coverage < 1.0) {
+                return Err("coverage has to be above 0 and below 1".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// `shapefile` is where `NaturalEarth` is read from, relative to `data_dir`,
+    /// a shapefile or GeoJSON, see `load_land`.
+    pub fn land_polygons(&self, data_dir: &DataDir, shapefile: &str) -> Vec<Polygon<f64>> {
+        match self {
+            WorldPreset::NaturalEarth => data_dir
+                .resolve(shapefile)
+                .map_err(|err| err.into())
+                .and_then(load_land)
+                .unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    eprintln!(
+                        "WARNING: high-resolution land data wasn't found, using the embedded coarse coastline"
+                    );
+                    load_embedded_land_polygons()
+                }),
+            WorldPreset::EmptyOcean => vec![],
+            WorldPreset::Island => vec![circle_polygon(0.0, 0.0, 20.0, 64)],
+            WorldPreset::Channel => vec![
+                Rect::new((-90.0, 5.0), (90.0, 60.0)).to_polygon(),
+                Rect::new((-90.0, -60.0), (90.0, -5.0)).to_polygon(),
+            ],
+            WorldPreset::Archipelago {
+                islands,
+                coverage,
+                seed,
+            } => generate_archipelago(
+                &mut StdRng::seed_from_u64(*seed),
+                *islands,
+                *coverage,
+                Rect::new((-180.0, -85.0), (180.0, 85.0)),
+            ),
+        }
+    }
+}
+
+fn circle_polygon(center_x: f64, center_y: f64, radius: f64, segments: usize) -> Polygon<f64> {
+    let ring = (0..=segments)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / segments as f64;
+            (
+                center_x + radius * angle.cos(),
+                center_y + radius * angle.sin(),
+            )
+        })
+        .collect::<Vec<_>>();
+    Polygon::new(LineString::from(ring), vec![])
+}
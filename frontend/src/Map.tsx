@@ -9,11 +9,27 @@ import {
 import "maptalks-gl/dist/maptalks-gl.css";
 
 interface SharkData {
+  id: number;
   position: { x: number; y: number };
   rotation_rad: number;
+  reported_rotation_rad: number;
   speed: number;
 }
 
+interface SharkMove {
+  id: number;
+  position: { x: number; y: number };
+  reported_rotation_rad: number;
+}
+
+// Changes since the previous message, see `?delta=1` on the backend
+interface SharkDelta {
+  tick: number;
+  added: SharkData[];
+  moved: SharkMove[];
+  removed: number[];
+}
+
 interface MapGLProps {
   center?: Coordinate;
   zoom?: number;
@@ -72,8 +88,40 @@ const MapGL: React.FC<MapGLProps> = ({
     // Listen to zoom changes
     mapRef.current.on("zoomend", updateAllMarkerSizes);
 
-    // WebSocket connection
-    const ws = new WebSocket("ws://localhost:25555");
+    // Create or move the marker of one shark
+    const placeMarker = (shark: SharkMove, size: number) => {
+      const { id, position, reported_rotation_rad: rotation_rad } = shark;
+      const { x, y } = position;
+
+      if (sharkMarkersRef.current[id]) {
+        // Update existing marker
+        sharkMarkersRef.current[id].setCoordinates([x, y]);
+        sharkMarkersRef.current[id].updateSymbol({
+          markerRotation: (rotation_rad * 180) / Math.PI,
+        });
+      } else {
+        // Create new marker with current zoom size
+        const marker = new Marker([x, y], {
+          symbol: {
+            markerFile:
+              "https://cdn-icons-png.flaticon.com/512/9339/9339269.png",
+            markerWidth: size,
+            markerHeight: size,
+            markerRotation: (rotation_rad * 180) / Math.PI,
+          },
+        });
+        sharkMarkersRef.current[id] = marker;
+        vectorLayerRef.current?.addGeometry(marker);
+      }
+    };
+
+    const removeMarker = (id: number) => {
+      sharkMarkersRef.current[id]?.remove();
+      delete sharkMarkersRef.current[id];
+    };
+
+    // WebSocket connection, full frames now and then with deltas in between
+    const ws = new WebSocket("ws://localhost:25555/?delta=1");
 
     ws.onopen = () => {
       console.log("✅ WebSocket connected to ws://localhost:25555");
@@ -81,35 +129,33 @@ const MapGL: React.FC<MapGLProps> = ({
 
     ws.onmessage = (event) => {
       try {
-        const data = JSON.parse(event.data) as { sharks: SharkData[] };
-        if (!data.sharks || !Array.isArray(data.sharks)) return;
+        const data = JSON.parse(event.data) as {
+          sharks?: SharkData[];
+          delta?: SharkDelta;
+        };
 
         const currentZoom = mapRef.current?.getZoom() || zoom;
         const size = getMarkerSize(currentZoom);
 
-        data.sharks.forEach((shark, idx) => {
-          const { position, rotation_rad } = shark;
-          const { x, y } = position;
-
-          if (sharkMarkersRef.current[idx]) {
-            // Update existing marker
-            sharkMarkersRef.current[idx].setCoordinates([x, y]);
-            sharkMarkersRef.current[idx].updateSymbol({
-              markerRotation: (rotation_rad * 180) / Math.PI,
-            });
-          } else {
-            // Create new marker with current zoom size
-            const marker = new Marker([x, y], {
-              symbol: {
-                markerFile:
-                  "https://cdn-icons-png.flaticon.com/512/9339/9339269.png",
-                markerWidth: size,
-                markerHeight: size,
-                markerRotation: (rotation_rad * 180) / Math.PI,
-              },
-            });
-            sharkMarkersRef.current[idx] = marker;
-            vectorLayerRef.current?.addGeometry(marker);
+        if (data.delta) {
+          data.delta.added.forEach((shark) => placeMarker(shark, size));
+          data.delta.moved.forEach((shark) => placeMarker(shark, size));
+          data.delta.removed.forEach(removeMarker);
+          return;
+        }
+        if (!data.sharks || !Array.isArray(data.sharks)) return;
+
+        const seen = new Set<number>();
+        data.sharks.forEach((shark) => {
+          placeMarker(shark, size);
+          seen.add(shark.id);
+        });
+
+        // Remove markers of sharks that are gone
+        Object.keys(sharkMarkersRef.current).forEach((key) => {
+          const id = Number(key);
+          if (!seen.has(id)) {
+            removeMarker(id);
           }
         });
       } catch (err) {